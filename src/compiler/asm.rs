@@ -1,3 +1,5 @@
+use crate::compiler::symbols::SymbolStaticInitial;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Program {
     pub items: Vec<TopLevelItem>,
@@ -7,12 +9,16 @@ pub struct Program {
 pub enum TopLevelItem {
     FunctionDefinition(FunctionDefinition),
     StaticVariable(StaticVariable),
+    StaticConstant(StaticConstant),
+    JumpTable(JumpTable),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FunctionDefinition {
     pub function: Function,
     pub global: bool,
+    /// Whether the function sets up `%rbp`, which only calls and stack operands need.
+    pub frame_pointer: bool,
     pub instructions: Vec<Instruction>,
 }
 
@@ -20,32 +26,96 @@ pub struct FunctionDefinition {
 pub struct StaticVariable {
     pub variable: Variable,
     pub global: bool,
-    pub initial: i64,
+    pub ty: AssemblyType,
+    pub initial: Vec<SymbolStaticInitial>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaticConstant {
+    pub variable: Variable,
+    pub alignment: i64,
+    pub initial: SymbolStaticInitial,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JumpTable {
+    pub label: Label,
+    pub targets: Vec<Label>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Instruction {
     Mov {
+        ty: AssemblyType,
+        src: Operand,
+        dst: Operand,
+    },
+    Movsx {
+        src_ty: AssemblyType,
+        dst_ty: AssemblyType,
+        src: Operand,
+        dst: Operand,
+    },
+    Lea {
+        src: Operand,
+        dst: Operand,
+    },
+    MovZeroExtend {
+        src_ty: AssemblyType,
+        dst_ty: AssemblyType,
         src: Operand,
         dst: Operand,
     },
     Unary {
         op: UnaryOperator,
+        ty: AssemblyType,
         dst: Operand,
     },
     Binary {
         op: BinaryOperator,
+        ty: AssemblyType,
+        src: Operand,
+        dst: Operand,
+    },
+    /// Double to signed integer, truncating
+    Cvttsd2si {
+        dst_ty: AssemblyType,
+        src: Operand,
+        dst: Operand,
+    },
+    /// Signed integer to double
+    Cvtsi2sd {
+        src_ty: AssemblyType,
         src: Operand,
         dst: Operand,
     },
     Cmp {
+        ty: AssemblyType,
         src: Operand,
         dst: Operand,
     },
-    Idiv(Operand),
-    Cdq,
-    Sal(Operand),
-    Sar(Operand),
+    Idiv {
+        ty: AssemblyType,
+        operand: Operand,
+    },
+    /// Unsigned division of `%dx:%ax`
+    Div {
+        ty: AssemblyType,
+        operand: Operand,
+    },
+    Cdq(AssemblyType),
+    Sal {
+        ty: AssemblyType,
+        dst: Operand,
+    },
+    Sar {
+        ty: AssemblyType,
+        dst: Operand,
+    },
+    Shr {
+        ty: AssemblyType,
+        dst: Operand,
+    },
     Jmp {
         target: Label,
     },
@@ -53,6 +123,10 @@ pub enum Instruction {
         cc: ConditionCode,
         target: Label,
     },
+    JmpTable {
+        table: Label,
+        index: Reg,
+    },
     SetCC {
         cc: ConditionCode,
         dst: Operand,
@@ -61,8 +135,51 @@ pub enum Instruction {
     AllocateStack(u64),
     DeallocateStack(u64),
     Push(Operand),
-    Call(Function),
+    Pop(Reg),
+    /// `external` functions are defined in another file, and called through the PLT on Linux.
+    Call {
+        function: Function,
+        external: bool,
+    },
+    /// A call to the address in the operand.
+    IndirectCall(Operand),
     Ret,
+    /// Where the code for a line of the source starts, for debug info.
+    SourceLine(usize),
+    /// Describes the instructions that follow, for reading the emitted assembly.
+    Comment(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AssemblyType {
+    /// 1 byte
+    Byte,
+    /// 4 bytes
+    Longword,
+    /// 8 bytes
+    Quadword,
+    /// 8 byte floating point, lives in SSE registers
+    Double,
+    /// Arrays, which are only ever accessed through their address
+    ByteArray { size: i64, alignment: i64 },
+}
+
+impl AssemblyType {
+    pub fn size(&self) -> i64 {
+        match self {
+            AssemblyType::Byte => 1,
+            AssemblyType::Longword => 4,
+            AssemblyType::Quadword | AssemblyType::Double => 8,
+            AssemblyType::ByteArray { size, .. } => *size,
+        }
+    }
+
+    pub fn alignment(&self) -> i64 {
+        match self {
+            AssemblyType::ByteArray { alignment, .. } => *alignment,
+            _ => self.size(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -79,6 +196,8 @@ pub enum BinaryOperator {
     And,
     Or,
     Xor,
+    /// Only for doubles, integers use `Idiv`/`Div`
+    DivDouble,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -87,6 +206,14 @@ pub enum Operand {
     Reg(Reg),
     Pseudo(String),
     Stack(i64),
+    /// An offset from the address in a register.
+    Memory(Reg, i64),
+    /// `base + index * scale`
+    Indexed {
+        base: Reg,
+        index: Reg,
+        scale: i64,
+    },
     Data(String),
 }
 
@@ -109,11 +236,41 @@ pub enum ConditionCode {
     L,
     /// Less or Equal
     LE,
+    /// Above (unsigned greater)
+    A,
+    /// Above or Equal (unsigned greater or equal)
+    AE,
+    /// Below (unsigned less)
+    B,
+    /// Below or Equal (unsigned less or equal)
+    BE,
+    /// Parity, which comparing doubles sets when either of them is NaN
+    P,
+}
+
+impl ConditionCode {
+    /// The condition that holds with the compared operands the other way around.
+    pub fn swapped(self) -> Self {
+        match self {
+            ConditionCode::E => ConditionCode::E,
+            ConditionCode::NE => ConditionCode::NE,
+            ConditionCode::G => ConditionCode::L,
+            ConditionCode::GE => ConditionCode::LE,
+            ConditionCode::L => ConditionCode::G,
+            ConditionCode::LE => ConditionCode::GE,
+            ConditionCode::A => ConditionCode::B,
+            ConditionCode::AE => ConditionCode::BE,
+            ConditionCode::B => ConditionCode::A,
+            ConditionCode::BE => ConditionCode::AE,
+            ConditionCode::P => ConditionCode::P,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Reg {
     AX,
+    BX,
     CX,
     DX,
     DI,
@@ -122,6 +279,20 @@ pub enum Reg {
     R9,
     R10,
     R11,
+    R12,
+    R13,
+    R14,
+    R15,
+    XMM0,
+    XMM1,
+    XMM2,
+    XMM3,
+    XMM4,
+    XMM5,
+    XMM6,
+    XMM7,
+    XMM14,
+    XMM15,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -1,33 +1,113 @@
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+};
+
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Type {
     Int,
     Long,
+    UInt,
+    ULong,
+    Double,
+    Bool,
+    Char,
+    SChar,
+    UChar,
+    Void,
+    Pointer(Box<Type>),
+    Array {
+        element: Box<Type>,
+        size: usize,
+    },
     Function {
         return_type: Box<Type>,
         parameters: Vec<Type>,
     },
 }
 
+impl Type {
+    pub fn is_arithmetic(&self) -> bool {
+        matches!(
+            self,
+            Type::Int
+                | Type::Long
+                | Type::UInt
+                | Type::ULong
+                | Type::Double
+                | Type::Bool
+                | Type::Char
+                | Type::SChar
+                | Type::UChar
+        )
+    }
+
+    pub fn is_character(&self) -> bool {
+        matches!(self, Type::Char | Type::SChar | Type::UChar)
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.is_arithmetic() && *self != Type::Double
+    }
+
+    pub fn is_scalar(&self) -> bool {
+        self.is_arithmetic() || matches!(self, Type::Pointer(_))
+    }
+
+    /// Whether values of the type compare and divide as signed integers.
+    pub fn is_signed(&self) -> bool {
+        matches!(self, Type::Int | Type::Long | Type::Char | Type::SChar)
+    }
+
+    /// Size in bytes, if the type is an object type.
+    pub fn size(&self) -> Option<i64> {
+        match self {
+            Type::Int | Type::UInt => Some(4),
+            Type::Long | Type::ULong | Type::Double => Some(8),
+            Type::Bool | Type::Char | Type::SChar | Type::UChar => Some(1),
+            Type::Pointer(_) => Some(8),
+            Type::Array { element, size } => Some(element.size()? * *size as i64),
+            Type::Void | Type::Function { .. } => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Program {
     pub declarations: Vec<Declaration>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Declaration {
     Variable(VariableDeclaration),
     Function(FunctionDeclaration),
+    Enum(EnumDeclaration),
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VariableDeclaration {
     pub variable: Variable,
-    pub initializer: Option<Expression>,
+    pub initializer: Option<Initializer>,
     pub ty: Type,
     pub storage_class: Option<StorageClass>,
+    /// Whether the variable itself is `const`, rather than what it points to.
+    pub is_const: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Initializer {
+    Single(Expression),
+    /// e.g. `{1, 2, 3}`
+    Compound(Vec<Initializer>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FunctionDeclaration {
     pub function: Function,
     pub parameters: Vec<Variable>,
@@ -36,26 +116,51 @@ pub struct FunctionDeclaration {
     pub storage_class: Option<StorageClass>,
 }
 
+/// e.g. `enum Color { RED, GREEN = 5, BLUE };`, whose type is just `int`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnumDeclaration {
+    pub tag: Option<String>,
+    /// `None` for a use of a tag defined earlier, e.g. `enum Color c;`.
+    pub enumerators: Option<Vec<Enumerator>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Enumerator {
+    pub name: String,
+    /// `None` if the value follows on from the previous enumerator, until identifier resolution
+    /// fills in every value.
+    pub value: Option<Expression>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StorageClass {
     Static,
     Extern,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Block {
     pub items: Vec<BlockItem>,
+    /// The source line each item starts on, if known.
+    pub lines: Vec<Option<usize>>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BlockItem {
     Statement(Statement),
     Declaration(Declaration),
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Statement {
-    Return(Expression),
+    /// `None` for a bare `return;` in a void function.
+    Return(Option<Expression>),
     Expression(Expression),
     If {
         condition: Expression,
@@ -103,12 +208,14 @@ pub enum Statement {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ForInitializer {
-    VariableDeclaration(VariableDeclaration),
+    VariableDeclarations(Vec<VariableDeclaration>),
     Expression(Expression),
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Expression {
     Constant {
         c: Constant,
@@ -151,6 +258,38 @@ pub enum Expression {
         arguments: Vec<Expression>,
         ty: Option<Type>,
     },
+    /// A call through a function pointer, e.g. `(*fp)(1)` or `table[i](1)`.
+    IndirectCall {
+        callee: Box<Expression>,
+        arguments: Vec<Expression>,
+        ty: Option<Type>,
+    },
+    Comma {
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+        ty: Option<Type>,
+    },
+    SizeOf {
+        expr: Box<Expression>,
+        ty: Option<Type>,
+    },
+    SizeOfType {
+        target_ty: Type,
+        ty: Option<Type>,
+    },
+    AddrOf {
+        expr: Box<Expression>,
+        ty: Option<Type>,
+    },
+    Dereference {
+        expr: Box<Expression>,
+        ty: Option<Type>,
+    },
+    Subscript {
+        expr: Box<Expression>,
+        index: Box<Expression>,
+        ty: Option<Type>,
+    },
 }
 
 impl Expression {
@@ -164,12 +303,31 @@ impl Expression {
             Expression::Assignment { ty, .. } => ty.clone(),
             Expression::Conditional { ty, .. } => ty.clone(),
             Expression::FunctionCall { ty, .. } => ty.clone(),
+            Expression::IndirectCall { ty, .. } => ty.clone(),
+            Expression::Comma { ty, .. } => ty.clone(),
+            Expression::SizeOf { ty, .. } => ty.clone(),
+            Expression::SizeOfType { ty, .. } => ty.clone(),
+            Expression::AddrOf { ty, .. } => ty.clone(),
+            Expression::Dereference { ty, .. } => ty.clone(),
+            Expression::Subscript { ty, .. } => ty.clone(),
         }
     }
+
+    /// Whether the expression designates an object that can be assigned to.
+    pub fn is_lvalue(&self) -> bool {
+        matches!(
+            self,
+            Expression::Variable { .. }
+                | Expression::Dereference { .. }
+                | Expression::Subscript { .. }
+        )
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum UnaryOperator {
+    Plus,
     Complement,
     Negate,
     Not,
@@ -180,6 +338,7 @@ pub enum UnaryOperator {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BinaryOperator {
     Add,
     Subtract,
@@ -202,11 +361,13 @@ pub enum BinaryOperator {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Variable {
     pub identifier: String,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AssignmentOperator {
     Assign,
     AddAssign,
@@ -222,44 +383,489 @@ pub enum AssignmentOperator {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Label {
     pub identifier: String,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LoopLabel {
     pub identifier: String,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SwitchLabel {
     pub identifier: String,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LoopOrSwitchLabel {
     Loop(LoopLabel),
     Switch(SwitchLabel),
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Function {
     pub identifier: String,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SwitchCaseLabel {
     pub identifier: String,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SwitchCases {
     pub cases: Vec<(Constant, SwitchCaseLabel)>,
     pub default: Option<SwitchCaseLabel>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Constant {
     ConstantInt(i32),
     ConstantLong(i64),
+    ConstantUInt(u32),
+    ConstantULong(u64),
+    ConstantDouble(f64),
+}
+
+// literals are never NaN, so constants can be compared for case deduplication
+impl Eq for Constant {}
+
+impl Hash for Constant {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+
+        match self {
+            Constant::ConstantInt(n) => n.hash(state),
+            Constant::ConstantLong(n) => n.hash(state),
+            Constant::ConstantUInt(n) => n.hash(state),
+            Constant::ConstantULong(n) => n.hash(state),
+            Constant::ConstantDouble(d) => d.to_bits().hash(state),
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Long => write!(f, "long"),
+            Type::UInt => write!(f, "unsigned int"),
+            Type::ULong => write!(f, "unsigned long"),
+            Type::Double => write!(f, "double"),
+            Type::Bool => write!(f, "_Bool"),
+            Type::Char => write!(f, "char"),
+            Type::SChar => write!(f, "signed char"),
+            Type::UChar => write!(f, "unsigned char"),
+            Type::Void => write!(f, "void"),
+            Type::Pointer(referenced) => write!(f, "{referenced} *"),
+            Type::Array { element, size } => write!(f, "{element}[{size}]"),
+            Type::Function {
+                return_type,
+                parameters,
+            } => {
+                let parameters = parameters.iter().map(Type::to_string);
+                write!(
+                    f,
+                    "{return_type}({})",
+                    parameters.collect::<Vec<_>>().join(", ")
+                )
+            }
+        }
+    }
+}
+
+/// Prints the program as pseudo-C. Expressions are fully parenthesized, and annotated with their
+/// type once the type checker has run.
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, declaration) in self.declarations.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write_declaration(f, declaration, 0)?;
+        }
+
+        Ok(())
+    }
+}
+
+const INDENT: &str = "    ";
+
+fn write_declaration(
+    f: &mut fmt::Formatter<'_>,
+    declaration: &Declaration,
+    indent: usize,
+) -> fmt::Result {
+    write!(f, "{}", INDENT.repeat(indent))?;
+
+    match declaration {
+        Declaration::Variable(vd) => write_variable_declaration(f, vd),
+        Declaration::Enum(ed) => write_enum_declaration(f, ed),
+        Declaration::Function(fd) => {
+            write_storage_class(f, fd.storage_class)?;
+
+            let Type::Function {
+                return_type,
+                parameters,
+            } = &fd.ty
+            else {
+                return write!(f, "{} {};", fd.ty, fd.function.identifier);
+            };
+
+            let parameters = if parameters.is_empty() {
+                "void".to_string()
+            } else {
+                parameters
+                    .iter()
+                    .zip(&fd.parameters)
+                    .map(|(ty, parameter)| format!("{ty} {}", parameter.identifier))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            write!(f, "{return_type} {}({parameters})", fd.function.identifier)?;
+
+            match &fd.body {
+                Some(body) => {
+                    write!(f, " ")?;
+                    write_block(f, body, indent)
+                }
+                None => writeln!(f, ";"),
+            }
+        }
+    }
+}
+
+fn write_variable_declaration(f: &mut fmt::Formatter<'_>, vd: &VariableDeclaration) -> fmt::Result {
+    write_storage_class(f, vd.storage_class)?;
+    write!(f, "{} {}", vd.ty, vd.variable.identifier)?;
+
+    if let Some(initializer) = &vd.initializer {
+        write!(f, " = {initializer}")?;
+    }
+
+    writeln!(f, ";")
+}
+
+fn write_enum_declaration(f: &mut fmt::Formatter<'_>, ed: &EnumDeclaration) -> fmt::Result {
+    write!(f, "enum ")?;
+
+    if let Some(tag) = &ed.tag {
+        write!(f, "{tag} ")?;
+    }
+
+    let Some(enumerators) = &ed.enumerators else {
+        return writeln!(f, ";");
+    };
+
+    let enumerators = enumerators
+        .iter()
+        .map(|enumerator| match &enumerator.value {
+            Some(value) => format!("{} = {value}", enumerator.name),
+            None => enumerator.name.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    writeln!(f, "{{ {enumerators} }};")
+}
+
+fn write_storage_class(
+    f: &mut fmt::Formatter<'_>,
+    storage_class: Option<StorageClass>,
+) -> fmt::Result {
+    match storage_class {
+        Some(StorageClass::Static) => write!(f, "static "),
+        Some(StorageClass::Extern) => write!(f, "extern "),
+        None => Ok(()),
+    }
+}
+
+/// Writes the body of a statement like a loop after its header.
+fn write_body(f: &mut fmt::Formatter<'_>, body: &Statement, indent: usize) -> fmt::Result {
+    match body {
+        Statement::Compound(block) => {
+            write!(f, " ")?;
+            write_block(f, block, indent)
+        }
+        _ => {
+            writeln!(f)?;
+            write_statement(f, body, indent + 1)
+        }
+    }
+}
+
+fn write_block(f: &mut fmt::Formatter<'_>, block: &Block, indent: usize) -> fmt::Result {
+    writeln!(f, "{{")?;
+    for item in &block.items {
+        match item {
+            BlockItem::Statement(statement) => write_statement(f, statement, indent + 1)?,
+            BlockItem::Declaration(declaration) => write_declaration(f, declaration, indent + 1)?,
+        }
+    }
+    writeln!(f, "{}}}", INDENT.repeat(indent))
+}
+
+fn write_statement(
+    f: &mut fmt::Formatter<'_>,
+    statement: &Statement,
+    indent: usize,
+) -> fmt::Result {
+    write!(f, "{}", INDENT.repeat(indent))?;
+
+    match statement {
+        Statement::Return(Some(expression)) => writeln!(f, "return {expression};"),
+        Statement::Return(None) => writeln!(f, "return;"),
+        Statement::Expression(expression) => writeln!(f, "{expression};"),
+        Statement::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            write!(f, "if ({condition})")?;
+            write_body(f, then_branch, indent)?;
+
+            if let Some(else_branch) = else_branch {
+                write!(f, "{}else", INDENT.repeat(indent))?;
+                write_body(f, else_branch, indent)?;
+            }
+
+            Ok(())
+        }
+        Statement::Goto(label) => writeln!(f, "goto {};", label.identifier),
+        Statement::Labeled(label, body) => {
+            writeln!(f, "{}:", label.identifier)?;
+            write_statement(f, body, indent)
+        }
+        Statement::Compound(block) => write_block(f, block, indent),
+        Statement::Break(_) => writeln!(f, "break;"),
+        Statement::Continue(_) => writeln!(f, "continue;"),
+        Statement::While {
+            condition, body, ..
+        } => {
+            write!(f, "while ({condition})")?;
+            write_body(f, body, indent)
+        }
+        Statement::DoWhile {
+            body, condition, ..
+        } => {
+            write!(f, "do")?;
+            write_body(f, body, indent)?;
+            writeln!(f, "{}while ({condition});", INDENT.repeat(indent))
+        }
+        Statement::For {
+            initializer,
+            condition,
+            post,
+            body,
+            ..
+        } => {
+            write!(f, "for (")?;
+            match initializer {
+                Some(ForInitializer::VariableDeclarations(declarations)) => {
+                    for (i, vd) in declarations.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+                        write_storage_class(f, vd.storage_class)?;
+                        write!(f, "{} {}", vd.ty, vd.variable.identifier)?;
+                        if let Some(initializer) = &vd.initializer {
+                            write!(f, " = {initializer}")?;
+                        }
+                    }
+                }
+                Some(ForInitializer::Expression(expression)) => write!(f, "{expression}")?,
+                None => {}
+            }
+            write!(f, ";")?;
+            if let Some(condition) = condition {
+                write!(f, " {condition}")?;
+            }
+            write!(f, ";")?;
+            if let Some(post) = post {
+                write!(f, " {post}")?;
+            }
+            write!(f, ")")?;
+            write_body(f, body, indent)
+        }
+        Statement::Switch {
+            expression, body, ..
+        } => {
+            write!(f, "switch ({expression})")?;
+            write_body(f, body, indent)
+        }
+        Statement::Case {
+            expression, body, ..
+        } => {
+            write!(f, "case {expression}:")?;
+            write_body(f, body, indent)
+        }
+        Statement::Default { body, .. } => {
+            write!(f, "default:")?;
+            write_body(f, body, indent)
+        }
+        Statement::Null => writeln!(f, ";"),
+    }
+}
+
+impl fmt::Display for Initializer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Initializer::Single(expression) => write!(f, "{expression}"),
+            Initializer::Compound(initializers) => {
+                let initializers = initializers.iter().map(Initializer::to_string);
+                write!(f, "{{{}}}", initializers.collect::<Vec<_>>().join(", "))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ty = self.ty();
+        let parenthesize = ty.is_some()
+            || !matches!(
+                self,
+                Expression::Constant { .. }
+                    | Expression::Variable { .. }
+                    | Expression::FunctionCall { .. }
+                    | Expression::IndirectCall { .. }
+                    | Expression::SizeOf { .. }
+                    | Expression::SizeOfType { .. }
+                    | Expression::Subscript { .. }
+            );
+
+        if parenthesize {
+            write!(f, "(")?;
+        }
+
+        match self {
+            Expression::Constant { c, .. } => write!(f, "{c}")?,
+            Expression::Variable { v, .. } => write!(f, "{}", v.identifier)?,
+            Expression::Cast {
+                target_ty, expr, ..
+            } => write!(f, "({target_ty}) {expr}")?,
+            Expression::Unary { op, expr, .. } => match op {
+                UnaryOperator::PostfixIncrement => write!(f, "{expr}++")?,
+                UnaryOperator::PostfixDecrement => write!(f, "{expr}--")?,
+                UnaryOperator::Plus => write!(f, "+{expr}")?,
+                UnaryOperator::Complement => write!(f, "~{expr}")?,
+                UnaryOperator::Negate => write!(f, "-{expr}")?,
+                UnaryOperator::Not => write!(f, "!{expr}")?,
+                UnaryOperator::PrefixIncrement => write!(f, "++{expr}")?,
+                UnaryOperator::PrefixDecrement => write!(f, "--{expr}")?,
+            },
+            Expression::Binary { op, lhs, rhs, .. } => write!(f, "{lhs} {op} {rhs}")?,
+            Expression::Assignment { op, lhs, rhs, .. } => write!(f, "{lhs} {op} {rhs}")?,
+            Expression::Conditional {
+                condition,
+                then_expr,
+                else_expr,
+                ..
+            } => write!(f, "{condition} ? {then_expr} : {else_expr}")?,
+            Expression::FunctionCall {
+                function,
+                arguments,
+                ..
+            } => {
+                let arguments = arguments.iter().map(Expression::to_string);
+                write!(
+                    f,
+                    "{}({})",
+                    function.identifier,
+                    arguments.collect::<Vec<_>>().join(", ")
+                )?
+            }
+            Expression::IndirectCall {
+                callee, arguments, ..
+            } => {
+                let arguments = arguments.iter().map(Expression::to_string);
+                write!(f, "{callee}({})", arguments.collect::<Vec<_>>().join(", "))?
+            }
+            Expression::Comma { lhs, rhs, .. } => write!(f, "{lhs}, {rhs}")?,
+            Expression::SizeOf { expr, .. } => write!(f, "sizeof {expr}")?,
+            Expression::SizeOfType { target_ty, .. } => write!(f, "sizeof({target_ty})")?,
+            Expression::AddrOf { expr, .. } => write!(f, "&{expr}")?,
+            Expression::Dereference { expr, .. } => write!(f, "*{expr}")?,
+            Expression::Subscript { expr, index, .. } => write!(f, "{expr}[{index}]")?,
+        }
+
+        if let Some(ty) = ty {
+            write!(f, ": {ty}")?;
+        }
+
+        if parenthesize {
+            write!(f, ")")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Remainder => "%",
+            BinaryOperator::BitwiseAnd => "&",
+            BinaryOperator::BitwiseOr => "|",
+            BinaryOperator::BitwiseXor => "^",
+            BinaryOperator::ShiftLeft => "<<",
+            BinaryOperator::ShiftRight => ">>",
+            BinaryOperator::LogicalAnd => "&&",
+            BinaryOperator::LogicalOr => "||",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::LessThan => "<",
+            BinaryOperator::LessOrEqual => "<=",
+            BinaryOperator::GreaterThan => ">",
+            BinaryOperator::GreaterOrEqual => ">=",
+        };
+
+        write!(f, "{op}")
+    }
+}
+
+impl fmt::Display for AssignmentOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            AssignmentOperator::Assign => "=",
+            AssignmentOperator::AddAssign => "+=",
+            AssignmentOperator::SubtractAssign => "-=",
+            AssignmentOperator::MultiplyAssign => "*=",
+            AssignmentOperator::DivideAssign => "/=",
+            AssignmentOperator::RemainderAssign => "%=",
+            AssignmentOperator::BitwiseAndAssign => "&=",
+            AssignmentOperator::BitwiseOrAssign => "|=",
+            AssignmentOperator::BitwiseXorAssign => "^=",
+            AssignmentOperator::ShiftLeftAssign => "<<=",
+            AssignmentOperator::ShiftRightAssign => ">>=",
+        };
+
+        write!(f, "{op}")
+    }
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constant::ConstantInt(n) => write!(f, "{n}"),
+            Constant::ConstantLong(n) => write!(f, "{n}L"),
+            Constant::ConstantUInt(n) => write!(f, "{n}U"),
+            Constant::ConstantULong(n) => write!(f, "{n}UL"),
+            Constant::ConstantDouble(d) => write!(f, "{d:?}"),
+        }
+    }
 }
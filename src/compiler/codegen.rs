@@ -1,31 +1,67 @@
 use std::collections::HashMap;
 
-use crate::compiler::{asm, symbols::SymbolAttributes, tacky};
+use crate::compiler::{
+    asm, prefixes::ASM_LABEL_PREFIX, register_allocation, symbols::SymbolAttributes, tacky,
+};
 
-use super::symbols::{Symbol, SymbolTable};
+use super::{
+    ast::Type,
+    optimization::{self, Optimizations},
+    symbols::{Symbol, SymbolTable},
+};
 
-pub fn generate(program: &tacky::Program, symbols: &SymbolTable) -> asm::Program {
-    handle_program(program, symbols)
+pub fn generate(
+    program: &tacky::Program,
+    symbols: &SymbolTable,
+    optimizations: Optimizations,
+    annotate: bool,
+) -> asm::Program {
+    handle_program(program, symbols, optimizations, annotate)
 }
 
-fn handle_program(program: &tacky::Program, symbols: &SymbolTable) -> asm::Program {
+fn handle_program(
+    program: &tacky::Program,
+    symbols: &SymbolTable,
+    optimizations: Optimizations,
+    annotate: bool,
+) -> asm::Program {
     let mut items = Vec::new();
 
     for item in &program.items {
-        items.push(match item {
+        match item {
             tacky::TopLevelItem::FunctionDefinition(fd) => {
-                asm::TopLevelItem::FunctionDefinition(handle_function_definition(fd, symbols))
+                let mut jump_tables = Vec::new();
+                let definition = handle_function_definition(
+                    fd,
+                    symbols,
+                    optimizations,
+                    annotate,
+                    &mut jump_tables,
+                );
+
+                items.push(asm::TopLevelItem::FunctionDefinition(definition));
+                items.extend(jump_tables.into_iter().map(asm::TopLevelItem::JumpTable));
             }
             tacky::TopLevelItem::StaticVariable(sv) => {
-                asm::TopLevelItem::StaticVariable(asm::StaticVariable {
+                items.push(asm::TopLevelItem::StaticVariable(asm::StaticVariable {
                     variable: asm::Variable {
                         identifier: sv.variable.identifier.clone(),
                     },
                     global: sv.global,
-                    initial: sv.initial,
-                })
+                    ty: assembly_type(&sv.ty),
+                    initial: sv.initial.clone(),
+                }));
             }
-        });
+            tacky::TopLevelItem::StaticConstant(sc) => {
+                items.push(asm::TopLevelItem::StaticConstant(asm::StaticConstant {
+                    variable: asm::Variable {
+                        identifier: sc.variable.identifier.clone(),
+                    },
+                    alignment: assembly_type(&sc.ty).alignment(),
+                    initial: sc.initial,
+                }));
+            }
+        }
     }
 
     asm::Program { items }
@@ -43,76 +79,189 @@ fn get_register_for_argument(i: usize) -> Option<asm::Reg> {
     }
 }
 
+fn get_sse_register_for_argument(i: usize) -> Option<asm::Reg> {
+    match i {
+        0 => Some(asm::Reg::XMM0),
+        1 => Some(asm::Reg::XMM1),
+        2 => Some(asm::Reg::XMM2),
+        3 => Some(asm::Reg::XMM3),
+        4 => Some(asm::Reg::XMM4),
+        5 => Some(asm::Reg::XMM5),
+        6 => Some(asm::Reg::XMM6),
+        7 => Some(asm::Reg::XMM7),
+        _ => None,
+    }
+}
+
+/// Where each argument is passed: doubles and integers use separate registers, the rest goes on
+/// the stack in order.
+fn classify_arguments(types: &[asm::AssemblyType]) -> (Vec<(usize, asm::Reg)>, Vec<usize>) {
+    let mut registers = Vec::new();
+    let mut stack = Vec::new();
+    let (mut integers, mut doubles) = (0, 0);
+
+    for (i, ty) in types.iter().enumerate() {
+        let reg = if *ty == asm::AssemblyType::Double {
+            doubles += 1;
+            get_sse_register_for_argument(doubles - 1)
+        } else {
+            integers += 1;
+            get_register_for_argument(integers - 1)
+        };
+
+        match reg {
+            Some(reg) => registers.push((i, reg)),
+            None => stack.push(i),
+        }
+    }
+
+    (registers, stack)
+}
+
+fn return_register(ty: asm::AssemblyType) -> asm::Reg {
+    match ty {
+        asm::AssemblyType::Double => asm::Reg::XMM0,
+        _ => asm::Reg::AX,
+    }
+}
+
 fn handle_function_definition(
     fd: &tacky::FunctionDefinition,
     symbols: &SymbolTable,
+    optimizations: Optimizations,
+    annotate: bool,
+    jump_tables: &mut Vec<asm::JumpTable>,
 ) -> asm::FunctionDefinition {
     let mut instructions = Vec::new();
 
-    for (i, parameter) in fd.parameters.iter().enumerate() {
-        let src = match get_register_for_argument(i) {
-            Some(reg) => asm::Operand::Reg(reg),
-            None => asm::Operand::Stack(16 + ((i as i64 - 6) * 8)),
-        };
+    let parameter_types = fd
+        .parameters
+        .iter()
+        .map(|parameter| variable_assembly_type(parameter, symbols))
+        .collect::<Vec<_>>();
+    let (register_parameters, stack_parameters) = classify_arguments(&parameter_types);
 
+    for (i, reg) in register_parameters {
         instructions.push(asm::Instruction::Mov {
-            src,
-            dst: handle_variable(parameter),
+            ty: parameter_types[i],
+            src: asm::Operand::Reg(reg),
+            dst: handle_variable(&fd.parameters[i]),
+        });
+    }
+
+    for (position, i) in stack_parameters.into_iter().enumerate() {
+        instructions.push(asm::Instruction::Mov {
+            ty: parameter_types[i],
+            src: asm::Operand::Stack(16 + 8 * position as i64),
+            dst: handle_variable(&fd.parameters[i]),
         });
     }
 
-    instructions.extend(handle_instructions(&fd.instructions));
+    let Type::Function { return_type, .. } = &symbols.get_checked(&fd.function.identifier).ty
+    else {
+        unreachable!()
+    };
 
-    let stack_size = replace_pseudo_registers(&mut instructions, symbols);
-    fix_up_instructions(&mut instructions, stack_size);
+    instructions.extend(handle_instructions(
+        &fd.function.identifier,
+        &fd.instructions,
+        symbols,
+        (**return_type != Type::Void).then(|| assembly_type(return_type)),
+        annotate,
+        jump_tables,
+    ));
+
+    let registers = if optimizations.allocate_registers {
+        register_allocation::allocate(&instructions, jump_tables, symbols)
+    } else {
+        HashMap::new()
+    };
+
+    let callee_saved = register_allocation::ALLOCATABLE_REGISTERS
+        .into_iter()
+        .filter(|reg| registers.values().any(|used| used == reg))
+        .collect::<Vec<_>>();
+
+    let stack_size = replace_pseudo_registers(&mut instructions, symbols, &registers);
+    fix_up_instructions(&mut instructions, stack_size, &callee_saved);
+
+    let instructions = optimization::optimize_assembly(&instructions, optimizations);
 
     asm::FunctionDefinition {
         function: asm::Function {
             identifier: fd.function.identifier.clone(),
         },
         global: fd.global,
+        frame_pointer: instructions.iter().any(needs_frame_pointer),
         instructions,
     }
 }
 
-fn handle_instructions(instructions: &[tacky::Instruction]) -> Vec<asm::Instruction> {
+/// `function` names the labels needed to skip over comparisons with NaN.
+fn handle_instructions(
+    function: &str,
+    instructions: &[tacky::Instruction],
+    symbols: &SymbolTable,
+    return_ty: Option<asm::AssemblyType>,
+    annotate: bool,
+    jump_tables: &mut Vec<asm::JumpTable>,
+) -> Vec<asm::Instruction> {
     let mut ins = vec![];
 
+    let mut label_counter = 0;
+    let mut make_label = || {
+        let identifier = format!("{ASM_LABEL_PREFIX}.{function}.{label_counter}");
+        label_counter += 1;
+        asm::Label { identifier }
+    };
+
     for instruction in instructions {
+        // labels and lines already show up in the output as themselves
+        let annotated = !matches!(
+            instruction,
+            tacky::Instruction::Label(_) | tacky::Instruction::SourceLine(_)
+        );
+        if annotate && annotated {
+            ins.push(asm::Instruction::Comment(instruction.to_string()));
+        }
+
         match instruction {
             tacky::Instruction::Return(value) => {
-                ins.push(asm::Instruction::Mov {
-                    src: handle_value(value),
-                    dst: asm::Operand::Reg(asm::Reg::AX),
-                });
+                if let Some(value) = value {
+                    let ty = return_ty.expect("void functions don't return a value");
+                    ins.push(asm::Instruction::Mov {
+                        ty,
+                        src: handle_value(value),
+                        dst: asm::Operand::Reg(return_register(ty)),
+                    });
+                }
                 ins.push(asm::Instruction::Ret);
             }
             tacky::Instruction::Unary { op, src, dst } => match op {
                 tacky::UnaryOperator::Complement | tacky::UnaryOperator::Negate => {
+                    let ty = variable_assembly_type(dst, symbols);
                     let dst_asm = handle_variable(dst);
                     ins.push(asm::Instruction::Mov {
+                        ty,
                         src: handle_value(src),
                         dst: dst_asm.clone(),
                     });
                     ins.push(asm::Instruction::Unary {
                         op: handle_unary_operator(op),
+                        ty,
                         dst: dst_asm,
                     });
                 }
                 tacky::UnaryOperator::Not => {
-                    let dst_asm = handle_variable(dst);
-                    ins.push(asm::Instruction::Cmp {
-                        src: asm::Operand::Imm(0),
-                        dst: handle_value(src),
-                    });
-                    ins.push(asm::Instruction::Mov {
-                        src: asm::Operand::Imm(0),
-                        dst: dst_asm.clone(),
-                    });
-                    ins.push(asm::Instruction::SetCC {
-                        cc: asm::ConditionCode::E,
-                        dst: dst_asm,
-                    });
+                    let ty = value_assembly_type(src, symbols);
+                    push_compare_to_zero(&mut ins, ty, src);
+                    push_set_condition(
+                        &mut ins,
+                        asm::ConditionCode::E,
+                        handle_variable(dst),
+                        variable_assembly_type(dst, symbols),
+                        (ty == asm::AssemblyType::Double).then(&mut make_label),
+                    );
                 }
             },
             tacky::Instruction::Binary { op, lhs, rhs, dst } => match op {
@@ -122,54 +271,88 @@ fn handle_instructions(instructions: &[tacky::Instruction]) -> Vec<asm::Instruct
                 | tacky::BinaryOperator::BitwiseAnd
                 | tacky::BinaryOperator::BitwiseOr
                 | tacky::BinaryOperator::BitwiseXor => {
+                    let ty = variable_assembly_type(dst, symbols);
                     let dst_asm = handle_variable(dst);
                     ins.push(asm::Instruction::Mov {
+                        ty,
                         src: handle_value(lhs),
                         dst: dst_asm.clone(),
                     });
                     ins.push(asm::Instruction::Binary {
                         op: handle_binary_operator(op),
+                        ty,
+                        src: handle_value(rhs),
+                        dst: dst_asm,
+                    });
+                }
+                tacky::BinaryOperator::Divide
+                    if variable_assembly_type(dst, symbols) == asm::AssemblyType::Double =>
+                {
+                    let dst_asm = handle_variable(dst);
+                    ins.push(asm::Instruction::Mov {
+                        ty: asm::AssemblyType::Double,
+                        src: handle_value(lhs),
+                        dst: dst_asm.clone(),
+                    });
+                    ins.push(asm::Instruction::Binary {
+                        op: asm::BinaryOperator::DivDouble,
+                        ty: asm::AssemblyType::Double,
                         src: handle_value(rhs),
                         dst: dst_asm,
                     });
                 }
                 tacky::BinaryOperator::Divide => {
+                    let ty = variable_assembly_type(dst, symbols);
                     ins.push(asm::Instruction::Mov {
+                        ty,
                         src: handle_value(lhs),
                         dst: asm::Operand::Reg(asm::Reg::AX),
                     });
-                    ins.push(asm::Instruction::Cdq);
-                    ins.push(asm::Instruction::Idiv(handle_value(rhs)));
+                    push_division(&mut ins, ty, handle_value(rhs), is_signed(dst, symbols));
                     ins.push(asm::Instruction::Mov {
+                        ty,
                         src: asm::Operand::Reg(asm::Reg::AX),
                         dst: handle_variable(dst),
                     });
                 }
                 tacky::BinaryOperator::Remainder => {
+                    let ty = variable_assembly_type(dst, symbols);
                     ins.push(asm::Instruction::Mov {
+                        ty,
                         src: handle_value(lhs),
                         dst: asm::Operand::Reg(asm::Reg::AX),
                     });
-                    ins.push(asm::Instruction::Cdq);
-                    ins.push(asm::Instruction::Idiv(handle_value(rhs)));
+                    push_division(&mut ins, ty, handle_value(rhs), is_signed(dst, symbols));
                     ins.push(asm::Instruction::Mov {
+                        ty,
                         src: asm::Operand::Reg(asm::Reg::DX),
                         dst: handle_variable(dst),
                     });
                 }
                 tacky::BinaryOperator::ShiftLeft | tacky::BinaryOperator::ShiftRight => {
+                    let ty = variable_assembly_type(dst, symbols);
                     let dst_asm = handle_variable(dst);
                     ins.push(asm::Instruction::Mov {
+                        ty,
                         src: handle_value(lhs),
                         dst: dst_asm.clone(),
                     });
+                    // only %cl is used as the shift count
                     ins.push(asm::Instruction::Mov {
+                        ty: asm::AssemblyType::Longword,
                         src: handle_value(rhs),
-                        dst: asm::Operand::Reg(asm::Reg::CX).clone(),
+                        dst: asm::Operand::Reg(asm::Reg::CX),
                     });
                     ins.push(match op {
-                        tacky::BinaryOperator::ShiftLeft => asm::Instruction::Sal(dst_asm),
-                        tacky::BinaryOperator::ShiftRight => asm::Instruction::Sar(dst_asm),
+                        tacky::BinaryOperator::ShiftLeft => {
+                            asm::Instruction::Sal { ty, dst: dst_asm }
+                        }
+                        tacky::BinaryOperator::ShiftRight if is_signed(dst, symbols) => {
+                            asm::Instruction::Sar { ty, dst: dst_asm }
+                        }
+                        tacky::BinaryOperator::ShiftRight => {
+                            asm::Instruction::Shr { ty, dst: dst_asm }
+                        }
                         _ => unreachable!(),
                     });
                 }
@@ -179,23 +362,147 @@ fn handle_instructions(instructions: &[tacky::Instruction]) -> Vec<asm::Instruct
                 | tacky::BinaryOperator::LessOrEqual
                 | tacky::BinaryOperator::GreaterThan
                 | tacky::BinaryOperator::GreaterOrEqual => {
-                    let dst_asm = handle_variable(dst);
+                    let ty = operands_assembly_type(lhs, rhs, symbols);
                     ins.push(asm::Instruction::Cmp {
+                        ty,
                         src: handle_value(rhs),
                         dst: handle_value(lhs),
                     });
-                    ins.push(asm::Instruction::Mov {
-                        src: asm::Operand::Imm(0),
-                        dst: dst_asm.clone(),
-                    });
-                    ins.push(asm::Instruction::SetCC {
-                        cc: handle_relational_binary_operator(op),
-                        dst: dst_asm,
-                    });
+                    push_set_condition(
+                        &mut ins,
+                        handle_relational_binary_operator(op, operands_signed(lhs, rhs, symbols)),
+                        handle_variable(dst),
+                        variable_assembly_type(dst, symbols),
+                        (ty == asm::AssemblyType::Double).then(&mut make_label),
+                    );
                 }
             },
             tacky::Instruction::Copy { src, dst } => {
                 ins.push(asm::Instruction::Mov {
+                    ty: variable_assembly_type(dst, symbols),
+                    src: handle_value(src),
+                    dst: handle_variable(dst),
+                });
+            }
+            tacky::Instruction::SignExtend { src, dst } => {
+                ins.push(asm::Instruction::Movsx {
+                    src_ty: value_assembly_type(src, symbols),
+                    dst_ty: variable_assembly_type(dst, symbols),
+                    src: handle_value(src),
+                    dst: handle_variable(dst),
+                });
+            }
+            tacky::Instruction::ZeroExtend { src, dst } => {
+                ins.push(asm::Instruction::MovZeroExtend {
+                    src_ty: value_assembly_type(src, symbols),
+                    dst_ty: variable_assembly_type(dst, symbols),
+                    src: handle_value(src),
+                    dst: handle_variable(dst),
+                });
+            }
+            tacky::Instruction::DoubleToInt { src, dst } => {
+                ins.push(asm::Instruction::Cvttsd2si {
+                    dst_ty: variable_assembly_type(dst, symbols),
+                    src: handle_value(src),
+                    dst: handle_variable(dst),
+                });
+            }
+            tacky::Instruction::IntToDouble { src, dst } => {
+                ins.push(asm::Instruction::Cvtsi2sd {
+                    src_ty: value_assembly_type(src, symbols),
+                    src: handle_value(src),
+                    dst: handle_variable(dst),
+                });
+            }
+            tacky::Instruction::GetAddress { src, dst } => {
+                ins.push(asm::Instruction::Lea {
+                    src: handle_value(src),
+                    dst: handle_variable(dst),
+                });
+            }
+            tacky::Instruction::Load { src_ptr, dst } => {
+                ins.push(asm::Instruction::Mov {
+                    ty: asm::AssemblyType::Quadword,
+                    src: handle_value(src_ptr),
+                    dst: asm::Operand::Reg(asm::Reg::AX),
+                });
+                ins.push(asm::Instruction::Mov {
+                    ty: variable_assembly_type(dst, symbols),
+                    src: asm::Operand::Memory(asm::Reg::AX, 0),
+                    dst: handle_variable(dst),
+                });
+            }
+            tacky::Instruction::Store { src, dst_ptr } => {
+                let tacky::Value::Variable(ptr) = dst_ptr else {
+                    unreachable!("pointers are never folded into constants")
+                };
+                let Type::Pointer(referenced) = &symbols.get_checked(&ptr.identifier).ty else {
+                    unreachable!()
+                };
+
+                ins.push(asm::Instruction::Mov {
+                    ty: asm::AssemblyType::Quadword,
+                    src: handle_value(dst_ptr),
+                    dst: asm::Operand::Reg(asm::Reg::AX),
+                });
+                ins.push(asm::Instruction::Mov {
+                    ty: assembly_type(referenced),
+                    src: handle_value(src),
+                    dst: asm::Operand::Memory(asm::Reg::AX, 0),
+                });
+            }
+            tacky::Instruction::AddPtr {
+                ptr,
+                index,
+                scale,
+                dst,
+            } => {
+                ins.push(asm::Instruction::Mov {
+                    ty: asm::AssemblyType::Quadword,
+                    src: handle_value(ptr),
+                    dst: asm::Operand::Reg(asm::Reg::AX),
+                });
+
+                let src = match index {
+                    tacky::Value::Constant(n) => {
+                        asm::Operand::Memory(asm::Reg::AX, n.value() * scale)
+                    }
+                    tacky::Value::Variable(_) => {
+                        ins.push(asm::Instruction::Mov {
+                            ty: asm::AssemblyType::Quadword,
+                            src: handle_value(index),
+                            dst: asm::Operand::Reg(asm::Reg::DX),
+                        });
+
+                        // the addressing mode can only scale by 1, 2, 4 or 8
+                        let scale = if let 1 | 2 | 4 | 8 = scale {
+                            *scale
+                        } else {
+                            ins.push(asm::Instruction::Binary {
+                                op: asm::BinaryOperator::Mult,
+                                ty: asm::AssemblyType::Quadword,
+                                src: asm::Operand::Imm(*scale),
+                                dst: asm::Operand::Reg(asm::Reg::DX),
+                            });
+                            1
+                        };
+
+                        asm::Operand::Indexed {
+                            base: asm::Reg::AX,
+                            index: asm::Reg::DX,
+                            scale,
+                        }
+                    }
+                };
+
+                ins.push(asm::Instruction::Lea {
+                    src,
+                    dst: handle_variable(dst),
+                });
+            }
+            tacky::Instruction::Truncate { src, dst } => {
+                ins.push(asm::Instruction::Mov {
+                    ty: variable_assembly_type(dst, symbols),
                     src: handle_value(src),
                     dst: handle_variable(dst),
                 });
@@ -206,20 +513,33 @@ fn handle_instructions(instructions: &[tacky::Instruction]) -> Vec<asm::Instruct
                 });
             }
             tacky::Instruction::JumpIfZero { condition, target } => {
-                ins.push(asm::Instruction::Cmp {
-                    src: asm::Operand::Imm(0),
-                    dst: handle_value(condition),
-                });
+                let ty = value_assembly_type(condition, symbols);
+                push_compare_to_zero(&mut ins, ty, condition);
+                // NaN isn't zero, even though comparing with it sets the zero flag
+                let ordered = (ty == asm::AssemblyType::Double).then(&mut make_label);
+                if let Some(ordered) = &ordered {
+                    ins.push(asm::Instruction::JmpCC {
+                        cc: asm::ConditionCode::P,
+                        target: ordered.clone(),
+                    });
+                }
                 ins.push(asm::Instruction::JmpCC {
                     cc: asm::ConditionCode::E,
                     target: handle_label(target),
                 });
+                if let Some(ordered) = ordered {
+                    ins.push(asm::Instruction::Label(ordered));
+                }
             }
             tacky::Instruction::JumpIfNotZero { condition, target } => {
-                ins.push(asm::Instruction::Cmp {
-                    src: asm::Operand::Imm(0),
-                    dst: handle_value(condition),
-                });
+                let ty = value_assembly_type(condition, symbols);
+                push_compare_to_zero(&mut ins, ty, condition);
+                if ty == asm::AssemblyType::Double {
+                    ins.push(asm::Instruction::JmpCC {
+                        cc: asm::ConditionCode::P,
+                        target: handle_label(target),
+                    });
+                }
                 ins.push(asm::Instruction::JmpCC {
                     cc: asm::ConditionCode::NE,
                     target: handle_label(target),
@@ -233,57 +553,330 @@ fn handle_instructions(instructions: &[tacky::Instruction]) -> Vec<asm::Instruct
                 args,
                 dst,
             } => {
-                let (register_args, stack_args) = args.split_at(6.min(args.len()));
-
-                let stack_padding = if stack_args.len() % 2 == 0 { 0 } else { 8 };
-                if stack_padding != 0 {
-                    ins.push(asm::Instruction::AllocateStack(stack_padding));
-                }
+                let symbol = symbols.get_checked(&function.identifier);
+                let Type::Function { parameters, .. } = &symbol.ty else {
+                    unreachable!()
+                };
 
-                for (i, arg) in register_args.iter().enumerate() {
-                    let reg = get_register_for_argument(i).unwrap();
-                    ins.push(asm::Instruction::Mov {
-                        src: handle_value(arg),
-                        dst: asm::Operand::Reg(reg),
+                let arg_types = parameters.iter().map(assembly_type).collect::<Vec<_>>();
+                let call = asm::Instruction::Call {
+                    function: asm::Function {
+                        identifier: function.identifier.clone(),
+                    },
+                    external: matches!(
+                        symbol.attrs,
+                        SymbolAttributes::Function { defined: false, .. }
+                    ),
+                };
+                push_call(&mut ins, &arg_types, args, call, dst.as_ref(), symbols);
+            }
+            tacky::Instruction::IndirectCall {
+                function_ptr,
+                args,
+                dst,
+            } => {
+                // the arguments were already converted to the parameter types
+                let arg_types = args
+                    .iter()
+                    .map(|arg| value_assembly_type(arg, symbols))
+                    .collect::<Vec<_>>();
+                let call = asm::Instruction::IndirectCall(handle_value(function_ptr));
+                push_call(&mut ins, &arg_types, args, call, dst.as_ref(), symbols);
+            }
+            tacky::Instruction::JumpTable {
+                condition,
+                base,
+                targets,
+                default,
+                table,
+            } => {
+                let ty = value_assembly_type(condition, symbols);
+                ins.push(asm::Instruction::Mov {
+                    ty,
+                    src: handle_value(condition),
+                    dst: asm::Operand::Reg(asm::Reg::AX),
+                });
+                if *base != 0 {
+                    ins.push(asm::Instruction::Binary {
+                        op: asm::BinaryOperator::Sub,
+                        ty,
+                        src: asm::Operand::Imm(*base),
+                        dst: asm::Operand::Reg(asm::Reg::AX),
                     });
                 }
+                ins.push(asm::Instruction::Cmp {
+                    ty,
+                    src: asm::Operand::Imm(targets.len() as i64 - 1),
+                    dst: asm::Operand::Reg(asm::Reg::AX),
+                });
+                ins.push(asm::Instruction::JmpCC {
+                    cc: asm::ConditionCode::A,
+                    target: handle_label(default),
+                });
+                ins.push(asm::Instruction::JmpTable {
+                    table: handle_label(table),
+                    index: asm::Reg::AX,
+                });
 
-                for arg in stack_args.iter().rev() {
-                    let val = handle_value(arg);
-                    if let asm::Operand::Imm(_) | asm::Operand::Reg(_) = val {
-                        ins.push(asm::Instruction::Push(val));
-                    } else {
-                        ins.push(asm::Instruction::Mov {
-                            src: val,
-                            dst: asm::Operand::Reg(asm::Reg::AX),
-                        });
-                        ins.push(asm::Instruction::Push(asm::Operand::Reg(asm::Reg::AX)));
-                    }
-                }
+                jump_tables.push(asm::JumpTable {
+                    label: handle_label(table),
+                    targets: targets.iter().map(handle_label).collect(),
+                });
+            }
+            tacky::Instruction::SourceLine(line) => {
+                ins.push(asm::Instruction::SourceLine(*line));
+            }
+        }
+    }
 
-                ins.push(asm::Instruction::Call(asm::Function {
-                    identifier: function.identifier.clone(),
-                }));
+    ins
+}
 
-                let bytes_to_deallocate = 8 * (stack_args.len() as u64) + stack_padding;
-                if bytes_to_deallocate != 0 {
-                    ins.push(asm::Instruction::DeallocateStack(bytes_to_deallocate));
-                }
+fn assembly_type(ty: &Type) -> asm::AssemblyType {
+    match ty {
+        Type::Int | Type::UInt => asm::AssemblyType::Longword,
+        Type::Long | Type::ULong => asm::AssemblyType::Quadword,
+        Type::Double => asm::AssemblyType::Double,
+        Type::Bool | Type::Char | Type::SChar | Type::UChar => asm::AssemblyType::Byte,
+        Type::Pointer(_) => asm::AssemblyType::Quadword,
+        Type::Array { element, .. } => {
+            let size = ty.size().unwrap();
 
-                ins.push(asm::Instruction::Mov {
-                    src: asm::Operand::Reg(asm::Reg::AX),
-                    dst: handle_variable(dst),
-                });
+            asm::AssemblyType::ByteArray {
+                size,
+                // larger arrays get 16-byte alignment so they can be used with SSE
+                alignment: if size >= 16 {
+                    16
+                } else {
+                    assembly_type(element).alignment()
+                },
             }
         }
+        Type::Void | Type::Function { .. } => unreachable!("{ty:?} has no assembly type"),
+    }
+}
+
+fn variable_assembly_type(variable: &tacky::Variable, symbols: &SymbolTable) -> asm::AssemblyType {
+    assembly_type(&symbols.get_checked(&variable.identifier).ty)
+}
+
+fn value_assembly_type(value: &tacky::Value, symbols: &SymbolTable) -> asm::AssemblyType {
+    match value {
+        tacky::Value::Constant(c) => assembly_type(&c.ty()),
+        tacky::Value::Variable(variable) => variable_assembly_type(variable, symbols),
     }
+}
 
-    ins
+/// Operands of a comparison share a type, so prefer the one that carries it. A bool is compared
+/// to an int zero, which is wider than the bool itself.
+fn operands_assembly_type(
+    lhs: &tacky::Value,
+    rhs: &tacky::Value,
+    symbols: &SymbolTable,
+) -> asm::AssemblyType {
+    match (lhs, rhs) {
+        (tacky::Value::Variable(_), _) => value_assembly_type(lhs, symbols),
+        (_, tacky::Value::Variable(_)) => value_assembly_type(rhs, symbols),
+        _ => value_assembly_type(lhs, symbols),
+    }
+}
+
+fn is_signed(variable: &tacky::Variable, symbols: &SymbolTable) -> bool {
+    symbols.get_checked(&variable.identifier).ty.is_signed()
+}
+
+/// Like `operands_assembly_type`, prefers the signedness of a variable operand.
+fn operands_signed(lhs: &tacky::Value, rhs: &tacky::Value, symbols: &SymbolTable) -> bool {
+    match (lhs, rhs) {
+        (tacky::Value::Variable(variable), _) | (_, tacky::Value::Variable(variable)) => {
+            is_signed(variable, symbols)
+        }
+        (tacky::Value::Constant(c), _) => c.ty().is_signed(),
+    }
+}
+
+/// Sets the flags for comparing `value` to zero. Doubles have no immediates, so their zero is
+/// made by clearing a register.
+fn push_compare_to_zero(
+    ins: &mut Vec<asm::Instruction>,
+    ty: asm::AssemblyType,
+    value: &tacky::Value,
+) {
+    if ty == asm::AssemblyType::Double {
+        ins.push(asm::Instruction::Binary {
+            op: asm::BinaryOperator::Xor,
+            ty,
+            src: asm::Operand::Reg(asm::Reg::XMM0),
+            dst: asm::Operand::Reg(asm::Reg::XMM0),
+        });
+        ins.push(asm::Instruction::Cmp {
+            ty,
+            src: handle_value(value),
+            dst: asm::Operand::Reg(asm::Reg::XMM0),
+        });
+    } else {
+        ins.push(asm::Instruction::Cmp {
+            ty,
+            src: asm::Operand::Imm(0),
+            dst: handle_value(value),
+        });
+    }
+}
+
+/// Sets `dst` to whether `cc` holds after a comparison. With a `skip` label, the comparison was
+/// between doubles, and when either of them was NaN (which sets the zero and carry flags as well
+/// as the parity flag), `dst` is only true for `NE`.
+fn push_set_condition(
+    ins: &mut Vec<asm::Instruction>,
+    cc: asm::ConditionCode,
+    dst: asm::Operand,
+    ty: asm::AssemblyType,
+    skip: Option<asm::Label>,
+) {
+    let unordered = skip.is_some() && cc == asm::ConditionCode::NE;
+    ins.push(asm::Instruction::Mov {
+        ty,
+        src: asm::Operand::Imm(unordered as i64),
+        dst: dst.clone(),
+    });
+
+    match skip {
+        // `a` and `ae` already don't hold for NaN
+        Some(skip) if cc != asm::ConditionCode::A && cc != asm::ConditionCode::AE => {
+            ins.push(asm::Instruction::JmpCC {
+                cc: asm::ConditionCode::P,
+                target: skip.clone(),
+            });
+            ins.push(asm::Instruction::SetCC { cc, dst });
+            ins.push(asm::Instruction::Label(skip));
+        }
+        _ => ins.push(asm::Instruction::SetCC { cc, dst }),
+    }
+}
+
+/// Divides `%ax` (extended into `%dx`) by `operand`, leaving the quotient in `%ax` and the
+/// remainder in `%dx`.
+fn push_division(
+    ins: &mut Vec<asm::Instruction>,
+    ty: asm::AssemblyType,
+    operand: asm::Operand,
+    signed: bool,
+) {
+    if signed {
+        ins.push(asm::Instruction::Cdq(ty));
+        ins.push(asm::Instruction::Idiv { ty, operand });
+    } else {
+        ins.push(asm::Instruction::Mov {
+            ty,
+            src: asm::Operand::Imm(0),
+            dst: asm::Operand::Reg(asm::Reg::DX),
+        });
+        ins.push(asm::Instruction::Div { ty, operand });
+    }
+}
+
+/// Passes `args` according to the calling convention around `call`, and stores the result in
+/// `dst`.
+fn push_call(
+    ins: &mut Vec<asm::Instruction>,
+    arg_types: &[asm::AssemblyType],
+    args: &[tacky::Value],
+    call: asm::Instruction,
+    dst: Option<&tacky::Variable>,
+    symbols: &SymbolTable,
+) {
+    let (register_args, stack_args) = classify_arguments(arg_types);
+
+    let stack_padding = if stack_args.len() % 2 == 0 { 0 } else { 8 };
+    if stack_padding != 0 {
+        ins.push(asm::Instruction::AllocateStack(stack_padding));
+    }
+
+    for (i, reg) in register_args {
+        ins.push(move_argument(
+            arg_types[i],
+            &args[i],
+            asm::Operand::Reg(reg),
+            symbols,
+        ));
+    }
+
+    for &i in stack_args.iter().rev() {
+        let val = handle_value(&args[i]);
+        if let asm::Operand::Imm(_) | asm::Operand::Reg(_) = val {
+            ins.push(asm::Instruction::Push(val));
+        } else {
+            // only the bits matter, so doubles go through a general purpose register
+            let ty = match arg_types[i] {
+                asm::AssemblyType::Double => asm::AssemblyType::Quadword,
+                ty => ty,
+            };
+            ins.push(move_argument(
+                ty,
+                &args[i],
+                asm::Operand::Reg(asm::Reg::AX),
+                symbols,
+            ));
+            ins.push(asm::Instruction::Push(asm::Operand::Reg(asm::Reg::AX)));
+        }
+    }
+
+    ins.push(call);
+
+    let bytes_to_deallocate = 8 * (stack_args.len() as u64) + stack_padding;
+    if bytes_to_deallocate != 0 {
+        ins.push(asm::Instruction::DeallocateStack(bytes_to_deallocate));
+    }
+
+    if let Some(dst) = dst {
+        let ty = variable_assembly_type(dst, symbols);
+        ins.push(asm::Instruction::Mov {
+            ty,
+            src: asm::Operand::Reg(return_register(ty)),
+            dst: handle_variable(dst),
+        });
+    }
+}
+
+/// Moves an argument to where it's passed. Bytes are extended to 32 bits, which the System V ABI
+/// doesn't ask for, but clang relies on for `char` and `_Bool` parameters.
+fn move_argument(
+    ty: asm::AssemblyType,
+    arg: &tacky::Value,
+    dst: asm::Operand,
+    symbols: &SymbolTable,
+) -> asm::Instruction {
+    let src = handle_value(arg);
+
+    if ty != asm::AssemblyType::Byte {
+        return asm::Instruction::Mov { ty, src, dst };
+    }
+
+    let signed = match arg {
+        tacky::Value::Variable(variable) => is_signed(variable, symbols),
+        tacky::Value::Constant(c) => c.ty().is_signed(),
+    };
+
+    if signed {
+        asm::Instruction::Movsx {
+            src_ty: ty,
+            dst_ty: asm::AssemblyType::Longword,
+            src,
+            dst,
+        }
+    } else {
+        asm::Instruction::MovZeroExtend {
+            src_ty: ty,
+            dst_ty: asm::AssemblyType::Longword,
+            src,
+            dst,
+        }
+    }
 }
 
 fn handle_value(value: &tacky::Value) -> asm::Operand {
     match value {
-        tacky::Value::Constant(value) => asm::Operand::Imm(*value),
+        tacky::Value::Constant(c) => asm::Operand::Imm(c.value()),
         tacky::Value::Variable(variable) => handle_variable(variable),
     }
 }
@@ -312,14 +905,21 @@ fn handle_binary_operator(op: &tacky::BinaryOperator) -> asm::BinaryOperator {
     }
 }
 
-fn handle_relational_binary_operator(op: &tacky::BinaryOperator) -> asm::ConditionCode {
-    match op {
-        tacky::BinaryOperator::Equal => asm::ConditionCode::E,
-        tacky::BinaryOperator::NotEqual => asm::ConditionCode::NE,
-        tacky::BinaryOperator::LessThan => asm::ConditionCode::L,
-        tacky::BinaryOperator::LessOrEqual => asm::ConditionCode::LE,
-        tacky::BinaryOperator::GreaterThan => asm::ConditionCode::G,
-        tacky::BinaryOperator::GreaterOrEqual => asm::ConditionCode::GE,
+fn handle_relational_binary_operator(
+    op: &tacky::BinaryOperator,
+    signed: bool,
+) -> asm::ConditionCode {
+    match (op, signed) {
+        (tacky::BinaryOperator::Equal, _) => asm::ConditionCode::E,
+        (tacky::BinaryOperator::NotEqual, _) => asm::ConditionCode::NE,
+        (tacky::BinaryOperator::LessThan, true) => asm::ConditionCode::L,
+        (tacky::BinaryOperator::LessOrEqual, true) => asm::ConditionCode::LE,
+        (tacky::BinaryOperator::GreaterThan, true) => asm::ConditionCode::G,
+        (tacky::BinaryOperator::GreaterOrEqual, true) => asm::ConditionCode::GE,
+        (tacky::BinaryOperator::LessThan, false) => asm::ConditionCode::B,
+        (tacky::BinaryOperator::LessOrEqual, false) => asm::ConditionCode::BE,
+        (tacky::BinaryOperator::GreaterThan, false) => asm::ConditionCode::A,
+        (tacky::BinaryOperator::GreaterOrEqual, false) => asm::ConditionCode::AE,
         _ => unreachable!("not possible to convert to asm condition code: {:?}", op),
     }
 }
@@ -333,56 +933,103 @@ fn handle_label(label: &tacky::Label) -> asm::Label {
 fn replace_pseudo_registers(
     instructions: &mut Vec<asm::Instruction>,
     symbols: &SymbolTable,
+    registers: &HashMap<String, asm::Reg>,
 ) -> u64 {
     let mut map = HashMap::new();
+    let mut stack_size = 0;
 
     for ins in instructions {
         match ins {
-            asm::Instruction::Mov { src, dst }
+            asm::Instruction::Mov { src, dst, .. }
+            | asm::Instruction::Movsx { src, dst, .. }
+            | asm::Instruction::MovZeroExtend { src, dst, .. }
+            | asm::Instruction::Lea { src, dst }
             | asm::Instruction::Binary { src, dst, .. }
-            | asm::Instruction::Cmp { src, dst } => {
-                replace_pseudo_registers_in_operand(src, &mut map, symbols);
-                replace_pseudo_registers_in_operand(dst, &mut map, symbols);
+            | asm::Instruction::Cmp { src, dst, .. }
+            | asm::Instruction::Cvttsd2si { src, dst, .. }
+            | asm::Instruction::Cvtsi2sd { src, dst, .. } => {
+                replace_pseudo_registers_in_operand(
+                    src,
+                    &mut map,
+                    &mut stack_size,
+                    symbols,
+                    registers,
+                );
+                replace_pseudo_registers_in_operand(
+                    dst,
+                    &mut map,
+                    &mut stack_size,
+                    symbols,
+                    registers,
+                );
             }
 
             asm::Instruction::Unary { dst: op, .. }
-            | asm::Instruction::Idiv(op)
-            | asm::Instruction::Sal(op)
-            | asm::Instruction::Sar(op)
+            | asm::Instruction::Idiv { operand: op, .. }
+            | asm::Instruction::Div { operand: op, .. }
+            | asm::Instruction::Sal { dst: op, .. }
+            | asm::Instruction::Sar { dst: op, .. }
+            | asm::Instruction::Shr { dst: op, .. }
             | asm::Instruction::SetCC { dst: op, .. }
-            | asm::Instruction::Push(op) => {
-                replace_pseudo_registers_in_operand(op, &mut map, symbols);
+            | asm::Instruction::Push(op)
+            | asm::Instruction::IndirectCall(op) => {
+                replace_pseudo_registers_in_operand(
+                    op,
+                    &mut map,
+                    &mut stack_size,
+                    symbols,
+                    registers,
+                );
             }
 
             asm::Instruction::Ret
-            | asm::Instruction::Cdq
+            | asm::Instruction::SourceLine(_)
+            | asm::Instruction::Comment(_)
+            | asm::Instruction::Cdq(_)
             | asm::Instruction::Jmp { .. }
             | asm::Instruction::JmpCC { .. }
+            | asm::Instruction::JmpTable { .. }
             | asm::Instruction::Label(_)
-            | asm::Instruction::Call(_)
+            | asm::Instruction::Pop(_)
+            | asm::Instruction::Call { .. }
             | asm::Instruction::AllocateStack(_)
             | asm::Instruction::DeallocateStack(_) => {}
         }
     }
 
-    4 * (map.len() as u64)
+    stack_size
 }
 
 fn replace_pseudo_registers_in_operand(
     operand: &mut asm::Operand,
     map: &mut HashMap<String, i64>,
+    stack_size: &mut u64,
     symbols: &SymbolTable,
+    registers: &HashMap<String, asm::Reg>,
 ) {
     if let asm::Operand::Pseudo(name) = operand {
+        if let Some(reg) = registers.get(name) {
+            *operand = asm::Operand::Reg(*reg);
+            return;
+        }
+
         *operand = match map.get(name) {
             Some(offset) => asm::Operand::Stack(*offset),
-            None => match symbols.get(name) {
-                Some(Symbol {
-                    attrs: SymbolAttributes::Static { .. },
+            None => match symbols.get_checked(name) {
+                // functions only show up here when their address is taken
+                Symbol {
+                    attrs:
+                        SymbolAttributes::Static { .. }
+                        | SymbolAttributes::Constant(_)
+                        | SymbolAttributes::Function { .. },
                     ..
-                }) => asm::Operand::Data(name.clone()),
-                _ => {
-                    let offset = -4 * ((map.len() as i64) + 1);
+                } => asm::Operand::Data(name.clone()),
+                Symbol { ty, .. } => {
+                    let ty = assembly_type(ty);
+                    *stack_size =
+                        (*stack_size + ty.size() as u64).next_multiple_of(ty.alignment() as u64);
+
+                    let offset = -(*stack_size as i64);
                     map.insert(name.clone(), offset);
                     asm::Operand::Stack(offset)
                 }
@@ -391,34 +1038,382 @@ fn replace_pseudo_registers_in_operand(
     }
 }
 
-fn fix_up_instructions(instructions: &mut Vec<asm::Instruction>, stack_size: u64) {
-    let mut result = Vec::new();
+/// Leaf functions that keep everything in registers can skip setting up a stack frame.
+fn needs_frame_pointer(instruction: &asm::Instruction) -> bool {
+    match instruction {
+        asm::Instruction::Mov { src, dst, .. }
+        | asm::Instruction::Movsx { src, dst, .. }
+        | asm::Instruction::MovZeroExtend { src, dst, .. }
+        | asm::Instruction::Lea { src, dst }
+        | asm::Instruction::Binary { src, dst, .. }
+        | asm::Instruction::Cmp { src, dst, .. }
+        | asm::Instruction::Cvttsd2si { src, dst, .. }
+        | asm::Instruction::Cvtsi2sd { src, dst, .. } => {
+            matches!(src, asm::Operand::Stack(_)) || matches!(dst, asm::Operand::Stack(_))
+        }
+        asm::Instruction::Unary { dst: op, .. }
+        | asm::Instruction::Idiv { operand: op, .. }
+        | asm::Instruction::Div { operand: op, .. }
+        | asm::Instruction::Sal { dst: op, .. }
+        | asm::Instruction::Sar { dst: op, .. }
+        | asm::Instruction::Shr { dst: op, .. }
+        | asm::Instruction::SetCC { dst: op, .. }
+        | asm::Instruction::Push(op) => matches!(op, asm::Operand::Stack(_)),
+        asm::Instruction::AllocateStack(_)
+        | asm::Instruction::Call { .. }
+        | asm::Instruction::IndirectCall(_) => true,
+        asm::Instruction::Cdq(_)
+        | asm::Instruction::Jmp { .. }
+        | asm::Instruction::JmpCC { .. }
+        | asm::Instruction::JmpTable { .. }
+        | asm::Instruction::Label(_)
+        | asm::Instruction::DeallocateStack(_)
+        | asm::Instruction::Pop(_)
+        | asm::Instruction::Ret
+        | asm::Instruction::SourceLine(_)
+        | asm::Instruction::Comment(_) => false,
+    }
+}
 
-    result.push(asm::Instruction::AllocateStack(
-        stack_size.next_multiple_of(16),
-    ));
+fn is_memory(operand: &asm::Operand) -> bool {
+    matches!(
+        operand,
+        asm::Operand::Stack(_)
+            | asm::Operand::Memory(..)
+            | asm::Operand::Indexed { .. }
+            | asm::Operand::Data(_)
+    )
+}
 
-    for ins in instructions.iter() {
-        match ins {
-            asm::Instruction::Mov {
-                src: src @ (asm::Operand::Stack(_) | asm::Operand::Data(_)),
-                dst: dst @ (asm::Operand::Stack(_) | asm::Operand::Data(_)),
-            } => {
-                result.push(asm::Instruction::Mov {
-                    src: src.clone(),
-                    dst: asm::Operand::Reg(asm::Reg::R10),
-                });
+/// Only `mov` into a register can take an immediate that doesn't fit in 32 bits.
+fn is_large_immediate(operand: &asm::Operand) -> bool {
+    matches!(operand, asm::Operand::Imm(n) if i32::try_from(*n).is_err())
+}
+
+/// `cmp` can't have an immediate destination, so `cmp %eax, $5` becomes `cmp $5, %eax` with the
+/// conditions reading its flags mirrored, which saves moving the immediate into a register.
+///
+/// Only conditions in the moves, `set`s and jumps right after the comparison are rewritten, so a
+/// label among them, which other flags could reach, keeps the comparison as it is.
+fn swap_immediate_comparisons(instructions: &mut [asm::Instruction]) {
+    for i in 0..instructions.len() {
+        let asm::Instruction::Cmp {
+            ty,
+            src,
+            dst: dst @ asm::Operand::Imm(_),
+        } = &instructions[i]
+        else {
+            continue;
+        };
+
+        if *ty == asm::AssemblyType::Double || matches!(src, asm::Operand::Imm(_)) {
+            continue;
+        }
+
+        let end = instructions[i + 1..]
+            .iter()
+            .position(|ins| {
+                !matches!(
+                    ins,
+                    asm::Instruction::Mov { .. }
+                        | asm::Instruction::SetCC { .. }
+                        | asm::Instruction::JmpCC { .. }
+                        | asm::Instruction::SourceLine(_)
+                        | asm::Instruction::Comment(_)
+                )
+            })
+            .map_or(instructions.len(), |offset| i + 1 + offset);
+
+        if let Some(asm::Instruction::Label(_)) = instructions.get(end) {
+            continue;
+        }
+
+        instructions[i] = asm::Instruction::Cmp {
+            ty: *ty,
+            src: dst.clone(),
+            dst: src.clone(),
+        };
+
+        for ins in &mut instructions[i + 1..end] {
+            if let asm::Instruction::SetCC { cc, .. } | asm::Instruction::JmpCC { cc, .. } = ins {
+                *cc = cc.swapped();
+            }
+        }
+    }
+}
+
+/// A zero-extended constant is just the constant, so it's fixed up like any other move of it.
+fn replace_zero_extended_constants(instructions: &mut [asm::Instruction]) {
+    for ins in instructions {
+        if let asm::Instruction::MovZeroExtend {
+            dst_ty,
+            src: src @ asm::Operand::Imm(_),
+            dst,
+            ..
+        } = ins
+        {
+            *ins = asm::Instruction::Mov {
+                ty: *dst_ty,
+                src: src.clone(),
+                dst: dst.clone(),
+            };
+        }
+    }
+}
+
+fn fix_up_instructions(
+    instructions: &mut Vec<asm::Instruction>,
+    stack_size: u64,
+    callee_saved: &[asm::Reg],
+) {
+    swap_immediate_comparisons(instructions);
+    replace_zero_extended_constants(instructions);
+
+    let mut result = Vec::new();
+
+    // the saved registers are pushed below the stack frame, so %rsp has to stay 16-byte aligned
+    // after those pushes, too. Without a frame, nothing is called, so nothing needs aligning.
+    let saved_size = 8 * callee_saved.len() as u64;
+    let allocation = if instructions.iter().any(needs_frame_pointer) {
+        (stack_size + saved_size).next_multiple_of(16) - saved_size
+    } else {
+        0
+    };
+    if allocation != 0 {
+        result.push(asm::Instruction::AllocateStack(allocation));
+    }
+    result.extend(
+        callee_saved
+            .iter()
+            .map(|reg| asm::Instruction::Push(asm::Operand::Reg(*reg))),
+    );
+
+    for ins in instructions.iter() {
+        match ins {
+            // SSE instructions can't go memory to memory, and their destinations must be registers
+            asm::Instruction::Mov {
+                ty: asm::AssemblyType::Double,
+                src,
+                dst,
+            } if is_memory(src) && is_memory(dst) => {
+                result.push(asm::Instruction::Mov {
+                    ty: asm::AssemblyType::Double,
+                    src: src.clone(),
+                    dst: asm::Operand::Reg(asm::Reg::XMM14),
+                });
                 result.push(asm::Instruction::Mov {
+                    ty: asm::AssemblyType::Double,
+                    src: asm::Operand::Reg(asm::Reg::XMM14),
+                    dst: dst.clone(),
+                });
+            }
+            asm::Instruction::Binary {
+                op,
+                ty: asm::AssemblyType::Double,
+                src,
+                dst,
+            } if !matches!(dst, asm::Operand::Reg(_)) => {
+                result.push(asm::Instruction::Mov {
+                    ty: asm::AssemblyType::Double,
+                    src: dst.clone(),
+                    dst: asm::Operand::Reg(asm::Reg::XMM15),
+                });
+                result.push(asm::Instruction::Binary {
+                    op: *op,
+                    ty: asm::AssemblyType::Double,
+                    src: src.clone(),
+                    dst: asm::Operand::Reg(asm::Reg::XMM15),
+                });
+                result.push(asm::Instruction::Mov {
+                    ty: asm::AssemblyType::Double,
+                    src: asm::Operand::Reg(asm::Reg::XMM15),
+                    dst: dst.clone(),
+                });
+            }
+            asm::Instruction::Cmp {
+                ty: asm::AssemblyType::Double,
+                src,
+                dst,
+            } if !matches!(dst, asm::Operand::Reg(_)) => {
+                result.push(asm::Instruction::Mov {
+                    ty: asm::AssemblyType::Double,
+                    src: dst.clone(),
+                    dst: asm::Operand::Reg(asm::Reg::XMM15),
+                });
+                result.push(asm::Instruction::Cmp {
+                    ty: asm::AssemblyType::Double,
+                    src: src.clone(),
+                    dst: asm::Operand::Reg(asm::Reg::XMM15),
+                });
+            }
+            asm::Instruction::Cvttsd2si { dst_ty, src, dst } if is_memory(dst) => {
+                result.push(asm::Instruction::Cvttsd2si {
+                    dst_ty: *dst_ty,
+                    src: src.clone(),
+                    dst: asm::Operand::Reg(asm::Reg::R11),
+                });
+                result.push(asm::Instruction::Mov {
+                    ty: *dst_ty,
+                    src: asm::Operand::Reg(asm::Reg::R11),
+                    dst: dst.clone(),
+                });
+            }
+            asm::Instruction::Cvtsi2sd { src_ty, src, dst }
+                if matches!(src, asm::Operand::Imm(_)) || !matches!(dst, asm::Operand::Reg(_)) =>
+            {
+                let src = if let asm::Operand::Imm(_) = src {
+                    result.push(asm::Instruction::Mov {
+                        ty: *src_ty,
+                        src: src.clone(),
+                        dst: asm::Operand::Reg(asm::Reg::R10),
+                    });
+                    asm::Operand::Reg(asm::Reg::R10)
+                } else {
+                    src.clone()
+                };
+
+                if let asm::Operand::Reg(_) = dst {
+                    result.push(asm::Instruction::Cvtsi2sd {
+                        src_ty: *src_ty,
+                        src,
+                        dst: dst.clone(),
+                    });
+                } else {
+                    result.push(asm::Instruction::Cvtsi2sd {
+                        src_ty: *src_ty,
+                        src,
+                        dst: asm::Operand::Reg(asm::Reg::XMM15),
+                    });
+                    result.push(asm::Instruction::Mov {
+                        ty: asm::AssemblyType::Double,
+                        src: asm::Operand::Reg(asm::Reg::XMM15),
+                        dst: dst.clone(),
+                    });
+                }
+            }
+            asm::Instruction::Mov { ty, src, dst }
+                if (is_memory(src) || is_large_immediate(src)) && is_memory(dst) =>
+            {
+                result.push(asm::Instruction::Mov {
+                    ty: *ty,
+                    src: src.clone(),
+                    dst: asm::Operand::Reg(asm::Reg::R10),
+                });
+                result.push(asm::Instruction::Mov {
+                    ty: *ty,
                     src: asm::Operand::Reg(asm::Reg::R10),
                     dst: dst.clone(),
                 });
             }
-            asm::Instruction::Idiv(value @ asm::Operand::Imm(_)) => {
+            asm::Instruction::Mov {
+                ty: asm::AssemblyType::Longword,
+                src: asm::Operand::Imm(n),
+                dst,
+            } if i32::try_from(*n).is_err() => {
+                result.push(asm::Instruction::Mov {
+                    ty: asm::AssemblyType::Longword,
+                    src: asm::Operand::Imm(*n as i32 as i64),
+                    dst: dst.clone(),
+                });
+            }
+            asm::Instruction::Mov {
+                ty: asm::AssemblyType::Byte,
+                src: asm::Operand::Imm(n),
+                dst,
+            } if i8::try_from(*n).is_err() => {
+                result.push(asm::Instruction::Mov {
+                    ty: asm::AssemblyType::Byte,
+                    src: asm::Operand::Imm(*n as i8 as i64),
+                    dst: dst.clone(),
+                });
+            }
+            // constants are already wrapped to their type, so sign-extending one is just the constant
+            asm::Instruction::Movsx {
+                dst_ty,
+                src: src @ asm::Operand::Imm(_),
+                dst,
+                ..
+            } => {
+                result.push(asm::Instruction::Mov {
+                    ty: *dst_ty,
+                    src: src.clone(),
+                    dst: dst.clone(),
+                });
+            }
+            asm::Instruction::Movsx {
+                src_ty,
+                dst_ty,
+                src,
+                dst,
+            } if is_memory(dst) => {
+                result.push(asm::Instruction::Movsx {
+                    src_ty: *src_ty,
+                    dst_ty: *dst_ty,
+                    src: src.clone(),
+                    dst: asm::Operand::Reg(asm::Reg::R11),
+                });
+                result.push(asm::Instruction::Mov {
+                    ty: *dst_ty,
+                    src: asm::Operand::Reg(asm::Reg::R11),
+                    dst: dst.clone(),
+                });
+            }
+            asm::Instruction::MovZeroExtend {
+                src_ty,
+                dst_ty,
+                src,
+                dst,
+            } if is_memory(dst) => {
+                result.push(asm::Instruction::MovZeroExtend {
+                    src_ty: *src_ty,
+                    dst_ty: *dst_ty,
+                    src: src.clone(),
+                    dst: asm::Operand::Reg(asm::Reg::R11),
+                });
+                result.push(asm::Instruction::Mov {
+                    ty: *dst_ty,
+                    src: asm::Operand::Reg(asm::Reg::R11),
+                    dst: dst.clone(),
+                });
+            }
+            asm::Instruction::Lea { src, dst } if is_memory(dst) => {
+                result.push(asm::Instruction::Lea {
+                    src: src.clone(),
+                    dst: asm::Operand::Reg(asm::Reg::R11),
+                });
+                result.push(asm::Instruction::Mov {
+                    ty: asm::AssemblyType::Quadword,
+                    src: asm::Operand::Reg(asm::Reg::R11),
+                    dst: dst.clone(),
+                });
+            }
+            asm::Instruction::Idiv {
+                ty,
+                operand: value @ asm::Operand::Imm(_),
+            } => {
+                result.push(asm::Instruction::Mov {
+                    ty: *ty,
+                    src: value.clone(),
+                    dst: asm::Operand::Reg(asm::Reg::R10),
+                });
+                result.push(asm::Instruction::Idiv {
+                    ty: *ty,
+                    operand: asm::Operand::Reg(asm::Reg::R10),
+                });
+            }
+            asm::Instruction::Div {
+                ty,
+                operand: value @ asm::Operand::Imm(_),
+            } => {
                 result.push(asm::Instruction::Mov {
+                    ty: *ty,
                     src: value.clone(),
                     dst: asm::Operand::Reg(asm::Reg::R10),
                 });
-                result.push(asm::Instruction::Idiv(asm::Operand::Reg(asm::Reg::R10)));
+                result.push(asm::Instruction::Div {
+                    ty: *ty,
+                    operand: asm::Operand::Reg(asm::Reg::R10),
+                });
             }
             asm::Instruction::Binary {
                 op:
@@ -427,63 +1422,112 @@ fn fix_up_instructions(instructions: &mut Vec<asm::Instruction>, stack_size: u64
                     | asm::BinaryOperator::And
                     | asm::BinaryOperator::Or
                     | asm::BinaryOperator::Xor),
-                src: src @ (asm::Operand::Stack(_) | asm::Operand::Data(_)),
-                dst: dst @ (asm::Operand::Stack(_) | asm::Operand::Data(_)),
-            } => {
+                ty,
+                src,
+                dst,
+            } if (is_memory(src) && is_memory(dst)) || is_large_immediate(src) => {
                 result.push(asm::Instruction::Mov {
+                    ty: *ty,
                     src: src.clone(),
                     dst: asm::Operand::Reg(asm::Reg::R10),
                 });
                 result.push(asm::Instruction::Binary {
                     op: *op,
+                    ty: *ty,
                     src: asm::Operand::Reg(asm::Reg::R10),
                     dst: dst.clone(),
                 });
             }
             asm::Instruction::Binary {
                 op: asm::BinaryOperator::Mult,
+                ty,
                 src,
-                dst: dst @ (asm::Operand::Stack(_) | asm::Operand::Data(_)),
-            } => {
+                dst,
+            } if is_memory(dst) || is_large_immediate(src) => {
+                let src = if is_large_immediate(src) {
+                    result.push(asm::Instruction::Mov {
+                        ty: *ty,
+                        src: src.clone(),
+                        dst: asm::Operand::Reg(asm::Reg::R10),
+                    });
+                    asm::Operand::Reg(asm::Reg::R10)
+                } else {
+                    src.clone()
+                };
+
                 result.push(asm::Instruction::Mov {
+                    ty: *ty,
                     src: dst.clone(),
                     dst: asm::Operand::Reg(asm::Reg::R11),
                 });
                 result.push(asm::Instruction::Binary {
                     op: asm::BinaryOperator::Mult,
-                    src: src.clone(),
+                    ty: *ty,
+                    src,
                     dst: asm::Operand::Reg(asm::Reg::R11),
                 });
                 result.push(asm::Instruction::Mov {
+                    ty: *ty,
                     src: asm::Operand::Reg(asm::Reg::R11),
                     dst: dst.clone(),
                 });
             }
-            asm::Instruction::Cmp {
-                src: src @ (asm::Operand::Stack(_) | asm::Operand::Data(_)),
-                dst: dst @ (asm::Operand::Stack(_) | asm::Operand::Data(_)),
-            } => {
+            asm::Instruction::Cmp { ty, src, dst }
+                if (is_memory(src) && is_memory(dst))
+                    || is_large_immediate(src)
+                    || matches!(dst, asm::Operand::Imm(_)) =>
+            {
+                let src = if (is_memory(src) && is_memory(dst)) || is_large_immediate(src) {
+                    result.push(asm::Instruction::Mov {
+                        ty: *ty,
+                        src: src.clone(),
+                        dst: asm::Operand::Reg(asm::Reg::R10),
+                    });
+                    asm::Operand::Reg(asm::Reg::R10)
+                } else {
+                    src.clone()
+                };
+
+                let dst = if let asm::Operand::Imm(_) = dst {
+                    result.push(asm::Instruction::Mov {
+                        ty: *ty,
+                        src: dst.clone(),
+                        dst: asm::Operand::Reg(asm::Reg::R11),
+                    });
+                    asm::Operand::Reg(asm::Reg::R11)
+                } else {
+                    dst.clone()
+                };
+
+                result.push(asm::Instruction::Cmp { ty: *ty, src, dst });
+            }
+            // a constant function pointer, e.g. a null one
+            asm::Instruction::IndirectCall(function_ptr @ asm::Operand::Imm(_)) => {
                 result.push(asm::Instruction::Mov {
-                    src: src.clone(),
+                    ty: asm::AssemblyType::Quadword,
+                    src: function_ptr.clone(),
                     dst: asm::Operand::Reg(asm::Reg::R10),
                 });
-                result.push(asm::Instruction::Cmp {
-                    src: asm::Operand::Reg(asm::Reg::R10),
-                    dst: dst.clone(),
-                });
+                result.push(asm::Instruction::IndirectCall(asm::Operand::Reg(
+                    asm::Reg::R10,
+                )));
             }
-            asm::Instruction::Cmp {
-                src,
-                dst: dst @ asm::Operand::Imm(_),
-            } => {
+            asm::Instruction::Push(value) if is_large_immediate(value) => {
                 result.push(asm::Instruction::Mov {
-                    src: dst.clone(),
-                    dst: asm::Operand::Reg(asm::Reg::R11),
+                    ty: asm::AssemblyType::Quadword,
+                    src: value.clone(),
+                    dst: asm::Operand::Reg(asm::Reg::R10),
                 });
-                result.push(asm::Instruction::Cmp {
-                    src: src.clone(),
-                    dst: asm::Operand::Reg(asm::Reg::R11),
-                })
+                result.push(asm::Instruction::Push(asm::Operand::Reg(asm::Reg::R10)));
+            }
+            asm::Instruction::Ret => {
+                result.extend(
+                    callee_saved
+                        .iter()
+                        .rev()
+                        .map(|reg| asm::Instruction::Pop(*reg)),
+                );
+                result.push(asm::Instruction::Ret);
             }
 
             _ => result.push(ins.clone()),
@@ -497,6 +1541,39 @@ fn fix_up_instructions(instructions: &mut Vec<asm::Instruction>, stack_size: u64
 mod tests {
     use super::*;
 
+    use crate::compiler::optimization::OptLevel;
+
+    fn generate_from_source(source: &str, optimizations: Optimizations) -> asm::Program {
+        let tokens = crate::compiler::lexer::tokenize(source).unwrap();
+        let ast = crate::compiler::parser::parse(&tokens, false).unwrap();
+        let (ast, mut symbols, _) = crate::compiler::semantic::analyze(&ast, false).unwrap();
+        let tacky_program = crate::compiler::tackygen::generate(&ast, &mut symbols);
+
+        generate(&tacky_program, &symbols, optimizations, false)
+    }
+
+    fn comments(program: &asm::Program) -> Vec<String> {
+        let asm::TopLevelItem::FunctionDefinition(fd) = &program.items[0] else {
+            panic!("expected a function definition");
+        };
+
+        fd.instructions
+            .iter()
+            .filter_map(|ins| match ins {
+                asm::Instruction::Comment(text) => Some(text.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Every pseudo gets a stack slot, which keeps the expected operands predictable.
+    fn naive() -> Optimizations {
+        Optimizations {
+            allocate_registers: false,
+            ..Optimizations::from(OptLevel::O0)
+        }
+    }
+
     #[test]
     fn test_generate() {
         let tacky_program = tacky::Program {
@@ -507,12 +1584,34 @@ mod tests {
                     },
                     global: true,
                     parameters: vec![],
-                    instructions: vec![tacky::Instruction::Return(tacky::Value::Constant(42))],
+                    instructions: vec![tacky::Instruction::Return(Some(tacky::Value::Constant(
+                        tacky::Constant::Int(42),
+                    )))],
                 },
             )],
         };
 
-        let program = generate(&tacky_program, &SymbolTable::new());
+        let mut symbols = SymbolTable::new();
+        symbols.insert(
+            "main".to_string(),
+            Symbol {
+                ty: Type::Function {
+                    return_type: Box::new(Type::Int),
+                    parameters: vec![],
+                },
+                attrs: SymbolAttributes::Function {
+                    defined: true,
+                    global: true,
+                },
+            },
+        );
+
+        let program = generate(
+            &tacky_program,
+            &symbols,
+            Optimizations::from(OptLevel::O0),
+            false,
+        );
 
         assert_eq!(
             program,
@@ -523,9 +1622,10 @@ mod tests {
                             identifier: "main".to_string()
                         },
                         global: true,
+                        frame_pointer: false,
                         instructions: vec![
-                            asm::Instruction::AllocateStack(0),
                             asm::Instruction::Mov {
+                                ty: asm::AssemblyType::Longword,
                                 src: asm::Operand::Imm(42),
                                 dst: asm::Operand::Reg(asm::Reg::AX),
                             },
@@ -536,4 +1636,386 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_generate_long_arithmetic() {
+        let source = "long f(long a, long b) { return a * b + 4294967296L; }";
+
+        let program = generate_from_source(source, naive());
+        let asm::TopLevelItem::FunctionDefinition(fd) = &program.items[0] else {
+            panic!("expected a function definition");
+        };
+
+        // four 8-byte slots: two parameters and two temporaries
+        assert_eq!(fd.instructions[0], asm::Instruction::AllocateStack(32));
+
+        assert!(fd.instructions.iter().all(|ins| match ins {
+            asm::Instruction::Mov { ty, .. } | asm::Instruction::Binary { ty, .. } =>
+                *ty == asm::AssemblyType::Quadword,
+            _ => true,
+        }));
+
+        assert!(fd.instructions.windows(2).any(|w| w
+            == [
+                asm::Instruction::Mov {
+                    ty: asm::AssemblyType::Quadword,
+                    src: asm::Operand::Imm(4294967296),
+                    dst: asm::Operand::Reg(asm::Reg::R10),
+                },
+                asm::Instruction::Binary {
+                    op: asm::BinaryOperator::Add,
+                    ty: asm::AssemblyType::Quadword,
+                    src: asm::Operand::Reg(asm::Reg::R10),
+                    dst: asm::Operand::Stack(-32),
+                },
+            ]));
+    }
+
+    #[test]
+    fn test_generate_casts() {
+        let source = "long widen(int x) { return x; } int narrow(long x) { return x; }";
+
+        let program = generate_from_source(source, naive());
+        let [asm::TopLevelItem::FunctionDefinition(widen), asm::TopLevelItem::FunctionDefinition(narrow)] =
+            &program.items[..]
+        else {
+            panic!("expected two function definitions");
+        };
+
+        assert!(widen.instructions.windows(2).any(|w| w
+            == [
+                asm::Instruction::Movsx {
+                    src_ty: asm::AssemblyType::Longword,
+                    dst_ty: asm::AssemblyType::Quadword,
+                    src: asm::Operand::Stack(-4),
+                    dst: asm::Operand::Reg(asm::Reg::R11),
+                },
+                asm::Instruction::Mov {
+                    ty: asm::AssemblyType::Quadword,
+                    src: asm::Operand::Reg(asm::Reg::R11),
+                    dst: asm::Operand::Stack(-16),
+                },
+            ]));
+
+        assert!(narrow.instructions.windows(2).any(|w| w
+            == [
+                asm::Instruction::Mov {
+                    ty: asm::AssemblyType::Longword,
+                    src: asm::Operand::Stack(-8),
+                    dst: asm::Operand::Reg(asm::Reg::R10),
+                },
+                asm::Instruction::Mov {
+                    ty: asm::AssemblyType::Longword,
+                    src: asm::Operand::Reg(asm::Reg::R10),
+                    dst: asm::Operand::Stack(-12),
+                },
+            ]));
+    }
+
+    #[test]
+    fn test_generate_narrow_arguments() {
+        let source = "int f(signed char a, unsigned char b, long c);
+int g(signed char x, unsigned char y) { return f(x, y, 4294967296L); }";
+
+        let program = generate_from_source(source, naive());
+        let asm::TopLevelItem::FunctionDefinition(g) = &program.items[0] else {
+            panic!("expected a function definition");
+        };
+
+        // bytes are passed extended to 32 bits, and longs as quadwords
+        assert!(g.instructions.windows(3).any(|w| w
+            == [
+                asm::Instruction::Movsx {
+                    src_ty: asm::AssemblyType::Byte,
+                    dst_ty: asm::AssemblyType::Longword,
+                    src: asm::Operand::Stack(-1),
+                    dst: asm::Operand::Reg(asm::Reg::DI),
+                },
+                asm::Instruction::MovZeroExtend {
+                    src_ty: asm::AssemblyType::Byte,
+                    dst_ty: asm::AssemblyType::Longword,
+                    src: asm::Operand::Stack(-2),
+                    dst: asm::Operand::Reg(asm::Reg::SI),
+                },
+                asm::Instruction::Mov {
+                    ty: asm::AssemblyType::Quadword,
+                    src: asm::Operand::Imm(4294967296),
+                    dst: asm::Operand::Reg(asm::Reg::DX),
+                },
+            ]));
+    }
+
+    #[test]
+    fn test_generate_allocates_registers() {
+        let source = "long f(long a, long b) { return a * b + 4294967296L; }";
+
+        let program = generate_from_source(
+            source,
+            Optimizations {
+                allocate_registers: true,
+                ..Optimizations::from(OptLevel::O0)
+            },
+        );
+        let asm::TopLevelItem::FunctionDefinition(fd) = &program.items[0] else {
+            panic!("expected a function definition");
+        };
+
+        assert!(!fd.instructions.iter().any(|ins| match ins {
+            asm::Instruction::Mov { src, dst, .. } | asm::Instruction::Binary { src, dst, .. } =>
+                matches!(src, asm::Operand::Stack(_)) || matches!(dst, asm::Operand::Stack(_)),
+            _ => false,
+        }));
+
+        // every callee-saved register that was pushed in the prologue is popped before returning
+        let pushed = fd
+            .instructions
+            .iter()
+            .skip_while(|ins| matches!(ins, asm::Instruction::AllocateStack(_)))
+            .map_while(|ins| match ins {
+                asm::Instruction::Push(asm::Operand::Reg(reg)) => Some(*reg),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert!(!pushed.is_empty());
+
+        let popped = fd.instructions[fd.instructions.len() - 1 - pushed.len()..]
+            .iter()
+            .filter_map(|ins| match ins {
+                asm::Instruction::Pop(reg) => Some(*reg),
+                _ => None,
+            })
+            .rev()
+            .collect::<Vec<_>>();
+        assert_eq!(pushed, popped);
+
+        // a leaf function with everything in registers needs no stack frame
+        assert!(!fd.frame_pointer);
+    }
+
+    #[test]
+    fn test_generate_annotates() {
+        let source = "int f(int a) { if (a) return a + 1; return -a; }";
+
+        let tokens = crate::compiler::lexer::tokenize(source).unwrap();
+        let ast = crate::compiler::parser::parse(&tokens, false).unwrap();
+        let (ast, mut symbols, _) = crate::compiler::semantic::analyze(&ast, false).unwrap();
+        let tacky_program = crate::compiler::tackygen::generate(&ast, &mut symbols);
+
+        let annotated = generate(&tacky_program, &symbols, naive(), true);
+        assert_eq!(
+            comments(&annotated),
+            vec![
+                "jump_if_zero sem.var.0.a, tac.label.0.if_end",
+                "tac.var.0 = sem.var.0.a + 1",
+                "return tac.var.0",
+                "tac.var.1 = -sem.var.0.a",
+                "return tac.var.1",
+                "return 0",
+            ]
+        );
+
+        let plain = generate(&tacky_program, &symbols, naive(), false);
+        assert!(comments(&plain).is_empty());
+    }
+
+    #[test]
+    fn test_swap_immediate_comparisons() {
+        use asm::{AssemblyType::Longword, ConditionCode, Instruction, Operand, Reg};
+
+        let label = |identifier: &str| asm::Label {
+            identifier: identifier.to_string(),
+        };
+        let cmp = |src, dst| Instruction::Cmp {
+            ty: Longword,
+            src,
+            dst,
+        };
+        let set = |cc| Instruction::SetCC {
+            cc,
+            dst: Operand::Stack(-4),
+        };
+        let jump = |cc| Instruction::JmpCC {
+            cc,
+            target: label("target"),
+        };
+        let clear = Instruction::Mov {
+            ty: Longword,
+            src: Operand::Imm(0),
+            dst: Operand::Stack(-4),
+        };
+
+        // `5 < x`, then `5 >= x` jumping
+        let mut instructions = vec![
+            cmp(Operand::Reg(Reg::CX), Operand::Imm(5)),
+            clear.clone(),
+            set(ConditionCode::L),
+            cmp(Operand::Stack(-8), Operand::Imm(5)),
+            jump(ConditionCode::AE),
+            jump(ConditionCode::E),
+            Instruction::Ret,
+        ];
+        fix_up_instructions(&mut instructions, 0, &[]);
+        assert_eq!(
+            instructions,
+            vec![
+                cmp(Operand::Imm(5), Operand::Reg(Reg::CX)),
+                clear.clone(),
+                set(ConditionCode::G),
+                cmp(Operand::Imm(5), Operand::Stack(-8)),
+                jump(ConditionCode::BE),
+                jump(ConditionCode::E),
+                Instruction::Ret,
+            ]
+        );
+
+        // the flags might be read after the label by code that jumps there
+        let mut instructions = vec![
+            cmp(Operand::Reg(Reg::CX), Operand::Imm(5)),
+            Instruction::Label(label("target")),
+            set(ConditionCode::L),
+            Instruction::Ret,
+        ];
+        fix_up_instructions(&mut instructions, 0, &[]);
+        assert_eq!(
+            instructions,
+            vec![
+                Instruction::Mov {
+                    ty: Longword,
+                    src: Operand::Imm(5),
+                    dst: Operand::Reg(Reg::R11),
+                },
+                cmp(Operand::Reg(Reg::CX), Operand::Reg(Reg::R11)),
+                Instruction::Label(label("target")),
+                set(ConditionCode::L),
+                Instruction::Ret,
+            ]
+        );
+
+        // two immediates still need a register
+        let mut instructions = vec![
+            cmp(Operand::Imm(1), Operand::Imm(2)),
+            set(ConditionCode::G),
+            Instruction::Ret,
+        ];
+        fix_up_instructions(&mut instructions, 0, &[]);
+        assert_eq!(
+            instructions,
+            vec![
+                Instruction::Mov {
+                    ty: Longword,
+                    src: Operand::Imm(2),
+                    dst: Operand::Reg(Reg::R11),
+                },
+                cmp(Operand::Imm(1), Operand::Reg(Reg::R11)),
+                set(ConditionCode::G),
+                Instruction::Ret,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fix_up_large_immediates() {
+        use asm::{AssemblyType::Quadword, BinaryOperator, Instruction, Operand, Reg};
+
+        let large = Operand::Imm(i64::from(i32::MAX) + 1);
+        let to_r10 = Instruction::Mov {
+            ty: Quadword,
+            src: large.clone(),
+            dst: Operand::Reg(Reg::R10),
+        };
+
+        let mut instructions = vec![
+            Instruction::Cmp {
+                ty: Quadword,
+                src: large.clone(),
+                dst: Operand::Stack(-8),
+            },
+            Instruction::Mov {
+                ty: Quadword,
+                src: large.clone(),
+                dst: Operand::Stack(-8),
+            },
+            Instruction::Binary {
+                op: BinaryOperator::Add,
+                ty: Quadword,
+                src: large.clone(),
+                dst: Operand::Stack(-8),
+            },
+            Instruction::Push(large.clone()),
+            // fits in a sign-extended 32-bit immediate
+            Instruction::Push(Operand::Imm(i64::from(i32::MIN))),
+        ];
+        fix_up_instructions(&mut instructions, 0, &[]);
+        assert_eq!(
+            instructions,
+            vec![
+                to_r10.clone(),
+                Instruction::Cmp {
+                    ty: Quadword,
+                    src: Operand::Reg(Reg::R10),
+                    dst: Operand::Stack(-8),
+                },
+                to_r10.clone(),
+                Instruction::Mov {
+                    ty: Quadword,
+                    src: Operand::Reg(Reg::R10),
+                    dst: Operand::Stack(-8),
+                },
+                to_r10.clone(),
+                Instruction::Binary {
+                    op: BinaryOperator::Add,
+                    ty: Quadword,
+                    src: Operand::Reg(Reg::R10),
+                    dst: Operand::Stack(-8),
+                },
+                to_r10,
+                Instruction::Push(Operand::Reg(Reg::R10)),
+                Instruction::Push(Operand::Imm(i64::from(i32::MIN))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fix_up_large_immediate_moves() {
+        use asm::{AssemblyType::Quadword, Instruction, Operand, Reg};
+
+        let mov = |n: i64, dst| Instruction::Mov {
+            ty: Quadword,
+            src: Operand::Imm(n),
+            dst,
+        };
+        let data = Operand::Data("x".to_string());
+        let small = i64::from(i32::MAX);
+        let large = 5_000_000_000;
+        let negative = i64::from(i32::MIN) - 1;
+
+        let mut instructions = vec![
+            mov(large, data.clone()),
+            mov(negative, Operand::Stack(-8)),
+            // only the magnitude matters, not the type
+            mov(small, Operand::Stack(-8)),
+            // `movabsq` can load any immediate into a register
+            mov(large, Operand::Reg(Reg::AX)),
+        ];
+        fix_up_instructions(&mut instructions, 0, &[]);
+        assert_eq!(
+            instructions,
+            vec![
+                mov(large, Operand::Reg(Reg::R10)),
+                Instruction::Mov {
+                    ty: Quadword,
+                    src: Operand::Reg(Reg::R10),
+                    dst: data,
+                },
+                mov(negative, Operand::Reg(Reg::R10)),
+                Instruction::Mov {
+                    ty: Quadword,
+                    src: Operand::Reg(Reg::R10),
+                    dst: Operand::Stack(-8),
+                },
+                mov(small, Operand::Stack(-8)),
+                mov(large, Operand::Reg(Reg::AX)),
+            ]
+        );
+    }
 }
@@ -1,15 +1,42 @@
 use crate::compiler::ast::{Constant, Type};
 
 pub fn convert_constant_to_type(c: &Constant, ty: &Type) -> Constant {
+    if let Constant::ConstantDouble(d) = c {
+        return match ty {
+            Type::Int => Constant::ConstantInt(*d as i32),
+            Type::Long | Type::Pointer(_) => Constant::ConstantLong(*d as i64),
+            Type::UInt => Constant::ConstantUInt(*d as u32),
+            Type::ULong => Constant::ConstantULong(*d as u64),
+            Type::Double => Constant::ConstantDouble(*d),
+            Type::Bool => Constant::ConstantInt((*d != 0.0) as i32),
+            Type::Char | Type::SChar => Constant::ConstantInt(*d as i32 as i8 as i32),
+            Type::UChar => Constant::ConstantInt(*d as i32 as u8 as i32),
+            Type::Void | Type::Array { .. } | Type::Function { .. } => unreachable!(),
+        };
+    }
+
+    // the bit pattern, sign- or zero-extended to 64 bits as appropriate
+    let value = match c {
+        Constant::ConstantInt(n) => *n as i64,
+        Constant::ConstantLong(n) => *n,
+        Constant::ConstantUInt(n) => *n as i64,
+        Constant::ConstantULong(n) => *n as i64,
+        Constant::ConstantDouble(_) => unreachable!(),
+    };
+
     match ty {
-        Type::Int => match c {
-            Constant::ConstantInt(n) => Constant::ConstantInt(*n),
-            Constant::ConstantLong(n) => Constant::ConstantInt(*n as i32),
-        },
-        Type::Long => match c {
-            Constant::ConstantInt(n) => Constant::ConstantLong(*n as i64),
-            Constant::ConstantLong(n) => Constant::ConstantLong(*n),
+        Type::Int => Constant::ConstantInt(value as i32),
+        Type::Long | Type::Pointer(_) => Constant::ConstantLong(value),
+        Type::UInt => Constant::ConstantUInt(value as u32),
+        Type::ULong => Constant::ConstantULong(value as u64),
+        Type::Double => match c {
+            Constant::ConstantULong(n) => Constant::ConstantDouble(*n as f64),
+            Constant::ConstantUInt(n) => Constant::ConstantDouble(*n as f64),
+            _ => Constant::ConstantDouble(value as f64),
         },
-        Type::Function { .. } => unreachable!(),
+        Type::Bool => Constant::ConstantInt((value != 0) as i32),
+        Type::Char | Type::SChar => Constant::ConstantInt(value as i8 as i32),
+        Type::UChar => Constant::ConstantInt(value as u8 as i32),
+        Type::Void | Type::Array { .. } | Type::Function { .. } => unreachable!(),
     }
 }
@@ -1,32 +1,83 @@
 use crate::compiler::asm::{
-    BinaryOperator, ConditionCode, FunctionDefinition, Instruction, Label, Operand, Program, Reg,
-    TopLevelItem, UnaryOperator,
+    AssemblyType, BinaryOperator, ConditionCode, FunctionDefinition, Instruction, JumpTable, Label,
+    Operand, Program, Reg, TopLevelItem, UnaryOperator,
 };
 
-use super::asm::StaticVariable;
+use super::{
+    asm::{StaticConstant, StaticVariable},
+    symbols::SymbolStaticInitial,
+    Target,
+};
+
+/// With a `source_file`, the `.loc` directives of the program refer to it as file 1. With
+/// `ident`, the version of the compiler is recorded in the object file.
+pub fn emit(program: &Program, target: Target, source_file: Option<&str>, ident: bool) -> String {
+    let emitted = emit_program(program, target, ident);
 
-pub fn emit(program: &Program) -> String {
-    emit_program(program)
+    match source_file {
+        Some(source_file) => format!("\t.file\t1 {source_file:?}\n{emitted}"),
+        None => emitted,
+    }
 }
 
-fn emit_program(program: &Program) -> String {
-    program
-        .items
-        .iter()
-        .map(emit_top_level_item)
+/// The sections in the order their items are emitted in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Section {
+    Text,
+    ReadOnlyData,
+    Data,
+    Bss,
+}
+
+fn section(item: &TopLevelItem) -> Section {
+    match item {
+        TopLevelItem::FunctionDefinition(_) => Section::Text,
+        TopLevelItem::StaticConstant(_) | TopLevelItem::JumpTable(_) => Section::ReadOnlyData,
+        TopLevelItem::StaticVariable(sv) if sv.initial.iter().all(SymbolStaticInitial::is_zero) => {
+            Section::Bss
+        }
+        TopLevelItem::StaticVariable(_) => Section::Data,
+    }
+}
+
+/// Items are grouped by section, keeping their order within each one.
+fn emit_program(program: &Program, target: Target, ident: bool) -> String {
+    let mut items = program.items.iter().collect::<Vec<_>>();
+    items.sort_by_key(|item| section(item));
+
+    let mut items = items
+        .into_iter()
+        .map(|item| emit_top_level_item(item, target))
         .collect::<Vec<_>>()
-        .join("\n")
+        .join("\n");
+
+    if ident {
+        items.push_str(&format!(
+            "\n\t.ident\t\"cco {}\"\n",
+            env!("CARGO_PKG_VERSION")
+        ));
+    }
+
+    match target {
+        Target::MacOs => items,
+        Target::Linux => format!("{items}\n\t.section .note.GNU-stack,\"\",@progbits\n"),
+    }
 }
 
-fn emit_top_level_item(item: &TopLevelItem) -> String {
+fn emit_top_level_item(item: &TopLevelItem, target: Target) -> String {
     match item {
-        TopLevelItem::FunctionDefinition(fd) => emit_function_definition(fd),
-        TopLevelItem::StaticVariable(sv) => emit_static_variable(sv),
+        TopLevelItem::FunctionDefinition(fd) => emit_function_definition(fd, target),
+        TopLevelItem::StaticVariable(sv) => emit_static_variable(sv, target),
+        TopLevelItem::StaticConstant(sc) => emit_static_constant(sc, target),
+        TopLevelItem::JumpTable(jt) => emit_jump_table(jt, target),
     }
 }
 
-fn prefix_identifier(identifier: &str) -> String {
-    format!("_{identifier}",)
+fn prefix_identifier(identifier: &str, target: Target) -> String {
+    match target {
+        Target::MacOs => format!("_{identifier}"),
+        Target::Linux => identifier.to_string(),
+    }
 }
 
 fn build_global_directive(identifier: &str, global: bool) -> String {
@@ -37,134 +88,385 @@ fn build_global_directive(identifier: &str, global: bool) -> String {
     }
 }
 
-fn emit_function_definition(fd: &FunctionDefinition) -> String {
-    let prefixed = prefix_identifier(&fd.function.identifier);
+fn emit_function_definition(fd: &FunctionDefinition, target: Target) -> String {
+    let prefixed = prefix_identifier(&fd.function.identifier, target);
 
     let instructions = fd
         .instructions
         .iter()
-        .map(emit_instruction)
+        .map(|instruction| match instruction {
+            Instruction::Ret if !fd.frame_pointer => "\tret".to_string(),
+            _ => emit_instruction(instruction, target),
+        })
         .collect::<Vec<_>>()
         .join("\n");
 
     let global_directive = build_global_directive(&prefixed, fd.global);
+    let prologue = if fd.frame_pointer {
+        "\tpushq\t%rbp\n\tmovq\t%rsp, %rbp\n"
+    } else {
+        ""
+    };
 
     format!(
         "{global_directive}\t.text
 {prefixed}:
-\tpushq\t%rbp
-\tmovq\t%rsp, %rbp
-{instructions}
+{prologue}{instructions}
 "
     )
 }
 
-fn emit_static_variable(sv: &StaticVariable) -> String {
-    let identifier = prefix_identifier(&sv.variable.identifier);
-    let initial = sv.initial;
+fn emit_static_variable(sv: &StaticVariable, target: Target) -> String {
+    let identifier = prefix_identifier(&sv.variable.identifier, target);
     let global_directive = build_global_directive(&identifier, sv.global);
-    let alignment_directive = "\t.balign 4\n";
+    let alignment_directive = format!("\t.balign {}\n", sv.ty.alignment());
 
-    if initial == 0 {
+    if sv.initial.iter().all(SymbolStaticInitial::is_zero) {
         format!(
             "{global_directive}\t.bss
 {alignment_directive}{identifier}:
-\t.zero 4
-"
+\t.zero {}
+",
+            sv.ty.size()
         )
     } else {
+        let data = sv
+            .initial
+            .iter()
+            .map(emit_static_initial)
+            .collect::<String>();
+
         format!(
             "{global_directive}\t.data
 {alignment_directive}{identifier}:
-\t.long {initial}
-"
+{data}"
         )
     }
 }
 
-fn emit_instruction(instruction: &Instruction) -> String {
+fn emit_static_initial(initial: &SymbolStaticInitial) -> String {
+    match initial {
+        SymbolStaticInitial::Bool(b) => format!("\t.byte {}\n", *b as i32),
+        SymbolStaticInitial::Char(n) => format!("\t.byte {n}\n"),
+        SymbolStaticInitial::UChar(n) => format!("\t.byte {n}\n"),
+        SymbolStaticInitial::Int(n) => format!("\t.long {n}\n"),
+        SymbolStaticInitial::Long(n) => format!("\t.quad {n}\n"),
+        SymbolStaticInitial::UInt(n) => format!("\t.long {n}\n"),
+        SymbolStaticInitial::ULong(n) => format!("\t.quad {n}\n"),
+        // the exact bits, a decimal representation might round differently
+        SymbolStaticInitial::Double(d) => format!("\t.quad {}\n", d.to_bits()),
+        SymbolStaticInitial::Zero(n) => format!("\t.zero {n}\n"),
+    }
+}
+
+fn emit_static_constant(sc: &StaticConstant, target: Target) -> String {
+    let identifier = prefix_identifier(&sc.variable.identifier, target);
+    let section = match (target, sc.alignment) {
+        (Target::MacOs, 8) => "\t.literal8\n".to_string(),
+        (Target::MacOs, _) => "\t.const\n".to_string(),
+        (Target::Linux, _) => "\t.section .rodata\n".to_string(),
+    };
+
+    format!(
+        "{section}\t.balign {}
+{identifier}:
+{}",
+        sc.alignment,
+        emit_static_initial(&sc.initial)
+    )
+}
+
+/// The entries are offsets from the table, so it needs no relocations and can be read-only.
+fn emit_jump_table(jt: &JumpTable, target: Target) -> String {
+    let table = emit_label(&jt.label, target);
+    let section = match target {
+        Target::MacOs => "\t.const\n",
+        Target::Linux => "\t.section .rodata\n",
+    };
+    let entries = jt
+        .targets
+        .iter()
+        .map(|label| format!("\t.long\t{}-{table}\n", emit_label(label, target)))
+        .collect::<String>();
+
+    format!(
+        "{section}\t.balign 4
+{table}:
+{entries}"
+    )
+}
+
+fn emit_instruction(instruction: &Instruction, target: Target) -> String {
     match instruction {
-        Instruction::Mov { src, dst } => {
+        Instruction::Mov { ty, src, dst } => {
+            format!(
+                "\tmov{}\t{}, {}",
+                emit_type_suffix(*ty),
+                emit_operand(src, reg_size(*ty), target),
+                emit_operand(dst, reg_size(*ty), target)
+            )
+        }
+        Instruction::Movsx {
+            src_ty,
+            dst_ty,
+            src,
+            dst,
+        } => {
+            format!(
+                "\tmovs{}{}\t{}, {}",
+                emit_type_suffix(*src_ty),
+                emit_type_suffix(*dst_ty),
+                emit_operand(src, reg_size(*src_ty), target),
+                emit_operand(dst, reg_size(*dst_ty), target)
+            )
+        }
+        Instruction::Lea { src, dst } => {
+            format!(
+                "\tleaq\t{}, {}",
+                emit_operand(src, RegSize::EightBytes, target),
+                emit_operand(dst, RegSize::EightBytes, target)
+            )
+        }
+        Instruction::MovZeroExtend {
+            src_ty: AssemblyType::Byte,
+            dst_ty,
+            src,
+            dst,
+        } => {
+            format!(
+                "\tmovzb{}\t{}, {}",
+                emit_type_suffix(*dst_ty),
+                emit_operand(src, RegSize::OneByte, target),
+                emit_operand(dst, reg_size(*dst_ty), target)
+            )
+        }
+        // writing a 32-bit register clears its upper half
+        Instruction::MovZeroExtend {
+            src_ty: AssemblyType::Longword,
+            dst_ty: AssemblyType::Quadword,
+            src,
+            dst,
+        } => {
             format!(
                 "\tmovl\t{}, {}",
-                emit_operand(src, RegSize::FourBytes),
-                emit_operand(dst, RegSize::FourBytes)
+                emit_operand(src, RegSize::FourBytes, target),
+                emit_operand(dst, RegSize::FourBytes, target)
             )
         }
-        Instruction::Unary { op, dst } => {
+        Instruction::MovZeroExtend { src_ty, .. } => {
+            unreachable!("zero-extending a {src_ty:?} is not supported")
+        }
+        Instruction::Unary { op, ty, dst } => {
             format!(
                 "\t{}\t{}",
-                emit_unary_operator(op),
-                emit_operand(dst, RegSize::FourBytes)
+                emit_unary_operator(op, *ty),
+                emit_operand(dst, reg_size(*ty), target)
             )
         }
-        Instruction::Binary { op, src, dst } => {
+        Instruction::Binary { op, ty, src, dst } => {
             format!(
                 "\t{}\t{}, {}",
-                emit_binary_operator(op),
-                emit_operand(src, RegSize::FourBytes),
-                emit_operand(dst, RegSize::FourBytes)
+                emit_binary_operator(op, *ty),
+                emit_operand(src, reg_size(*ty), target),
+                emit_operand(dst, reg_size(*ty), target)
             )
         }
-        Instruction::Cmp { src, dst } => {
+        Instruction::Cvttsd2si { dst_ty, src, dst } => {
             format!(
-                "\tcmpl\t{}, {}",
-                emit_operand(src, RegSize::FourBytes),
-                emit_operand(dst, RegSize::FourBytes)
+                "\tcvttsd2si{}\t{}, {}",
+                emit_type_suffix(*dst_ty),
+                emit_operand(src, RegSize::EightBytes, target),
+                emit_operand(dst, reg_size(*dst_ty), target)
             )
         }
-        Instruction::Idiv(operand) => {
-            format!("\tidivl\t{}", emit_operand(operand, RegSize::FourBytes))
+        Instruction::Cvtsi2sd { src_ty, src, dst } => {
+            format!(
+                "\tcvtsi2sd{}\t{}, {}",
+                emit_type_suffix(*src_ty),
+                emit_operand(src, reg_size(*src_ty), target),
+                emit_operand(dst, RegSize::EightBytes, target)
+            )
         }
-        Instruction::Cdq => "\tcdq".to_string(),
-        Instruction::Sal(operand) => {
-            format!("\tsall\t%cl, {}", emit_operand(operand, RegSize::FourBytes))
+        Instruction::Cmp {
+            ty: AssemblyType::Double,
+            src,
+            dst,
+        } => {
+            format!(
+                "\tcomisd\t{}, {}",
+                emit_operand(src, RegSize::EightBytes, target),
+                emit_operand(dst, RegSize::EightBytes, target)
+            )
+        }
+        Instruction::Cmp { ty, src, dst } => {
+            format!(
+                "\tcmp{}\t{}, {}",
+                emit_type_suffix(*ty),
+                emit_operand(src, reg_size(*ty), target),
+                emit_operand(dst, reg_size(*ty), target)
+            )
+        }
+        Instruction::Idiv { ty, operand } => {
+            format!(
+                "\tidiv{}\t{}",
+                emit_type_suffix(*ty),
+                emit_operand(operand, reg_size(*ty), target)
+            )
         }
-        Instruction::Sar(operand) => {
-            format!("\tsarl\t%cl, {}", emit_operand(operand, RegSize::FourBytes))
+        Instruction::Div { ty, operand } => {
+            format!(
+                "\tdiv{}\t{}",
+                emit_type_suffix(*ty),
+                emit_operand(operand, reg_size(*ty), target)
+            )
         }
-        Instruction::Jmp { target } => format!("\tjmp\t\t{}", emit_label(target)),
-        Instruction::JmpCC { cc, target } => {
-            format!("\tj{}\t\t{}", emit_condition_code(cc), emit_label(target))
+        Instruction::Cdq(AssemblyType::Longword) => "\tcdq".to_string(),
+        Instruction::Cdq(AssemblyType::Quadword) => "\tcqo".to_string(),
+        Instruction::Cdq(
+            ty @ (AssemblyType::Byte | AssemblyType::Double | AssemblyType::ByteArray { .. }),
+        ) => {
+            unreachable!("{ty:?} is never divided")
+        }
+        Instruction::Sal { ty, dst } => {
+            format!(
+                "\tsal{}\t%cl, {}",
+                emit_type_suffix(*ty),
+                emit_operand(dst, reg_size(*ty), target)
+            )
+        }
+        Instruction::Sar { ty, dst } => {
+            format!(
+                "\tsar{}\t%cl, {}",
+                emit_type_suffix(*ty),
+                emit_operand(dst, reg_size(*ty), target)
+            )
+        }
+        Instruction::Shr { ty, dst } => {
+            format!(
+                "\tshr{}\t%cl, {}",
+                emit_type_suffix(*ty),
+                emit_operand(dst, reg_size(*ty), target)
+            )
+        }
+        Instruction::Jmp { target: label } => format!("\tjmp\t\t{}", emit_label(label, target)),
+        Instruction::JmpCC { cc, target: label } => {
+            format!(
+                "\tj{}\t\t{}",
+                emit_condition_code(cc),
+                emit_label(label, target)
+            )
+        }
+        Instruction::JmpTable { table, index } => {
+            format!(
+                "\tleaq\t{}(%rip), %r11
+\tmovslq\t(%r11,{},4), %r10
+\taddq\t%r10, %r11
+\tjmp\t\t*%r11",
+                emit_label(table, target),
+                emit_operand(&Operand::Reg(*index), RegSize::EightBytes, target)
+            )
         }
         Instruction::SetCC { cc, dst } => {
             format!(
                 "\tset{}\t{}",
                 emit_condition_code(cc),
-                emit_operand(dst, RegSize::OneByte)
+                emit_operand(dst, RegSize::OneByte, target)
             )
         }
-        Instruction::Label(label) => format!("{}:", emit_label(label)),
+        Instruction::Label(label) => format!("{}:", emit_label(label, target)),
         Instruction::AllocateStack(bytes) => format!("\tsubq\t${bytes}, %rsp"),
         Instruction::DeallocateStack(bytes) => format!("\taddq\t${bytes}, %rsp"),
         Instruction::Push(operand) => {
-            format!("\tpushq\t{}", emit_operand(operand, RegSize::EightBytes))
+            format!(
+                "\tpushq\t{}",
+                emit_operand(operand, RegSize::EightBytes, target)
+            )
         }
-        Instruction::Call(function) => {
-            format!("\tcall\t{}", prefix_identifier(&function.identifier))
+        Instruction::Pop(reg) => {
+            format!(
+                "\tpopq\t{}",
+                emit_operand(&Operand::Reg(*reg), RegSize::EightBytes, target)
+            )
+        }
+        Instruction::Call { function, external } => {
+            let suffix = match target {
+                Target::Linux if *external => "@PLT",
+                _ => "",
+            };
+
+            format!(
+                "\tcall\t{}{suffix}",
+                prefix_identifier(&function.identifier, target)
+            )
+        }
+        Instruction::IndirectCall(operand) => {
+            format!(
+                "\tcall\t*{}",
+                emit_operand(operand, RegSize::EightBytes, target)
+            )
         }
         Instruction::Ret => "\tmovq\t%rbp, %rsp
 \tpopq\t%rbp
 \tret"
             .to_string(),
+        Instruction::SourceLine(line) => format!("\t.loc\t1 {line}"),
+        Instruction::Comment(text) => format!("\t# {text}"),
     }
 }
 
-fn emit_unary_operator(operator: &UnaryOperator) -> String {
+fn emit_unary_operator(operator: &UnaryOperator, ty: AssemblyType) -> String {
+    let suffix = emit_type_suffix(ty);
+
     match operator {
-        UnaryOperator::Neg => "negl".to_string(),
-        UnaryOperator::Not => "notl".to_string(),
+        UnaryOperator::Neg => format!("neg{suffix}"),
+        UnaryOperator::Not => format!("not{suffix}"),
     }
 }
 
-fn emit_binary_operator(operator: &BinaryOperator) -> String {
+fn emit_binary_operator(operator: &BinaryOperator, ty: AssemblyType) -> String {
+    if ty == AssemblyType::Double {
+        return match operator {
+            BinaryOperator::Add => "addsd",
+            BinaryOperator::Sub => "subsd",
+            BinaryOperator::Mult => "mulsd",
+            BinaryOperator::DivDouble => "divsd",
+            BinaryOperator::Xor => "xorpd",
+            BinaryOperator::And | BinaryOperator::Or => {
+                unreachable!("doubles have no {operator:?}")
+            }
+        }
+        .to_string();
+    }
+
+    let suffix = emit_type_suffix(ty);
+
     match operator {
-        BinaryOperator::Add => "addl".to_string(),
-        BinaryOperator::Sub => "subl".to_string(),
-        BinaryOperator::Mult => "imull".to_string(),
-        BinaryOperator::And => "andl".to_string(),
-        BinaryOperator::Or => "orl\t".to_string(),
-        BinaryOperator::Xor => "xorl".to_string(),
+        BinaryOperator::Add => format!("add{suffix}"),
+        BinaryOperator::Sub => format!("sub{suffix}"),
+        BinaryOperator::Mult => format!("imul{suffix}"),
+        BinaryOperator::And => format!("and{suffix}"),
+        BinaryOperator::Or => format!("or{suffix}\t"),
+        BinaryOperator::Xor => format!("xor{suffix}"),
+        BinaryOperator::DivDouble => unreachable!("integers are divided with idiv/div"),
+    }
+}
+
+fn emit_type_suffix(ty: AssemblyType) -> &'static str {
+    match ty {
+        AssemblyType::Byte => "b",
+        AssemblyType::Longword => "l",
+        AssemblyType::Quadword => "q",
+        AssemblyType::Double => "sd",
+        AssemblyType::ByteArray { .. } => unreachable!("arrays are never operands"),
+    }
+}
+
+fn reg_size(ty: AssemblyType) -> RegSize {
+    match ty {
+        AssemblyType::Byte => RegSize::OneByte,
+        AssemblyType::Longword => RegSize::FourBytes,
+        AssemblyType::Quadword | AssemblyType::Double => RegSize::EightBytes,
+        AssemblyType::ByteArray { .. } => unreachable!("arrays are never operands"),
     }
 }
 
@@ -175,11 +477,22 @@ enum RegSize {
     EightBytes,
 }
 
-fn emit_operand(operand: &Operand, size: RegSize) -> String {
+fn emit_operand(operand: &Operand, size: RegSize, target: Target) -> String {
     match operand {
+        Operand::Reg(Reg::XMM0) => "%xmm0".to_string(),
+        Operand::Reg(Reg::XMM1) => "%xmm1".to_string(),
+        Operand::Reg(Reg::XMM2) => "%xmm2".to_string(),
+        Operand::Reg(Reg::XMM3) => "%xmm3".to_string(),
+        Operand::Reg(Reg::XMM4) => "%xmm4".to_string(),
+        Operand::Reg(Reg::XMM5) => "%xmm5".to_string(),
+        Operand::Reg(Reg::XMM6) => "%xmm6".to_string(),
+        Operand::Reg(Reg::XMM7) => "%xmm7".to_string(),
+        Operand::Reg(Reg::XMM14) => "%xmm14".to_string(),
+        Operand::Reg(Reg::XMM15) => "%xmm15".to_string(),
         Operand::Reg(reg) => match size {
             RegSize::OneByte => match reg {
                 Reg::AX => "%al",
+                Reg::BX => "%bl",
                 Reg::CX => "%cl",
                 Reg::DX => "%dl",
                 Reg::DI => "%dil",
@@ -188,9 +501,15 @@ fn emit_operand(operand: &Operand, size: RegSize) -> String {
                 Reg::R9 => "%r9b",
                 Reg::R10 => "%r10b",
                 Reg::R11 => "%r11b",
+                Reg::R12 => "%r12b",
+                Reg::R13 => "%r13b",
+                Reg::R14 => "%r14b",
+                Reg::R15 => "%r15b",
+                _ => unreachable!("SSE registers are handled above"),
             },
             RegSize::FourBytes => match reg {
                 Reg::AX => "%eax",
+                Reg::BX => "%ebx",
                 Reg::CX => "%ecx",
                 Reg::DX => "%edx",
                 Reg::DI => "%edi",
@@ -199,9 +518,15 @@ fn emit_operand(operand: &Operand, size: RegSize) -> String {
                 Reg::R9 => "%r9d",
                 Reg::R10 => "%r10d",
                 Reg::R11 => "%r11d",
+                Reg::R12 => "%r12d",
+                Reg::R13 => "%r13d",
+                Reg::R14 => "%r14d",
+                Reg::R15 => "%r15d",
+                _ => unreachable!("SSE registers are handled above"),
             },
             RegSize::EightBytes => match reg {
                 Reg::AX => "%rax",
+                Reg::BX => "%rbx",
                 Reg::CX => "%rcx",
                 Reg::DX => "%rdx",
                 Reg::DI => "%rdi",
@@ -210,18 +535,37 @@ fn emit_operand(operand: &Operand, size: RegSize) -> String {
                 Reg::R9 => "%r9",
                 Reg::R10 => "%r10",
                 Reg::R11 => "%r11",
+                Reg::R12 => "%r12",
+                Reg::R13 => "%r13",
+                Reg::R14 => "%r14",
+                Reg::R15 => "%r15",
+                _ => unreachable!("SSE registers are handled above"),
             },
         }
         .to_string(),
         Operand::Stack(offset) => format!("{offset}(%rbp)"),
+        Operand::Memory(reg, offset) => format!(
+            "{offset}({})",
+            emit_operand(&Operand::Reg(*reg), RegSize::EightBytes, target)
+        ),
+        Operand::Indexed { base, index, scale } => format!(
+            "({}, {}, {scale})",
+            emit_operand(&Operand::Reg(*base), RegSize::EightBytes, target),
+            emit_operand(&Operand::Reg(*index), RegSize::EightBytes, target)
+        ),
         Operand::Imm(value) => format!("${}", value),
-        Operand::Data(identifier) => format!("{}(%rip)", prefix_identifier(identifier)),
+        Operand::Data(identifier) => {
+            format!("{}(%rip)", prefix_identifier(identifier, target))
+        }
         Operand::Pseudo(_) => unreachable!(),
     }
 }
 
-fn emit_label(label: &Label) -> String {
-    format!("L{}", label.identifier)
+fn emit_label(label: &Label, target: Target) -> String {
+    match target {
+        Target::MacOs => format!("L{}", label.identifier),
+        Target::Linux => format!(".L{}", label.identifier),
+    }
 }
 
 fn emit_condition_code(cc: &ConditionCode) -> String {
@@ -232,6 +576,11 @@ fn emit_condition_code(cc: &ConditionCode) -> String {
         ConditionCode::LE => "le".to_string(),
         ConditionCode::G => "g".to_string(),
         ConditionCode::GE => "ge".to_string(),
+        ConditionCode::A => "a".to_string(),
+        ConditionCode::AE => "ae".to_string(),
+        ConditionCode::B => "b".to_string(),
+        ConditionCode::BE => "be".to_string(),
+        ConditionCode::P => "p".to_string(),
     }
 }
 
@@ -239,7 +588,7 @@ fn emit_condition_code(cc: &ConditionCode) -> String {
 mod tests {
     use super::*;
 
-    use crate::compiler::asm::Function;
+    use crate::compiler::asm::{Function, Variable};
 
     #[test]
     fn test_emit() {
@@ -249,8 +598,10 @@ mod tests {
                     identifier: "main".to_string(),
                 },
                 global: true,
+                frame_pointer: true,
                 instructions: vec![
                     Instruction::Mov {
+                        ty: AssemblyType::Longword,
                         src: Operand::Imm(42),
                         dst: Operand::Reg(Reg::AX),
                     },
@@ -270,6 +621,282 @@ _main:
 \tret
 ";
 
-        assert_eq!(emit(&program), expected);
+        assert_eq!(emit(&program, Target::MacOs, None, false), expected);
+    }
+
+    #[test]
+    fn test_emit_without_frame_pointer() {
+        let program = Program {
+            items: vec![TopLevelItem::FunctionDefinition(FunctionDefinition {
+                function: Function {
+                    identifier: "main".to_string(),
+                },
+                global: true,
+                frame_pointer: false,
+                instructions: vec![
+                    Instruction::Mov {
+                        ty: AssemblyType::Longword,
+                        src: Operand::Imm(42),
+                        dst: Operand::Reg(Reg::AX),
+                    },
+                    Instruction::Ret,
+                ],
+            })],
+        };
+
+        let expected = "\t.globl\t_main
+\t.text
+_main:
+\tmovl\t$42, %eax
+\tret
+";
+
+        assert_eq!(emit(&program, Target::MacOs, None, false), expected);
+    }
+
+    #[test]
+    fn test_emit_jump_table() {
+        let program = Program {
+            items: vec![TopLevelItem::JumpTable(JumpTable {
+                label: Label {
+                    identifier: "table".to_string(),
+                },
+                targets: vec![
+                    Label {
+                        identifier: "a".to_string(),
+                    },
+                    Label {
+                        identifier: "b".to_string(),
+                    },
+                ],
+            })],
+        };
+
+        let expected = "\t.const
+\t.balign 4
+Ltable:
+\t.long\tLa-Ltable
+\t.long\tLb-Ltable
+";
+
+        assert_eq!(emit(&program, Target::MacOs, None, false), expected);
+
+        let expected = "\t.section .rodata
+\t.balign 4
+.Ltable:
+\t.long\t.La-.Ltable
+\t.long\t.Lb-.Ltable
+";
+
+        assert!(emit(&program, Target::Linux, None, false).starts_with(expected));
+
+        assert_eq!(
+            emit_instruction(
+                &Instruction::JmpTable {
+                    table: Label {
+                        identifier: "table".to_string(),
+                    },
+                    index: Reg::AX,
+                },
+                Target::MacOs
+            ),
+            "\tleaq\tLtable(%rip), %r11\n\tmovslq\t(%r11,%rax,4), %r10\n\taddq\t%r10, %r11\n\tjmp\t\t*%r11"
+        );
+    }
+
+    #[test]
+    fn test_emit_linux_symbols() {
+        let program = Program {
+            items: vec![TopLevelItem::FunctionDefinition(FunctionDefinition {
+                function: Function {
+                    identifier: "main".to_string(),
+                },
+                global: true,
+                frame_pointer: true,
+                instructions: vec![
+                    Instruction::Call {
+                        function: Function {
+                            identifier: "foo".to_string(),
+                        },
+                        external: false,
+                    },
+                    Instruction::Call {
+                        function: Function {
+                            identifier: "putchar".to_string(),
+                        },
+                        external: true,
+                    },
+                    Instruction::Jmp {
+                        target: Label {
+                            identifier: "end".to_string(),
+                        },
+                    },
+                    Instruction::Label(Label {
+                        identifier: "end".to_string(),
+                    }),
+                    Instruction::Ret,
+                ],
+            })],
+        };
+
+        let expected = "\t.globl\tmain
+\t.text
+main:
+\tpushq\t%rbp
+\tmovq\t%rsp, %rbp
+\tcall\tfoo
+\tcall\tputchar@PLT
+\tjmp\t\t.Lend
+.Lend:
+\tmovq\t%rbp, %rsp
+\tpopq\t%rbp
+\tret
+
+\t.section .note.GNU-stack,\"\",@progbits
+";
+
+        assert_eq!(emit(&program, Target::Linux, None, false), expected);
+
+        let macos = emit(&program, Target::MacOs, None, false);
+        assert!(macos.contains("\t.globl\t_main\n"));
+        assert!(macos.contains("\tcall\t_foo"));
+        assert!(macos.contains("\tcall\t_putchar\n"));
+        assert!(macos.contains("\tjmp\t\tLend"));
+        assert!(!macos.contains(".note.GNU-stack"));
+    }
+
+    #[test]
+    fn test_emit_sse_registers() {
+        // SSE registers have one name, whatever the size of the operand
+        for size in [RegSize::OneByte, RegSize::FourBytes, RegSize::EightBytes] {
+            assert_eq!(
+                emit_operand(&Operand::Reg(Reg::XMM0), size, Target::Linux),
+                "%xmm0"
+            );
+        }
+
+        assert_eq!(
+            emit_operand(&Operand::Reg(Reg::XMM7), RegSize::FourBytes, Target::Linux),
+            "%xmm7"
+        );
+        assert_eq!(
+            emit_operand(
+                &Operand::Reg(Reg::XMM15),
+                RegSize::EightBytes,
+                Target::Linux
+            ),
+            "%xmm15"
+        );
+    }
+
+    #[test]
+    fn test_emit_static_long() {
+        let program = Program {
+            items: vec![
+                TopLevelItem::StaticVariable(StaticVariable {
+                    variable: Variable {
+                        identifier: "g".to_string(),
+                    },
+                    global: false,
+                    ty: AssemblyType::Quadword,
+                    initial: vec![SymbolStaticInitial::Long(5000000000)],
+                }),
+                TopLevelItem::StaticVariable(StaticVariable {
+                    variable: Variable {
+                        identifier: "h".to_string(),
+                    },
+                    global: true,
+                    ty: AssemblyType::Quadword,
+                    initial: vec![SymbolStaticInitial::Long(0)],
+                }),
+            ],
+        };
+
+        let expected = "\t.data
+\t.balign 8
+_g:
+\t.quad 5000000000
+
+\t.globl\t_h
+\t.bss
+\t.balign 8
+_h:
+\t.zero 8
+";
+
+        assert_eq!(emit(&program, Target::MacOs, None, false), expected);
+    }
+
+    #[test]
+    fn test_emit_static_array() {
+        let program = Program {
+            items: vec![TopLevelItem::StaticVariable(StaticVariable {
+                variable: Variable {
+                    identifier: "a".to_string(),
+                },
+                global: false,
+                ty: AssemblyType::ByteArray {
+                    size: 12,
+                    alignment: 4,
+                },
+                initial: vec![SymbolStaticInitial::Int(1), SymbolStaticInitial::Zero(8)],
+            })],
+        };
+
+        let expected = "\t.data
+\t.balign 4
+a:
+\t.long 1
+\t.zero 8
+
+\t.section .note.GNU-stack,\"\",@progbits
+";
+
+        assert_eq!(emit(&program, Target::Linux, None, false), expected);
+    }
+
+    #[test]
+    fn test_emit_sections_in_order_with_ident() {
+        let program = Program {
+            items: vec![
+                TopLevelItem::StaticVariable(StaticVariable {
+                    variable: Variable {
+                        identifier: "z".to_string(),
+                    },
+                    global: false,
+                    ty: AssemblyType::Longword,
+                    initial: vec![SymbolStaticInitial::Int(0)],
+                }),
+                TopLevelItem::StaticVariable(StaticVariable {
+                    variable: Variable {
+                        identifier: "d".to_string(),
+                    },
+                    global: false,
+                    ty: AssemblyType::Longword,
+                    initial: vec![SymbolStaticInitial::Int(3)],
+                }),
+            ],
+        };
+
+        let expected = format!(
+            "\t.data
+\t.balign 4
+d:
+\t.long 3
+
+\t.bss
+\t.balign 4
+z:
+\t.zero 4
+
+\t.ident\t\"cco {}\"
+
+\t.section .note.GNU-stack,\"\",@progbits
+",
+            env!("CARGO_PKG_VERSION")
+        );
+
+        assert_eq!(emit(&program, Target::Linux, None, true), expected);
+        assert!(!emit(&program, Target::Linux, None, false).contains(".ident"));
     }
 }
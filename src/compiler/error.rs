@@ -0,0 +1,77 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompilerError {
+    Lex {
+        message: String,
+        span: Option<Span>,
+    },
+    Parse {
+        message: String,
+        span: Option<Span>,
+    },
+    Semantic {
+        message: String,
+        span: Option<Span>,
+    },
+    Io {
+        message: String,
+    },
+    /// The C compiler used for preprocessing, assembling or linking failed.
+    Driver {
+        message: String,
+    },
+}
+
+impl CompilerError {
+    pub fn message(&self) -> &str {
+        match self {
+            CompilerError::Lex { message, .. }
+            | CompilerError::Parse { message, .. }
+            | CompilerError::Semantic { message, .. }
+            | CompilerError::Io { message }
+            | CompilerError::Driver { message } => message,
+        }
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            CompilerError::Lex { span, .. }
+            | CompilerError::Parse { span, .. }
+            | CompilerError::Semantic { span, .. } => *span,
+            CompilerError::Io { .. } | CompilerError::Driver { .. } => None,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            CompilerError::Lex { .. } => "lex",
+            CompilerError::Parse { .. } => "parse",
+            CompilerError::Semantic { .. } => "semantic",
+            CompilerError::Io { .. } => "io",
+            CompilerError::Driver { .. } => "driver",
+        }
+    }
+}
+
+impl fmt::Display for CompilerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.span() {
+            Some(Span { line, column }) => write!(
+                f,
+                "{} error at {line}:{column}: {}",
+                self.kind(),
+                self.message()
+            ),
+            None => write!(f, "{} error: {}", self.kind(), self.message()),
+        }
+    }
+}
+
+impl std::error::Error for CompilerError {}
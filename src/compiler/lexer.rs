@@ -1,6 +1,31 @@
 use regex::Regex;
 
-use super::token::Token;
+use super::{
+    error::{CompilerError, Span},
+    token::Token,
+};
+
+fn parse_integer_literal(literal: &str) -> Option<String> {
+    let (digits, radix) = if let Some(hex) = literal
+        .strip_prefix("0x")
+        .or_else(|| literal.strip_prefix("0X"))
+    {
+        (hex, 16)
+    } else if let Some(bin) = literal
+        .strip_prefix("0b")
+        .or_else(|| literal.strip_prefix("0B"))
+    {
+        (bin, 2)
+    } else if literal.len() > 1 && literal.starts_with('0') {
+        (&literal[1..], 8)
+    } else {
+        return Some(literal.to_string());
+    };
+
+    u128::from_str_radix(digits, radix)
+        .ok()
+        .map(|value| value.to_string())
+}
 
 fn find_first_token(s: &str) -> Option<(Token, &str)> {
     if s.is_empty() {
@@ -15,6 +40,17 @@ fn find_first_token(s: &str) -> Option<(Token, &str)> {
             "void" => Token::VoidKeyword,
             "int" => Token::IntKeyword,
             "long" => Token::LongKeyword,
+            "_Bool" => Token::BoolKeyword,
+            "char" => Token::CharKeyword,
+            "signed" => Token::SignedKeyword,
+            "unsigned" => Token::UnsignedKeyword,
+            "double" => Token::DoubleKeyword,
+            "enum" => Token::EnumKeyword,
+            "struct" => Token::StructKeyword,
+            "union" => Token::UnionKeyword,
+            "const" => Token::ConstKeyword,
+            "volatile" => Token::VolatileKeyword,
+            "restrict" | "__restrict" => Token::RestrictKeyword,
             "return" => Token::ReturnKeyword,
             "if" => Token::IfKeyword,
             "else" => Token::ElseKeyword,
@@ -29,28 +65,68 @@ fn find_first_token(s: &str) -> Option<(Token, &str)> {
             "switch" => Token::SwitchKeyword,
             "case" => Token::CaseKeyword,
             "default" => Token::DefaultKeyword,
+            "sizeof" => Token::SizeofKeyword,
             _ => Token::Identifier(ms.to_string()),
         };
 
         return Some((t, rest));
     }
 
-    if let Some(m) = Regex::new(r"^(\d+)[lL]\b").unwrap().captures(s) {
+    if let Some(m) =
+        Regex::new(r"^(\d*\.\d+(?:[eE][+-]?\d+)?|\d+\.(?:[eE][+-]?\d+)?|\d+[eE][+-]?\d+)")
+            .unwrap()
+            .find(s)
+    {
+        let rest = &s[m.end()..];
+
+        // e.g. `1.5f` or `1.2.3`
+        if rest.starts_with(|c: char| c.is_alphanumeric() || c == '_' || c == '.') {
+            return None;
+        }
+
+        return Some((Token::ConstantDouble(m.as_str().to_string()), rest));
+    }
+
+    if let Some(m) = Regex::new(
+        r"^(0[xX][0-9a-fA-F]+|0[bB][01]+|\d+)([uU](?:ll|LL|[lL])?|(?:ll|LL|[lL])[uU]?)?\b",
+    )
+    .unwrap()
+    .captures(s)
+    {
         let ms = m.get(1).unwrap().as_str();
+        let suffix = m.get(2).map_or("", |suffix| suffix.as_str());
         let rest = &s[m.get(0).unwrap().end()..];
 
-        let t = Token::ConstantLong(ms.to_string());
+        let value = parse_integer_literal(ms)?;
+
+        let t = match (suffix.contains(['u', 'U']), suffix.contains(['l', 'L'])) {
+            (false, false) => Token::ConstantInt(value),
+            (false, true) => Token::ConstantLong(value),
+            (true, false) => Token::ConstantUInt(value),
+            (true, true) => Token::ConstantULong(value),
+        };
 
         return Some((t, rest));
     }
 
-    if let Some(m) = Regex::new(r"^\d+\b").unwrap().find(s) {
-        let ms = m.as_str();
-        let rest = &s[m.end()..];
+    if let Some(m) = Regex::new(r#"^'([^'\\\n]|\\[nt0\\'"])'"#)
+        .unwrap()
+        .captures(s)
+    {
+        let ms = m.get(1).unwrap().as_str();
+        let rest = &s[m.get(0).unwrap().end()..];
 
-        let t = Token::ConstantInt(ms.to_string());
+        let c = match ms {
+            r"\n" => '\n',
+            r"\t" => '\t',
+            r"\0" => '\0',
+            r"\\" => '\\',
+            r"\'" => '\'',
+            r#"\""# => '"',
+            _ => ms.chars().next().unwrap(),
+        };
 
-        return Some((t, rest));
+        return Some((Token::ConstantChar(c), rest));
     }
 
     let tokens = [
@@ -74,10 +150,13 @@ fn find_first_token(s: &str) -> Option<(Token, &str)> {
         ("^=", Token::CaretEqual),
         ("--", Token::MinusMinus),
         ("++", Token::PlusPlus),
+        ("->", Token::Arrow),
         ("(", Token::OpenParen),
         (")", Token::CloseParen),
         ("{", Token::OpenBrace),
         ("}", Token::CloseBrace),
+        ("[", Token::OpenBracket),
+        ("]", Token::CloseBracket),
         (";", Token::Semicolon),
         ("~", Token::Tilde),
         ("-", Token::Minus),
@@ -95,6 +174,7 @@ fn find_first_token(s: &str) -> Option<(Token, &str)> {
         ("?", Token::Question),
         (":", Token::Colon),
         (",", Token::Comma),
+        (".", Token::Dot),
     ];
 
     tokens
@@ -102,16 +182,118 @@ fn find_first_token(s: &str) -> Option<(Token, &str)> {
         .find_map(|(p, t)| s.strip_prefix(p).map(|rest| (t.clone(), rest)))
 }
 
-pub fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+/// A `#` is only skipped as a directive if it's the first thing on its line, and `s` starts a
+/// line if `line_start` is set. Anywhere else, it's left for the tokenizer to reject.
+fn skip_whitespace_and_comments(s: &str, mut line_start: bool) -> Result<&str, &str> {
+    let mut rest = s;
+
+    loop {
+        let trimmed = rest.trim_start();
+        line_start |= rest[..rest.len() - trimmed.len()].contains('\n');
+        rest = trimmed;
+
+        if let Some(comment) = rest.strip_prefix("//") {
+            rest = match comment.find('\n') {
+                Some(end) => &comment[end..],
+                None => "",
+            };
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            let Some(end) = comment.find("*/") else {
+                return Err(rest);
+            };
+            rest = &comment[end + 2..];
+            line_start = false;
+        } else if let Some(directive) = rest.strip_prefix('#').filter(|_| line_start) {
+            // line markers and pragmas left behind by the preprocessor
+            rest = match directive.find('\n') {
+                Some(end) => &directive[end..],
+                None => "",
+            };
+        } else {
+            return Ok(rest);
+        }
+    }
+}
+
+/// Tracks the line and column while the input is consumed, following the preprocessor's line
+/// markers (`# 12 "main.c"`) so positions refer to the original source.
+struct Position<'a> {
+    source: &'a str,
+    offset: usize,
+    line: usize,
+    column: usize,
+    /// The line that starts after the current line marker.
+    next_line: Option<usize>,
+}
+
+impl<'a> Position<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            offset: 0,
+            line: 1,
+            column: 1,
+            next_line: None,
+        }
+    }
+
+    fn advance_to(&mut self, rest: &str) -> Span {
+        let target = self.source.len() - rest.len();
+
+        for (i, c) in self.source[self.offset..target].char_indices() {
+            if self.column == 1 && c == '#' {
+                let marker = self.source[self.offset + i + 1..].trim_start_matches([' ', '\t']);
+                let digits = marker.find(|c: char| !c.is_ascii_digit());
+                self.next_line = marker[..digits.unwrap_or(marker.len())].parse().ok();
+            }
+
+            if c == '\n' {
+                self.line = self.next_line.take().unwrap_or(self.line + 1);
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+
+        self.offset = target;
+
+        Span {
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn error(&mut self, rest: &str, message: &str) -> CompilerError {
+        CompilerError::Lex {
+            message: message.to_string(),
+            span: Some(self.advance_to(rest)),
+        }
+    }
+}
+
+pub fn tokenize(s: &str) -> Result<Vec<Token>, CompilerError> {
+    Ok(tokenize_with_spans(s)?
+        .into_iter()
+        .map(|(token, _)| token)
+        .collect())
+}
+
+/// Like `tokenize`, but also returns where each token starts.
+pub fn tokenize_with_spans(s: &str) -> Result<Vec<(Token, Span)>, CompilerError> {
+    let mut position = Position::new(s);
     let mut tokens = Vec::new();
-    let mut rest = s.trim_start();
+    let mut rest = skip_whitespace_and_comments(s, true)
+        .map_err(|r| position.error(r, "Unterminated block comment"))?;
 
     while !rest.is_empty() {
         if let Some((t, r)) = find_first_token(rest) {
-            tokens.push(t);
-            rest = r.trim_start();
+            tokens.push((t, position.advance_to(rest)));
+            rest = skip_whitespace_and_comments(r, false)
+                .map_err(|r| position.error(r, "Unterminated block comment"))?;
         } else {
-            return Err(format!("Could not tokenize: {}", rest));
+            let unexpected = rest.split_whitespace().next().unwrap_or(rest);
+
+            return Err(position.error(rest, &format!("Could not tokenize: {unexpected}")));
         }
     }
 
@@ -132,12 +314,26 @@ mod tests {
         let test_cases = vec![
             ("void", Token::VoidKeyword),
             ("int", Token::IntKeyword),
+            ("_Bool", Token::BoolKeyword),
+            ("char", Token::CharKeyword),
+            ("signed", Token::SignedKeyword),
+            ("unsigned", Token::UnsignedKeyword),
+            ("double", Token::DoubleKeyword),
+            ("enum", Token::EnumKeyword),
+            ("struct", Token::StructKeyword),
+            ("union", Token::UnionKeyword),
+            ("const", Token::ConstKeyword),
+            ("volatile", Token::VolatileKeyword),
+            ("__restrict", Token::RestrictKeyword),
             ("return", Token::ReturnKeyword),
+            ("sizeof", Token::SizeofKeyword),
             ("42", Token::ConstantInt("42".to_string())),
             ("(", Token::OpenParen),
             (")", Token::CloseParen),
             ("{", Token::OpenBrace),
             ("}", Token::CloseBrace),
+            ("[", Token::OpenBracket),
+            ("]", Token::CloseBracket),
             (";", Token::Semicolon),
             ("main", Token::Identifier("main".to_string())),
             ("foo", Token::Identifier("foo".to_string())),
@@ -150,6 +346,90 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_integer_bases() {
+        let test_cases = vec![
+            ("0", Token::ConstantInt("0".to_string())),
+            ("0x1F", Token::ConstantInt("31".to_string())),
+            ("0X1f", Token::ConstantInt("31".to_string())),
+            ("0777", Token::ConstantInt("511".to_string())),
+            ("0b1010", Token::ConstantInt("10".to_string())),
+            ("0B11", Token::ConstantInt("3".to_string())),
+            ("0x10L", Token::ConstantLong("16".to_string())),
+            ("010l", Token::ConstantLong("8".to_string())),
+        ];
+
+        for (input, expected_token) in test_cases {
+            assert_eq!(tokenize(input), Ok(vec![expected_token.clone()]));
+        }
+
+        for input in ["0x1Fg", "0x", "08", "0b102", "0b"] {
+            assert!(tokenize(input).is_err());
+        }
+    }
+
+    #[test]
+    fn test_floating_literals() {
+        for input in ["1.5", "1e10", ".5", "3.", "2.5E-3", "1.e+2"] {
+            assert_eq!(
+                tokenize(input),
+                Ok(vec![Token::ConstantDouble(input.to_string())])
+            );
+        }
+
+        for input in ["1.5f", "1.2.3", "1e", "1.5x"] {
+            assert!(tokenize(input).is_err());
+        }
+    }
+
+    #[test]
+    fn test_integer_suffixes() {
+        let test_cases = vec![
+            ("10", Token::ConstantInt("10".to_string())),
+            ("10L", Token::ConstantLong("10".to_string())),
+            ("10l", Token::ConstantLong("10".to_string())),
+            ("10u", Token::ConstantUInt("10".to_string())),
+            ("10UL", Token::ConstantULong("10".to_string())),
+            ("10lu", Token::ConstantULong("10".to_string())),
+            ("10LL", Token::ConstantLong("10".to_string())),
+            ("10ull", Token::ConstantULong("10".to_string())),
+        ];
+
+        for (input, expected_token) in test_cases {
+            assert_eq!(tokenize(input), Ok(vec![expected_token.clone()]));
+        }
+
+        for input in ["42LL5", "10lL", "10lul", "10uu", "10Lx"] {
+            assert!(tokenize(input).is_err());
+        }
+    }
+
+    #[test]
+    fn test_char_constants() {
+        let test_cases = vec![
+            ("'a'", 'a'),
+            ("'Z'", 'Z'),
+            ("' '", ' '),
+            ("'\"'", '"'),
+            (r"'\n'", '\n'),
+            (r"'\t'", '\t'),
+            (r"'\0'", '\0'),
+            (r"'\\'", '\\'),
+            (r"'\''", '\''),
+        ];
+
+        for (input, expected_char) in test_cases {
+            assert_eq!(
+                tokenize(input),
+                Ok(vec![Token::ConstantChar(expected_char)])
+            );
+        }
+
+        for input in ["''", "'ab'", "'a", r"'\q'", "'''"] {
+            assert!(tokenize(input).is_err());
+        }
+    }
+
     #[test]
     fn test_multiple_tokens() {
         let input = "int main(void) { return 2; }";
@@ -203,6 +483,75 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_error_position() {
+        let error = tokenize("int main(void) {\n    return 2 @abc;\n}").unwrap_err();
+        assert_eq!(
+            error.span(),
+            Some(Span {
+                line: 2,
+                column: 14
+            })
+        );
+        assert_eq!(error.message(), "Could not tokenize: @abc;");
+        assert_eq!(
+            error.to_string(),
+            "lex error at 2:14: Could not tokenize: @abc;"
+        );
+
+        let error = tokenize("@").unwrap_err();
+        assert_eq!(error.span(), Some(Span { line: 1, column: 1 }));
+
+        // a `#` that doesn't start a line isn't a directive
+        let error = tokenize("int x = 40 # + 100\n;").unwrap_err();
+        assert_eq!(
+            error.span(),
+            Some(Span {
+                line: 1,
+                column: 12
+            })
+        );
+        assert_eq!(error.message(), "Could not tokenize: #");
+
+        let error = tokenize("int x;\n\n  /* never closed").unwrap_err();
+        assert_eq!(error.span(), Some(Span { line: 3, column: 3 }));
+        assert_eq!(error.message(), "Unterminated block comment");
+    }
+
+    #[test]
+    fn test_line_markers() {
+        let input = "# 1 \"main.c\"\n# 1 \"<built-in>\"\n# 1 \"main.c\"\n\n\n# 7 \"main.c\"\nint x;\n  long y;";
+
+        let spans = tokenize_with_spans(input)
+            .unwrap()
+            .into_iter()
+            .map(|(_, span)| span)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            spans,
+            vec![
+                Span { line: 7, column: 1 },
+                Span { line: 7, column: 5 },
+                Span { line: 7, column: 6 },
+                Span { line: 8, column: 3 },
+                Span { line: 8, column: 8 },
+                Span { line: 8, column: 9 },
+            ]
+        );
+
+        // indented directives and ones after a comment on the line before are still skipped
+        assert_eq!(
+            tokenize("int x; // a\n  #pragma once\n/* b */\n# 3 \"main.c\"\n;").unwrap(),
+            vec![
+                Token::IntKeyword,
+                Token::Identifier("x".to_string()),
+                Token::Semicolon,
+                Token::Semicolon
+            ]
+        );
+    }
+
     #[test]
     fn test_whitespace() {
         let input = "   int  main  (  void  )  {\n\n\treturn 2;\n}";
@@ -222,6 +571,64 @@ mod tests {
         assert_eq!(tokenize(input), expected);
     }
 
+    #[test]
+    fn test_comments() {
+        let input = "// leading\nint /* inline */ main(void) {\n\treturn /* multi\nline */ 2; // trailing\n}\n// eof";
+        let expected = Ok(vec![
+            Token::IntKeyword,
+            Token::Identifier("main".to_string()),
+            Token::OpenParen,
+            Token::VoidKeyword,
+            Token::CloseParen,
+            Token::OpenBrace,
+            Token::ReturnKeyword,
+            Token::ConstantInt("2".to_string()),
+            Token::Semicolon,
+            Token::CloseBrace,
+        ]);
+
+        assert_eq!(tokenize(input), expected);
+
+        assert_eq!(
+            tokenize("1 /**/ / /* / */ 2"),
+            Ok(vec![
+                Token::ConstantInt("1".to_string()),
+                Token::Slash,
+                Token::ConstantInt("2".to_string()),
+            ])
+        );
+
+        assert!(tokenize("int /* unterminated").is_err());
+    }
+
+    #[test]
+    fn test_member_access() {
+        let identifier = |name: &str| Token::Identifier(name.to_string());
+
+        assert_eq!(
+            tokenize("a.b->c-->d"),
+            Ok(vec![
+                identifier("a"),
+                Token::Dot,
+                identifier("b"),
+                Token::Arrow,
+                identifier("c"),
+                Token::MinusMinus,
+                Token::Greater,
+                identifier("d"),
+            ])
+        );
+
+        // a dot before a digit starts a constant
+        assert_eq!(
+            tokenize("a.5"),
+            Ok(vec![
+                identifier("a"),
+                Token::ConstantDouble(".5".to_string())
+            ])
+        );
+    }
+
     #[test]
     fn test_unary_ops() {
         assert_eq!(
@@ -280,4 +687,25 @@ mod tests {
             ])
         );
     }
+
+    #[test]
+    fn test_random_input_does_not_panic() {
+        // xorshift, so that failures can be reproduced
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for _ in 0..200 {
+            let len = next() % 24;
+            let bytes: Vec<u8> = (0..len).map(|_| (next() % 128) as u8).collect();
+            let source = String::from_utf8(bytes).unwrap();
+
+            // errors are fine, this only checks that there is no panic
+            let _ = tokenize(&source);
+        }
+    }
 }
@@ -3,9 +3,12 @@ mod ast;
 mod codegen;
 mod constant_conversion;
 mod emitter;
+mod error;
 mod lexer;
+mod optimization;
 mod parser;
 mod prefixes;
+mod register_allocation;
 mod semantic;
 mod symbols;
 mod tacky;
@@ -15,52 +18,1215 @@ mod token;
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CompilerStage {
     Lex,
+    /// Like `Lex`, but the tokens are printed as JSON.
+    #[cfg(feature = "serde")]
+    LexJson,
     Parse,
+    /// Like `Parse`, but the AST is printed as JSON.
+    #[cfg(feature = "serde")]
+    ParseJson,
     Validate,
+    /// Like `Validate`, but the symbol table is printed instead of the AST.
+    Symbols,
     Tacky,
     Codegen,
     Full,
 }
 
-pub fn compile(input: &std::path::PathBuf, output: &std::path::PathBuf, stage: CompilerStage) {
-    if std::env::consts::OS != "macos" {
-        panic!("Unsupported OS");
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Target {
+    #[value(name = "macos")]
+    MacOs,
+    Linux,
+}
+
+impl Target {
+    pub fn host() -> Option<Self> {
+        match std::env::consts::OS {
+            "macos" => Some(Target::MacOs),
+            "linux" => Some(Target::Linux),
+            _ => None,
+        }
     }
+}
 
-    let str = std::fs::read_to_string(input).unwrap();
+pub use error::CompilerError;
+pub use optimization::{OptLevel, Optimizations};
+use symbols::{Symbol, SymbolAttributes};
 
-    let tokens = lexer::tokenize(&str).expect("Error during lexing");
+pub const STDOUT_PATH: &str = "-";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompileOptions {
+    pub target: Target,
+    pub optimizations: Optimizations,
+    /// Treat warnings as errors.
+    pub strict: bool,
+    /// Accept trailing commas after the arguments of a call.
+    pub lenient: bool,
+    /// Emit `.loc` directives for the lines of the source.
+    pub debug_info: bool,
+    /// Comment the assembly with the IR instructions it was generated from.
+    pub annotate: bool,
+    /// Record the version of the compiler in the object file.
+    pub ident: bool,
+}
+
+/// For the host (or Linux elsewhere), without optimizations or any of the extras.
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self {
+            target: Target::host().unwrap_or(Target::Linux),
+            optimizations: Optimizations::from(OptLevel::O0),
+            strict: false,
+            lenient: false,
+            debug_info: false,
+            annotate: false,
+            ident: false,
+        }
+    }
+}
+
+/// What `compile` found out about its input, besides the output it writes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompileReport {
+    /// Whether the input defines a `main` function, without which a program can't be linked.
+    pub defines_main: bool,
+    /// Warnings about the input, which are errors instead with `strict`.
+    pub warnings: Vec<String>,
+}
+
+pub fn compile(
+    input: &std::path::PathBuf,
+    output: &std::path::PathBuf,
+    stage: CompilerStage,
+    options: CompileOptions,
+) -> Result<CompileReport, CompilerError> {
+    let str = std::fs::read_to_string(input).map_err(|e| CompilerError::Io {
+        message: format!("Could not read {}: {e}", input.display()),
+    })?;
+
+    // the preprocessed input sits next to its source file
+    let source_file = input.with_extension("c");
+    let (result, report) = compile_source(&str, source_file.to_str().unwrap(), stage, options)?;
+
+    if stage != CompilerStage::Full {
+        eprintln!("{result}");
+        return Ok(report);
+    }
+
+    if output.as_os_str() == STDOUT_PATH {
+        print!("{result}");
+        return Ok(report);
+    }
+
+    std::fs::write(output, result).map_err(|e| CompilerError::Io {
+        message: format!("Could not write {}: {e}", output.display()),
+    })?;
+
+    Ok(report)
+}
+
+/// Compiles preprocessed C source for the host (or Linux elsewhere) without optimizations.
+///
+/// Returns the assembly, or for earlier stages a dump of that stage's result.
+pub fn compile_str(source: &str, stage: CompilerStage) -> Result<String, CompilerError> {
+    compile_str_with_options(source, stage, CompileOptions::default())
+}
+
+/// Like `compile_str`, but for a specific target and set of optimizations.
+pub fn compile_str_with_options(
+    source: &str,
+    stage: CompilerStage,
+    options: CompileOptions,
+) -> Result<String, CompilerError> {
+    compile_source(source, "<stdin>", stage, options).map(|(result, _)| result)
+}
+
+/// `source_file` is the name the debug info refers to. Whether the source defines `main` is only
+/// checked when compiling all the way.
+fn compile_source(
+    source: &str,
+    source_file: &str,
+    stage: CompilerStage,
+    options: CompileOptions,
+) -> Result<(String, CompileReport), CompilerError> {
     if stage == CompilerStage::Lex {
-        dbg!(tokens);
-        return;
+        return Ok((
+            format!("{:#?}", lexer::tokenize(source)?),
+            CompileReport::default(),
+        ));
+    }
+    #[cfg(feature = "serde")]
+    if stage == CompilerStage::LexJson {
+        return Ok((
+            serde_json::to_string(&lexer::tokenize(source)?).unwrap(),
+            CompileReport::default(),
+        ));
     }
 
-    let ast_result = parser::parse(&tokens).expect("Error during parsing");
+    let ast_result = if options.debug_info {
+        parser::parse_with_spans(&lexer::tokenize_with_spans(source)?, options.lenient)?
+    } else {
+        parser::parse(&lexer::tokenize(source)?, options.lenient)?
+    };
     if stage == CompilerStage::Parse {
-        dbg!(&ast_result);
-        return;
+        return Ok((ast_result.to_string(), CompileReport::default()));
+    }
+    #[cfg(feature = "serde")]
+    if stage == CompilerStage::ParseJson {
+        return Ok((
+            serde_json::to_string(&ast_result).unwrap(),
+            CompileReport::default(),
+        ));
     }
 
-    let (validated_ast_result, symbols) =
-        semantic::analyze(&ast_result).expect("Error during semantic analysis");
+    let (validated_ast_result, mut symbols, warnings) =
+        semantic::analyze(&ast_result, options.strict)?;
+    let mut report = CompileReport {
+        defines_main: false,
+        warnings,
+    };
     if stage == CompilerStage::Validate {
-        dbg!(&validated_ast_result);
-        return;
+        return Ok((validated_ast_result.to_string(), report));
+    }
+    if stage == CompilerStage::Symbols {
+        return Ok((symbols.to_string(), report));
     }
 
-    let tacky_result = tackygen::generate(&validated_ast_result, &symbols);
+    let tacky_result = tackygen::generate(&validated_ast_result, &mut symbols);
+    let tacky_result = optimization::optimize(&tacky_result, &symbols, options.optimizations);
     if stage == CompilerStage::Tacky {
-        dbg!(&tacky_result);
-        return;
+        return Ok((tacky_result.to_string(), report));
     }
 
-    let asm_result = codegen::generate(&tacky_result, &symbols);
+    let asm_result = codegen::generate(
+        &tacky_result,
+        &symbols,
+        options.optimizations,
+        options.annotate,
+    );
     if stage == CompilerStage::Codegen {
-        dbg!(&asm_result);
+        return Ok((format!("{asm_result:#?}"), report));
+    }
+
+    report.defines_main = matches!(
+        symbols.get("main"),
+        Some(Symbol {
+            attrs: SymbolAttributes::Function {
+                defined: true,
+                global: true,
+            },
+            ..
+        })
+    );
+
+    Ok((
+        emitter::emit(
+            &asm_result,
+            options.target,
+            options.debug_info.then_some(source_file),
+            options.ident,
+        ),
+        report,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compiles and runs `source` with every optimization setting, checking the exit code of each.
+    fn assert_exit_code(test: &str, source: &str, expected: i32) {
+        assert_output(test, source, expected, "");
+    }
+
+    /// Compiles `source` at every optimization level, links it against the C library and checks
+    /// what the program does.
+    fn assert_output(test: &str, source: &str, expected: i32, expected_stdout: &str) {
+        let dir = std::env::temp_dir().join(format!("cco-{test}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let input = dir.join("main.i");
+        std::fs::write(&input, source).unwrap();
+
+        // the register allocator on its own, without the passes that simplify its input
+        let registers = Optimizations {
+            allocate_registers: true,
+            ..Optimizations::from(OptLevel::O0)
+        };
+
+        for (name, optimizations) in [
+            ("O0", Optimizations::from(OptLevel::O0)),
+            ("O1", Optimizations::from(OptLevel::O1)),
+            ("registers", registers),
+        ] {
+            let assembly = dir.join(format!("main-{name}.s"));
+            let binary = dir.join(format!("main-{name}"));
+
+            compile(
+                &input,
+                &assembly,
+                CompilerStage::Full,
+                CompileOptions {
+                    target: Target::host().unwrap(),
+                    optimizations,
+                    strict: true,
+                    ..CompileOptions::default()
+                },
+            )
+            .unwrap();
+            crate::driver::assemble_and_link(&[assembly], &binary).unwrap();
+
+            let output = std::process::Command::new(&binary).output().unwrap();
+            assert_eq!(
+                output.status.code(),
+                Some(expected),
+                "wrong exit code with {name}"
+            );
+            assert_eq!(
+                String::from_utf8_lossy(&output.stdout),
+                expected_stdout,
+                "wrong output with {name}"
+            );
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_opt_levels_agree() {
+        let source = "int main(void) {
+    int a = 2 + 3 * 4;
+    if (a > 10) {
+        return a - 1;
+    }
+    return 0;
+    a = 7;
+}
+";
+
+        assert_exit_code("opt", source, 13);
+    }
+
+    #[test]
+    fn test_bool_conversion() {
+        assert_exit_code("bool", "int main(void) { _Bool b = 5; return b; }", 1);
+
+        let source = "_Bool flag = 2;
+_Bool negate(_Bool b) { return !b; }
+int main(void) {
+    long big = 4294967296L;
+    _Bool from_long = big;
+    _Bool zero = 0;
+    return flag + from_long * 2 + negate(zero) * 4 + (zero ? 8 : 16) + -from_long;
+}
+";
+
+        assert_exit_code("bool-mixed", source, 22);
+    }
+
+    #[test]
+    fn test_pointers() {
+        let source = "static int counter = 3;
+int *pick(int *a, int *b, int first) { return first ? a : b; }
+int bump(int *p) { *p += 2; return (*p)++; }
+int main(void) {
+    int x = 1;
+    int y = 10;
+    int *p = pick(&x, &y, 0);
+    *p = *p * 2;
+    int *null = 0;
+    int **pp = &p;
+    **pp = **pp + bump(&counter);
+    return y + counter + (null == 0) + (*&x == x);
+}
+";
+
+        assert_exit_code("pointers", source, 33);
+    }
+
+    #[test]
+    fn test_arrays() {
+        let source = "long sum(long *values, int n) {
+    long total = 0;
+    for (int i = 0; i < n; i++)
+        total += values[i];
+    return total;
+}
+int grid[2][3];
+int main(void) {
+    long values[5];
+    for (int i = 0; i < 5; i++)
+        values[i] = i * 3;
+    grid[1][2] = 4;
+    int *last = &grid[0][0] + 5;
+    long *end = values + 5;
+    return sum(values, 5) + *last + (end - values) + (end[-1] == 12);
+}
+";
+
+        assert_exit_code("arrays", source, 40);
+    }
+
+    #[test]
+    fn test_compound_initializers() {
+        let source = "int primes[4] = {2, 3, 5, 7};
+static long grid[2][3] = {{1, 2, 3}, {4}};
+int main(void) {
+    int full[3] = {1, 2, 3};
+    long partial[4] = {10, 20,};
+    int nested[2][2] = {{1}, {2, 3}};
+    int sum = primes[0] + primes[3] + grid[0][2] + grid[1][0] + grid[1][2];
+    sum += full[0] + full[1] + full[2] + partial[1] + partial[3];
+    return sum + nested[0][0] + nested[0][1] + nested[1][0] + nested[1][1];
+}
+";
+
+        assert_exit_code("initializers", source, 48);
+    }
+
+    #[test]
+    fn test_unsigned() {
+        let source = "unsigned int max = 4294967295u;
+unsigned long halve(unsigned long x) { return x >> 1; }
+int main(void) {
+    unsigned int wrapped = max + 2u;
+    unsigned long big = -1;
+    int result = wrapped;
+    result += max / 2u == 2147483647u;
+    result += (-1 < 1u) * 10;
+    result += (big > 1) * 4;
+    result += halve(big) == 9223372036854775807ul;
+    result += (unsigned long)max % 10 == 5;
+    return result;
+}
+";
+
+        assert_exit_code("unsigned", source, 8);
+    }
+
+    #[test]
+    fn test_doubles() {
+        let source = "double half(double x) { return x / 2.0; }
+double sum(double a, double b, double c, double d, double e, double f, double g, double h, double i, int j) {
+    return a + b + c + d + e + f + g + h + i + j;
+}
+int main(void) {
+    double d = half(5.0);
+    unsigned long big = 18446744073709551615.0 - 4096.0;
+    int result = d == 2.5;
+    result += (d > 2.0 && !(d - 2.5)) * 2;
+    result += (sum(1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10) == 55.0) * 4;
+    result += ((double)big > 1e19) * 8;
+    result += (int)-d;
+    return result;
+}
+";
+
+        assert_exit_code("doubles", source, 13);
+    }
+
+    #[test]
+    fn test_nan_comparisons() {
+        let source = "double quotient(double a, double b) { return a / b; }
+int main(void) {
+    double nan = quotient(0.0, 0.0);
+    int result = (nan == nan) + (nan != nan) * 2 + (nan < 1.0) * 4 + (nan <= 1.0) * 8;
+    result += (nan > 1.0) * 16 + (1.0 >= nan) * 32 + !nan * 64;
+    if (nan) {
+        result += 128;
+    }
+    return result;
+}
+";
+
+        assert_exit_code("nan_comparisons", source, 130);
+    }
+
+    #[test]
+    fn test_void_functions() {
+        let source = "int counter = 0;
+void bump(int by) {
+    if (by < 0)
         return;
+    counter += by;
+}
+void twice(int by) { bump(by); bump(by); }
+int main(void) {
+    bump(3);
+    twice(4);
+    bump(-10);
+    counter > 5 ? bump(1) : twice(1);
+    (void) counter;
+    return counter;
+}
+";
+
+        assert_exit_code("void", source, 12);
+    }
+
+    #[test]
+    fn test_compound_assignment_conversions() {
+        let source = "int main(void) {
+    int r = 0;
+    int x = 10;
+    x /= 4294967296L;
+    if (x == 0) r = r + 1;
+    int y = 1;
+    y += 5000000000L;
+    if (y == 705032705) r = r + 2;
+    int z = 5;
+    z *= 2.5;
+    if (z == 12) r = r + 4;
+    int w = -1;
+    w /= 2U;
+    if (w == 2147483647) r = r + 8;
+    int a[3] = {1, 2, 3};
+    int *p = a + 1;
+    *++p -= 1L;
+    if (a[2] == 2) r = r + 16;
+    return r;
+}
+";
+
+        assert_exit_code("compound", source, 31);
+    }
+
+    #[test]
+    fn test_compound_assignment_through_pointers() {
+        let source = "int main(void) {
+    long a[4] = {1, 2, 3, 4};
+    int x = 5;
+    int *p = &x;
+    *p += 3;
+    (*p)++;
+    --*p;
+    int i = 1;
+    a[i] *= 10;
+    a[i + 1]++;
+    ++a[0];
+    a[3] -= a[0]--;
+    long *q = a;
+    q[3] <<= 1;
+    *(q + 2) %= 3;
+    return x + a[0] + a[1] + a[2] + a[3];
+}
+";
+
+        assert_exit_code("lvalues", source, 34);
     }
 
-    let emitted = emitter::emit(&asm_result);
+    #[test]
+    fn test_static_locals() {
+        let source = "int next(void) {
+    static int c = 0;
+    c = c + 1;
+    return c;
+}
+int main(void) {
+    next();
+    next();
+    return next();
+}
+";
 
-    std::fs::write(output, emitted).unwrap();
+        assert_exit_code("static", source, 3);
+
+        // defined once, not on every call
+        let assembly = compile_str(source, CompilerStage::Full).unwrap();
+        let definitions = assembly.lines().filter(|line| line.ends_with(".c:"));
+        assert_eq!(definitions.count(), 1);
+    }
+
+    #[test]
+    fn test_static_locals_with_the_same_name() {
+        let source = "int a(void) { static int count; count = count + 1; return count; }
+int b(void) { static int count = 10; count = count + 2; return count; }
+int main(void) { a(); a(); b(); return a() + b(); }
+";
+
+        assert_exit_code("static_names", source, 17);
+    }
+
+    #[test]
+    fn test_function_pointers() {
+        let source = "int add(int a, int b) { return a + b; }
+int sub(int a, int b) { return a - b; }
+int apply(int (*op)(int, int), int a, int b) { return op(a, b); }
+int (*pick(int i))(int, int) { return i ? add : sub; }
+int main(void) {
+    int (*table[2])(int, int) = {add, sub};
+    int (*fp)(int, int) = &sub;
+    int result = (*fp)(10, 3) + table[0](1, 2) + apply(add, 4, 5) + pick(0)(8, 6);
+    fp = add;
+    return fp == add ? result + fp(0, 1) : 0;
+}
+";
+
+        assert_exit_code("function_pointers", source, 22);
+    }
+
+    #[test]
+    fn test_extern_prototypes() {
+        let source = "extern int putchar(int c);
+int putchar(int);
+int shout(int c) { int putchar(int ch); return putchar(c - 32); }
+int main(void) { putchar(104); shout(105); putchar(10); return putchar(33) == 33; }
+";
+
+        assert_output("extern_prototypes", source, 1, "hI\n!");
+    }
+
+    #[test]
+    fn test_main_arguments() {
+        let source = "int main(int argc, char **argv) {
+    if (argv[0] == 0 || argv[argc] != 0) return 100;
+    return argc;
+}
+";
+
+        assert_exit_code("main_arguments", source, 1);
+        assert_exit_code(
+            "main_array_arguments",
+            &source.replace("char **argv", "char *argv[]"),
+            1,
+        );
+    }
+
+    #[test]
+    fn test_short_circuit_side_effects() {
+        let source = "int calls = 0;
+int bump(void) { calls = calls + 1; return 1; }
+int main(void) {
+    int i = 0;
+    int a = 0 && bump();
+    int b = 1 || bump();
+    int c = 0 && i++;
+    int d = 1 || (i = 5);
+    int e = 1 && bump();
+    int f = 0 || i++;
+    return calls * 10 + i + a + b + c + d + e + f;
+}
+";
+
+        // only `e` calls bump and only `f` increments i
+        assert_exit_code("short_circuit_side_effects", source, 14);
+    }
+
+    #[test]
+    fn test_continue_runs_for_post() {
+        let source = "int main(void) {
+    int sum = 0;
+    int i;
+    for (i = 0; i < 10; i++) {
+        if (i % 2) continue;
+        sum += i;
+    }
+    int j = 0;
+    for (int k = 0; k < 5; k++, j++)
+        continue;
+    return sum + i + j;
+}
+";
+
+        // a continue that skipped the post-expression would loop forever
+        assert_exit_code("continue_runs_for_post", source, 35);
+    }
+
+    #[test]
+    fn test_multiple_declarators() {
+        let source = "static int total = 1, unused;
+int twice(int x), add(int a, int b);
+int add(int a, int b) { return a + b; }
+int twice(int x) { return add(x, x); }
+int main(void) {
+    int a = 2, b, *p = &b;
+    *p = twice(a);
+    for (int i = 0, j = 10; i < j; i++, j--)
+        total += i;
+    return total + a * 100 + b + unused;
+}
+";
+
+        // total is 1 + 0 + 1 + 2 + 3 + 4
+        assert_exit_code("multiple_declarators", source, 215);
+    }
+
+    #[test]
+    fn test_enums() {
+        let source = "enum Color { RED, GREEN = 5, BLUE };
+enum Color favourite = BLUE;
+int classify(enum Color c) {
+    switch (c) {
+    case RED: return 1;
+    case GREEN: return 2;
+    case BLUE: return 3;
+    }
+    return 0;
+}
+int main(void) {
+    enum { TEN = 10, ELEVEN } x = ELEVEN;
+    int total = favourite * 10 + classify(GREEN) + x;
+    {
+        // shadows the enumerator
+        int RED = 100;
+        total += RED;
+    }
+    return total + RED + BLUE;
+}
+";
+
+        // 60 + 2 + 11 + 100 + 0 + 6
+        assert_exit_code("enums", source, 179);
+    }
+
+    #[test]
+    fn test_negative_enumerators() {
+        let source = "enum { NEG = -1, ZERO, MIN = -2147483648L, ONE = -NEG };
+int main(void) {
+    if (MIN >= 0 || MIN + 2147483647 != NEG)
+        return 1;
+    return 40 + NEG + ZERO + ONE * 3;
+}
+";
+
+        assert_exit_code("negative_enumerators", source, 42);
+    }
+
+    #[test]
+    fn test_negative_case_values() {
+        // the unsigned values become negative when converted to the type of the switch
+        let source = "int sparse(int x) {
+    switch (x) {
+    case 4294967293u: return 1;
+    case 4294967295u: return 2;
+    case 100000: return 3;
+    default: return 4;
+    }
+}
+int dense(long x) {
+    switch (x) {
+    case 18446744073709551614ul: return 10;
+    case 18446744073709551615ul: return 20;
+    case 0: return 30;
+    case 1: return 40;
+    case 2: return 50;
+    }
+    return 60;
+}
+int main(void) {
+    return sparse(-3) + sparse(-1) + sparse(100000) + sparse(7) + dense(-2) + dense(-1) + dense(3);
+}
+";
+
+        // 1 + 2 + 3 + 4 + 10 + 20 + 60
+        assert_exit_code("negative_case_values", source, 100);
+    }
+
+    #[test]
+    fn test_negative_case_labels() {
+        let source = "int sparse(int x) {
+    switch (x) {
+    case -2147483648: return 1;
+    case -1: return 2;
+    case 100000: return 3;
+    default: return 4;
+    }
+}
+int dense(long x) {
+    switch (x) {
+    case -4: return 1;
+    case -3: return 2;
+    case -2: return 10;
+    case -1: return 20;
+    case 0: return 30;
+    case 1: return 40;
+    case 2: return 50;
+    case 3: return 60;
+    case 4: return 70;
+    }
+    return 60;
+}
+int main(void) {
+    return sparse(-2147483647 - 1) + sparse(-1) + sparse(100000) + sparse(7) + dense(-2)
+        + dense(-1) + dense(5);
+}
+";
+
+        // 1 + 2 + 3 + 4 + 10 + 20 + 60, with enough cases in dense for a jump table
+        assert_exit_code("negative_case_labels", source, 100);
+    }
+
+    #[test]
+    fn test_constant_expression_case_labels() {
+        let source = "enum { A = 1 + 1, B = -(-A) * 3 };
+int classify(int x) {
+    switch (x) {
+    case A - 3: return 1;
+    case B: return 2;
+    case 1 << 4: return 3;
+    }
+    return 5;
+}
+int main(void) {
+    return classify(-1) + classify(6) + classify(16) + classify(0);
+}
+";
+
+        // 1 + 2 + 3 + 5
+        assert_exit_code("constant_expression_case_labels", source, 11);
+    }
+
+    #[test]
+    fn test_calls_inside_expressions() {
+        let source = "int square(int x) { return x * x; }
+int sum(int a, int b, int c, int d, int e, int f, int g, int h) {
+    return a + b + c + d + e + f + g + h;
+}
+int main(void) {
+    int a = 3, b = 4, c = 5, d = 6, e = 7, f = 8;
+    int result = a * b + square(a + b) * (c - b) + d - square(e - f) + f;
+    int nested = sum(a, b, square(c), d, e, f, a + square(b), sum(1, 2, 3, 4, 5, 6, 7, 8) - a);
+    return result + nested + a + b + c + d + e + f;
+}
+";
+
+        // more values are live across the calls than there are registers to keep them in
+        assert_exit_code("calls_inside_expressions", source, 74 + 105 + 33);
+    }
+
+    #[test]
+    fn test_stack_arguments_keep_alignment() {
+        // a 16-byte array is 16-byte aligned relative to %rbp, so it's only aligned in memory if
+        // the stack was at the call
+        let source = "int misalignment(void) {
+    long buf[2];
+    return (int)((long)buf % 16);
+}
+int seven(int a, int b, int c, int d, int e, int f, int g) {
+    return misalignment() * 100 + g;
+}
+int eight(int a, int b, int c, int d, int e, int f, int g, long h) {
+    return misalignment() * 100 + g + (int)h;
+}
+int nine(int a, int b, int c, int d, int e, int f, int g, int h, double i) {
+    return misalignment() * 100 + (int)i;
+}
+int main(void) {
+    int x = 1;
+    return seven(x, 2, 3, 4, 5, 6, 7)
+        + eight(1, 2, 3, 4, 5, 6, seven(1, 2, 3, 4, 5, 6, 7), 8)
+        + nine(1, 2, 3, 4, 5, 6, 7, 8, 9.0)
+        + misalignment();
+}
+";
+
+        assert_exit_code("stack_arguments_keep_alignment", source, 7 + 15 + 9);
+    }
+
+    #[test]
+    fn test_long_and_char_arguments() {
+        let source = "long add(long a, long b) { return a + b; }
+int bytes(int a, int b, int c, int d, int e, int f, signed char g, unsigned char h) {
+    return g + h;
+}
+int main(void) {
+    signed char minus_one = -1;
+    unsigned char max = 255;
+    if (add(4294967296L, 1) != 4294967297L)
+        return 1;
+    if (add(-4294967296L, 4294967296L) != 0)
+        return 2;
+    if (bytes(1, 2, 3, 4, 5, 6, minus_one, max) != 254)
+        return 3;
+    return bytes(1, 2, 3, 4, 5, 6, -3, 300);
+}
+";
+
+        // 300 wraps around to 44
+        assert_exit_code("long_and_char_arguments", source, 41);
+    }
+
+    #[test]
+    fn test_large_long_constants() {
+        let source = "long sum(long a, long b, long c, long d, long e, long f, long g) {
+    return a + g;
+}
+int main(void) {
+    long x = 3000000000L;
+    long big = 8589934592L;
+    if (x != 3000000000L || !(x < 4294967296L) || x > 3000000000L)
+        return 1;
+    x = x + 4294967296L;
+    if (x != 7294967296L)
+        return 2;
+    if (x * 2 != 14589934592L)
+        return 3;
+    if (sum(1, 2, 3, 4, 5, 6, big) != big + 1)
+        return 4;
+    return x - 7294967256L;
+}
+";
+
+        assert_exit_code("large_long_constants", source, 40);
+    }
+
+    #[test]
+    fn test_large_zero_extended_constants() {
+        let source = "unsigned long f(unsigned long a) { return a; }
+int main(void) {
+    unsigned long a = 1;
+    a = a + 4294967295u;
+    if (a != 4294967296ul)
+        return 1;
+    if (f(4294967295u) != 4294967295ul)
+        return 2;
+    return 42;
+}
+";
+
+        assert_exit_code("large_zero_extended_constants", source, 42);
+    }
+
+    #[test]
+    fn test_function_defined_after_main() {
+        let source = "int helper(int x);
+int main(void) {
+    return helper(20);
+}
+int helper(int x) {
+    return x * 2 + 2;
+}
+";
+
+        assert_exit_code("function_defined_after_main", source, 42);
+    }
+
+    #[test]
+    fn test_do_while_break() {
+        let source = "int main(void) {
+    int runs = 0;
+    do {
+        runs++;
+        break;
+        runs = 100;
+    } while (1);
+    int i = 0;
+    do {
+        if (i == 3) break;
+        i++;
+    } while (i < 10);
+    return runs * 10 + i;
+}
+";
+
+        assert_exit_code("do_while_break", source, 13);
+    }
+
+    #[test]
+    fn test_characters() {
+        let source = "char g = 300;
+unsigned char ug = 511;
+int main(void) {
+    char c = 127;
+    c++;
+    unsigned char u = 250.7;
+    u += 6;
+    signed char s = -3;
+    long l = s;
+    double d = c;
+    char arr[3] = {1, 2, 3};
+    arr[1] = arr[0] + arr[2] * 100;
+    return c == -128 && u == 0 && l == -3 && d == -128.0 && g == 44 && ug == 255
+        && arr[1] == 45 && sizeof arr == 3 && (char)1000 == -24;
+}
+";
+
+        assert_exit_code("characters", source, 1);
+    }
+
+    #[test]
+    fn test_compile_str() {
+        let source = "int main(void) { return 2; }";
+
+        let assembly = compile_str(source, CompilerStage::Full).unwrap();
+        assert!(assembly.contains("main:"));
+        assert!(assembly.contains("\tret"));
+
+        let tokens = compile_str(source, CompilerStage::Lex).unwrap();
+        assert!(tokens.starts_with('['));
+        assert!(tokens.contains("IntKeyword"));
+
+        let error = compile_str("int main(void) { return x; }", CompilerStage::Full).unwrap_err();
+        assert!(matches!(error, CompilerError::Semantic { .. }));
+    }
+
+    #[test]
+    fn test_static_functions_are_not_global() {
+        // a later definition without `static` keeps the internal linkage of the declaration
+        let source = "\
+static int helper(void) { return 1; }
+static int other(void);
+int other(void) { return 2; }
+int main(void) { return helper() + other(); }";
+
+        let options = CompileOptions {
+            target: Target::Linux,
+            strict: true,
+            ..CompileOptions::default()
+        };
+        let assembly = compile_str_with_options(source, CompilerStage::Full, options).unwrap();
+
+        assert!(assembly.contains("\n\t.globl\tmain\n"), "{assembly}");
+        assert!(assembly.contains("\nhelper:\n"), "{assembly}");
+        assert!(!assembly.contains(".globl\thelper"), "{assembly}");
+        assert!(!assembly.contains(".globl\tother"), "{assembly}");
+    }
+
+    #[test]
+    fn test_static_array_initializers() {
+        let source = "\
+static int a[3] = {1, 2, 3};
+long b[4] = {5, 6};
+int main(void) { return a[2] + b[1]; }";
+
+        let options = CompileOptions {
+            target: Target::Linux,
+            strict: true,
+            ..CompileOptions::default()
+        };
+        let assembly = compile_str_with_options(source, CompilerStage::Full, options).unwrap();
+
+        assert!(
+            assembly.contains("a:\n\t.long 1\n\t.long 2\n\t.long 3\n"),
+            "{assembly}"
+        );
+        // the elements without an initializer are zeroed together
+        assert!(
+            assembly.contains("b:\n\t.quad 5\n\t.quad 6\n\t.zero 16\n"),
+            "{assembly}"
+        );
+    }
+
+    #[test]
+    fn test_static_output_is_deterministic() {
+        let source = "\
+static int a = 1;
+int b;
+static long c = 2;
+long d;
+double e = 1.5;
+int main(void) { return a + b + c + d + e; }";
+
+        let assembly = compile_str(source, CompilerStage::Full).unwrap();
+        for _ in 0..10 {
+            assert_eq!(compile_str(source, CompilerStage::Full).unwrap(), assembly);
+        }
+
+        // everything initialized comes before everything that is zeroed
+        let data = assembly.find("\t.data\n").unwrap();
+        let bss = assembly.rfind("\t.bss\n").unwrap();
+        assert!(assembly.find("main:").unwrap() < data, "{assembly}");
+        assert!(assembly.rfind("\t.data\n").unwrap() < bss, "{assembly}");
+        assert!(assembly.find("\t.bss\n").unwrap() > data, "{assembly}");
+    }
+
+    #[test]
+    fn test_const_variables() {
+        let error = compile_str(
+            "int main(void) { const int x = 1; x = 2; return x; }",
+            CompilerStage::Full,
+        )
+        .unwrap_err();
+        assert!(
+            matches!(&error, CompilerError::Semantic { message, .. } if message == "Assignment to const variable x"),
+            "{error:?}"
+        );
+
+        assert_exit_code(
+            "const_variables",
+            "int main(void) { const int y = 1; int *const p = 0; return p ? 0 : y; }",
+            1,
+        );
+    }
+
+    #[test]
+    fn test_compile_defines_main() {
+        let dir = std::env::temp_dir().join(format!("cco-defines-main-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let options = CompileOptions {
+            target: Target::Linux,
+            strict: true,
+            ..CompileOptions::default()
+        };
+
+        let test_cases = [
+            ("int helper(int x) { return x + 1; }", false),
+            ("int main(void);", false),
+            ("static int main(void) { return 0; }", false),
+            ("int main(void) { return 0; }", true),
+        ];
+
+        for (source, expected) in test_cases {
+            let input = dir.join("main.i");
+            std::fs::write(&input, source).unwrap();
+
+            let report =
+                compile(&input, &dir.join("main.s"), CompilerStage::Full, options).unwrap();
+            assert_eq!(report.defines_main, expected, "{source}");
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_compile_reports_warnings() {
+        let dir = std::env::temp_dir().join(format!("cco-warnings-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let input = dir.join("main.i");
+        std::fs::write(&input, "int f(int x) { if (x) return 1; }").unwrap();
+
+        let mut options = CompileOptions {
+            target: Target::Linux,
+            ..CompileOptions::default()
+        };
+        let warning = "Control may reach the end of non-void function f".to_string();
+
+        let report = compile(&input, &dir.join("main.s"), CompilerStage::Full, options).unwrap();
+        assert_eq!(report.warnings, vec![warning.clone()]);
+
+        options.strict = true;
+        let error = compile(&input, &dir.join("main.s"), CompilerStage::Full, options).unwrap_err();
+        assert_eq!(error.message(), warning);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_compile_str_ast() {
+        let source = "long f(int x) { while (x > 0) x = x - 1; return x; }";
+
+        let expected = "\
+long f(int x) {
+    while ((x > 0))
+        (x = (x - 1));
+    return x;
+}
+";
+        assert_eq!(compile_str(source, CompilerStage::Parse).unwrap(), expected);
+
+        let expected = "\
+long f(int sem.var.0.x) {
+    while (((sem.var.0.x: int) > (0: int): int))
+        ((sem.var.0.x: int) = ((sem.var.0.x: int) - (1: int): int): int);
+    return ((long) (sem.var.0.x: int): long);
+}
+";
+        assert_eq!(
+            compile_str(source, CompilerStage::Validate).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_compile_str_symbols() {
+        let source = "static int counter = 3;
+long big[2] = {1};
+int pending;
+extern double rate;
+static int helper(int x) { return x + counter; }
+int main(void) {
+    static char letter = 'a';
+    return helper(1) + letter + (int)rate;
+}
+";
+
+        let expected = "\
+big               long[2]   static, global, initial 1, 8 zero bytes
+counter           int       static, internal, initial 3
+helper            int(int)  function, internal, defined
+main              int()     function, global, defined
+pending           int       static, global, tentative
+rate              double    static, global, defined elsewhere
+sem.var.0.x       int       local
+sem.var.1.letter  char      static, internal, initial 97
+";
+        assert_eq!(
+            compile_str(source, CompilerStage::Symbols).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_compile_str_tacky() {
+        let source = "static int n = 2; int twice(int x) { if (x) return x * n; return 0; }";
+
+        let expected = "\
+global function twice(sem.var.0.x):
+   0  jump_if_zero sem.var.0.x, tac.label.0.if_end
+   1  tac.var.0 = sem.var.0.x * n
+   2  return tac.var.0
+   3  tac.label.0.if_end:
+   4  return 0
+   5  return 0
+
+static n: int = [Int(2)]
+";
+        assert_eq!(compile_str(source, CompilerStage::Tacky).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_for_without_condition() {
+        let source = "int f(int x) { for (;;) { if (x) break; } return 7; }";
+
+        // the only conditional jump is the `if`
+        let expected = "\
+global function f(sem.var.0.x):
+   0  tac.label.0.for_start:
+   1  jump_if_zero sem.var.0.x, tac.label.1.if_end
+   2  jump sem.loop.0.for.break
+   3  tac.label.1.if_end:
+   4  sem.loop.0.for.continue:
+   5  jump tac.label.0.for_start
+   6  sem.loop.0.for.break:
+   7  return 7
+   8  return 0
+";
+        assert_eq!(compile_str(source, CompilerStage::Tacky).unwrap(), expected);
+
+        let source = "int main(void) {
+    int i = 0;
+    for (;;) {
+        if (i == 6) break;
+        i++;
+    }
+    return i;
+}
+";
+
+        assert_exit_code("for_without_condition", source, 6);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_compile_str_json() {
+        assert_eq!(
+            compile_str("int x = 2;", CompilerStage::LexJson).unwrap(),
+            r#"["IntKeyword",{"Identifier":"x"},"Equal",{"ConstantInt":"2"},"Semicolon"]"#
+        );
+
+        let source = "static long n = 3; int f(int *p) { return p[0] ? (int)n : sizeof(double); }";
+        let json = compile_str(source, CompilerStage::ParseJson).unwrap();
+        let program: ast::Program = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            program,
+            parser::parse(&lexer::tokenize(source).unwrap(), false).unwrap()
+        );
+
+        // untyped expressions have no type yet
+        assert!(json.contains(r#"{"Variable":{"v":{"identifier":"n"},"ty":null}}"#));
+    }
 }
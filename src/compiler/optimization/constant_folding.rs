@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+
+use crate::compiler::{
+    ast::Type,
+    prefixes::TAC_VAR_PREFIX,
+    symbols::SymbolTable,
+    tacky::{Constant, Instruction, Label, Value, Variable},
+};
+
+pub struct ConstantFolder<'a> {
+    symbols: &'a SymbolTable,
+    /// Temporaries known to hold a constant at the current instruction.
+    constants: HashMap<String, Constant>,
+}
+
+impl<'a> ConstantFolder<'a> {
+    fn new(symbols: &'a SymbolTable) -> Self {
+        Self {
+            symbols,
+            constants: HashMap::new(),
+        }
+    }
+
+    pub fn optimize(instructions: &[Instruction], symbols: &SymbolTable) -> Vec<Instruction> {
+        let mut folder = ConstantFolder::new(symbols);
+
+        instructions
+            .iter()
+            .filter_map(|instruction| folder.handle_instruction(instruction))
+            .collect()
+    }
+
+    /// Returns `None` for instructions that can be dropped, like jumps that are never taken.
+    fn handle_instruction(&mut self, instruction: &Instruction) -> Option<Instruction> {
+        let folded = match instruction {
+            Instruction::Return(value) => {
+                Instruction::Return(value.as_ref().map(|value| self.substitute(value)))
+            }
+            Instruction::Unary { op, src, dst } => {
+                let src = self.substitute(src);
+
+                match src {
+                    Value::Constant(c) => {
+                        Self::copy(c.apply_unary(*op, self.variable_type(dst)), dst)
+                    }
+                    _ => Instruction::Unary {
+                        op: *op,
+                        src,
+                        dst: dst.clone(),
+                    },
+                }
+            }
+            Instruction::Binary { op, lhs, rhs, dst } => {
+                let lhs = self.substitute(lhs);
+                let rhs = self.substitute(rhs);
+
+                let folded = match (&lhs, &rhs) {
+                    (Value::Constant(l), Value::Constant(r)) => {
+                        l.apply_binary(*op, *r, self.variable_type(dst))
+                    }
+                    _ => None,
+                };
+
+                match folded {
+                    Some(value) => Self::copy(value, dst),
+                    None => Instruction::Binary {
+                        op: *op,
+                        lhs,
+                        rhs,
+                        dst: dst.clone(),
+                    },
+                }
+            }
+            Instruction::Copy { src, dst } => match self.substitute(src) {
+                Value::Constant(c) => self.convert(c, dst),
+                src => Instruction::Copy {
+                    src,
+                    dst: dst.clone(),
+                },
+            },
+            Instruction::SignExtend { src, dst } => match self.substitute(src) {
+                Value::Constant(c) => self.convert(c, dst),
+                src => Instruction::SignExtend {
+                    src,
+                    dst: dst.clone(),
+                },
+            },
+            Instruction::ZeroExtend { src, dst } => match self.substitute(src) {
+                Value::Constant(c) => self.convert(c, dst),
+                src => Instruction::ZeroExtend {
+                    src,
+                    dst: dst.clone(),
+                },
+            },
+            Instruction::Truncate { src, dst } => match self.substitute(src) {
+                Value::Constant(c) => self.convert(c, dst),
+                src => Instruction::Truncate {
+                    src,
+                    dst: dst.clone(),
+                },
+            },
+            // doubles are never tracked, so there is nothing to fold
+            Instruction::DoubleToInt { src, dst } => Instruction::DoubleToInt {
+                src: self.substitute(src),
+                dst: dst.clone(),
+            },
+            Instruction::IntToDouble { src, dst } => Instruction::IntToDouble {
+                src: self.substitute(src),
+                dst: dst.clone(),
+            },
+            // pointers stay variables, codegen needs their type to know the width of the access
+            Instruction::Store { src, dst_ptr } => Instruction::Store {
+                src: self.substitute(src),
+                dst_ptr: dst_ptr.clone(),
+            },
+            Instruction::AddPtr {
+                ptr,
+                index,
+                scale,
+                dst,
+            } => Instruction::AddPtr {
+                ptr: ptr.clone(),
+                index: self.substitute(index),
+                scale: *scale,
+                dst: dst.clone(),
+            },
+            Instruction::JumpIfZero { condition, target } => match self.substitute(condition) {
+                Value::Constant(c) if c.value() == 0 => Self::jump(target),
+                Value::Constant(_) => return None,
+                condition => Instruction::JumpIfZero {
+                    condition,
+                    target: target.clone(),
+                },
+            },
+            Instruction::JumpIfNotZero { condition, target } => match self.substitute(condition) {
+                Value::Constant(c) if c.value() != 0 => Self::jump(target),
+                Value::Constant(_) => return None,
+                condition => Instruction::JumpIfNotZero {
+                    condition,
+                    target: target.clone(),
+                },
+            },
+            Instruction::FunctionCall {
+                function,
+                args,
+                dst,
+            } => Instruction::FunctionCall {
+                function: function.clone(),
+                args: args.iter().map(|arg| self.substitute(arg)).collect(),
+                dst: dst.clone(),
+            },
+            Instruction::IndirectCall {
+                function_ptr,
+                args,
+                dst,
+            } => Instruction::IndirectCall {
+                function_ptr: self.substitute(function_ptr),
+                args: args.iter().map(|arg| self.substitute(arg)).collect(),
+                dst: dst.clone(),
+            },
+            Instruction::JumpTable {
+                condition,
+                base,
+                targets,
+                default,
+                table,
+            } => Instruction::JumpTable {
+                condition: self.substitute(condition),
+                base: *base,
+                targets: targets.clone(),
+                default: default.clone(),
+                table: table.clone(),
+            },
+            // the address of a variable doesn't depend on its value
+            Instruction::GetAddress { .. }
+            | Instruction::Load { .. }
+            | Instruction::Jump { .. }
+            | Instruction::Label(_)
+            | Instruction::SourceLine(_) => instruction.clone(),
+        };
+
+        self.track(&folded);
+
+        Some(folded)
+    }
+
+    fn track(&mut self, instruction: &Instruction) {
+        match instruction {
+            // values can arrive from any jump to a label
+            Instruction::Label(_) => self.constants.clear(),
+            Instruction::Copy {
+                src: Value::Constant(c),
+                dst,
+            } if dst.identifier.starts_with(TAC_VAR_PREFIX) => {
+                self.constants.insert(dst.identifier.clone(), *c);
+            }
+            Instruction::Unary { dst, .. }
+            | Instruction::Binary { dst, .. }
+            | Instruction::Copy { dst, .. }
+            | Instruction::SignExtend { dst, .. }
+            | Instruction::ZeroExtend { dst, .. }
+            | Instruction::Truncate { dst, .. }
+            | Instruction::DoubleToInt { dst, .. }
+            | Instruction::IntToDouble { dst, .. }
+            | Instruction::GetAddress { dst, .. }
+            | Instruction::Load { dst, .. }
+            | Instruction::AddPtr { dst, .. }
+            | Instruction::FunctionCall { dst: Some(dst), .. }
+            | Instruction::IndirectCall { dst: Some(dst), .. } => {
+                self.constants.remove(&dst.identifier);
+            }
+            Instruction::Return(_)
+            | Instruction::FunctionCall { dst: None, .. }
+            | Instruction::IndirectCall { dst: None, .. }
+            | Instruction::Store { .. }
+            | Instruction::Jump { .. }
+            | Instruction::JumpIfZero { .. }
+            | Instruction::JumpIfNotZero { .. }
+            | Instruction::JumpTable { .. }
+            | Instruction::SourceLine(_) => {}
+        }
+    }
+
+    fn substitute(&self, value: &Value) -> Value {
+        match value {
+            Value::Variable(variable) => match self.constants.get(&variable.identifier) {
+                Some(c) => Value::Constant(*c),
+                None => value.clone(),
+            },
+            Value::Constant(_) => value.clone(),
+        }
+    }
+
+    fn variable_type(&self, variable: &Variable) -> &Type {
+        &self.symbols.get_checked(&variable.identifier).ty
+    }
+
+    /// Copies `c` into `dst`, converted to the type of `dst`.
+    fn convert(&self, c: Constant, dst: &Variable) -> Instruction {
+        Self::copy(Constant::new(c.value(), self.variable_type(dst)), dst)
+    }
+
+    fn jump(target: &Label) -> Instruction {
+        Instruction::Jump {
+            target: target.clone(),
+        }
+    }
+
+    fn copy(value: Constant, dst: &Variable) -> Instruction {
+        Instruction::Copy {
+            src: Value::Constant(value),
+            dst: dst.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::compiler::{
+        lexer, parser, semantic,
+        tacky::{BinaryOperator, Program, TopLevelItem},
+        tackygen,
+    };
+
+    fn fold_main(source: &str) -> Vec<Instruction> {
+        let tokens = lexer::tokenize(source).unwrap();
+        let ast = parser::parse(&tokens, false).unwrap();
+        let (ast, mut symbols, _) = semantic::analyze(&ast, false).unwrap();
+        let Program { items } = tackygen::generate(&ast, &mut symbols);
+
+        let Some(TopLevelItem::FunctionDefinition(fd)) = items.first() else {
+            panic!("expected a function definition");
+        };
+
+        ConstantFolder::optimize(&fd.instructions, &symbols)
+    }
+
+    fn first_return(instructions: &[Instruction]) -> &Value {
+        instructions
+            .iter()
+            .find_map(|instruction| match instruction {
+                Instruction::Return(value) => value.as_ref(),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_fold_arithmetic() {
+        let instructions = fold_main("int main(void) { return 2 + 3 * 4; }");
+
+        assert_eq!(
+            first_return(&instructions),
+            &Value::Constant(Constant::Int(14))
+        );
+    }
+
+    #[test]
+    fn test_fold_wraparound() {
+        let instructions = fold_main("int main(void) { return 2147483647 + 1; }");
+        assert_eq!(
+            first_return(&instructions),
+            &Value::Constant(Constant::Int(i32::MIN))
+        );
+
+        let instructions = fold_main("long main(void) { return -(9223372036854775807L) - 1L; }");
+        assert_eq!(
+            first_return(&instructions),
+            &Value::Constant(Constant::Long(i64::MIN))
+        );
+
+        let instructions = fold_main("int main(void) { return (int) 4294967297L; }");
+        assert_eq!(
+            first_return(&instructions),
+            &Value::Constant(Constant::Int(1))
+        );
+    }
+
+    #[test]
+    fn test_fold_keeps_types() {
+        let instructions = fold_main("unsigned int f(void) { return 4294967295U + 1U; }");
+        assert_eq!(
+            first_return(&instructions),
+            &Value::Constant(Constant::UInt(0))
+        );
+
+        let instructions = fold_main("unsigned long f(void) { return -1; }");
+        assert_eq!(
+            first_return(&instructions),
+            &Value::Constant(Constant::ULong(u64::MAX))
+        );
+
+        let instructions = fold_main("int f(void) { return 4294967295U > 1; }");
+        assert_eq!(
+            first_return(&instructions),
+            &Value::Constant(Constant::Int(1))
+        );
+    }
+
+    #[test]
+    fn test_division_by_zero_is_not_folded() {
+        let instructions = fold_main("int main(void) { return 1 / 0; }");
+
+        assert!(instructions.iter().any(|instruction| matches!(
+            instruction,
+            Instruction::Binary {
+                op: BinaryOperator::Divide,
+                lhs: Value::Constant(Constant::Int(1)),
+                rhs: Value::Constant(Constant::Int(0)),
+                ..
+            }
+        )));
+    }
+
+    #[test]
+    fn test_fold_constant_conditions() {
+        let label = |name: &str| Label {
+            identifier: name.to_string(),
+        };
+        let constant = |n| Value::Constant(Constant::Int(n));
+
+        let instructions = vec![
+            Instruction::JumpIfZero {
+                condition: constant(0),
+                target: label("taken"),
+            },
+            Instruction::JumpIfZero {
+                condition: constant(3),
+                target: label("never"),
+            },
+            Instruction::JumpIfNotZero {
+                condition: constant(-1),
+                target: label("taken"),
+            },
+            Instruction::JumpIfNotZero {
+                condition: constant(0),
+                target: label("never"),
+            },
+            Instruction::Label(label("taken")),
+            Instruction::Return(Some(constant(1))),
+        ];
+
+        assert_eq!(
+            ConstantFolder::optimize(&instructions, &SymbolTable::new()),
+            vec![
+                Instruction::Jump {
+                    target: label("taken"),
+                },
+                Instruction::Jump {
+                    target: label("taken"),
+                },
+                Instruction::Label(label("taken")),
+                Instruction::Return(Some(constant(1))),
+            ]
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::compiler::tacky::{Instruction, Label};
+
+pub struct JumpThreader;
+
+impl JumpThreader {
+    /// Leaves behind labels that are no longer jumped to, and jumps to the next instruction, for
+    /// the unreachable code eliminator.
+    pub fn optimize(instructions: &[Instruction]) -> Vec<Instruction> {
+        Self::invert_jumps_over_jumps(&Self::thread_jumps(instructions))
+    }
+
+    /// Jumps to a label that is only followed by another jump go straight to that jump's target,
+    /// e.g. out of the empty body of a nested loop.
+    fn thread_jumps(instructions: &[Instruction]) -> Vec<Instruction> {
+        let mut forwards = HashMap::new();
+
+        for (i, instruction) in instructions.iter().enumerate() {
+            if let Instruction::Label(label) = instruction {
+                if let Some(Instruction::Jump { target }) =
+                    instructions[i + 1..].iter().find(|next| {
+                        !matches!(next, Instruction::Label(_) | Instruction::SourceLine(_))
+                    })
+                {
+                    forwards.insert(label.identifier.clone(), target.clone());
+                }
+            }
+        }
+
+        let resolve = |label: &Label| {
+            let mut label = label;
+            let mut seen = HashSet::new();
+
+            // a cycle of jumps, e.g. `while (1);`, stays where it is
+            while let Some(next) = forwards.get(&label.identifier) {
+                if !seen.insert(&label.identifier) {
+                    break;
+                }
+                label = next;
+            }
+
+            label.clone()
+        };
+
+        instructions
+            .iter()
+            .map(|instruction| match instruction {
+                Instruction::Jump { target } => Instruction::Jump {
+                    target: resolve(target),
+                },
+                Instruction::JumpIfZero { condition, target } => Instruction::JumpIfZero {
+                    condition: condition.clone(),
+                    target: resolve(target),
+                },
+                Instruction::JumpIfNotZero { condition, target } => Instruction::JumpIfNotZero {
+                    condition: condition.clone(),
+                    target: resolve(target),
+                },
+                Instruction::JumpTable {
+                    condition,
+                    base,
+                    targets,
+                    default,
+                    table,
+                } => Instruction::JumpTable {
+                    condition: condition.clone(),
+                    base: *base,
+                    targets: targets.iter().map(resolve).collect(),
+                    default: resolve(default),
+                    table: table.clone(),
+                },
+                _ => instruction.clone(),
+            })
+            .collect()
+    }
+
+    /// A conditional jump over an unconditional one becomes the opposite conditional jump, e.g.
+    /// for the empty then-branch of `if (x); else ...`.
+    fn invert_jumps_over_jumps(instructions: &[Instruction]) -> Vec<Instruction> {
+        let mut result = Vec::new();
+        let mut i = 0;
+
+        while i < instructions.len() {
+            if let [condition_jump, Instruction::Jump { target }, Instruction::Label(label), ..] =
+                &instructions[i..]
+            {
+                let inverted = match condition_jump {
+                    Instruction::JumpIfZero {
+                        condition,
+                        target: over,
+                    } if over == label => Some(Instruction::JumpIfNotZero {
+                        condition: condition.clone(),
+                        target: target.clone(),
+                    }),
+                    Instruction::JumpIfNotZero {
+                        condition,
+                        target: over,
+                    } if over == label => Some(Instruction::JumpIfZero {
+                        condition: condition.clone(),
+                        target: target.clone(),
+                    }),
+                    _ => None,
+                };
+
+                if let Some(inverted) = inverted {
+                    result.push(inverted);
+                    i += 2;
+                    continue;
+                }
+            }
+
+            result.push(instructions[i].clone());
+            i += 1;
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::compiler::{
+        lexer, parser, semantic,
+        tacky::{Program, TopLevelItem, Value, Variable},
+        tackygen,
+    };
+
+    fn count_jumps(instructions: &[Instruction]) -> usize {
+        instructions
+            .iter()
+            .filter(|instruction| {
+                matches!(
+                    instruction,
+                    Instruction::Jump { .. }
+                        | Instruction::JumpIfZero { .. }
+                        | Instruction::JumpIfNotZero { .. }
+                )
+            })
+            .count()
+    }
+
+    #[test]
+    fn test_invert_jumps_over_jumps() {
+        let source = "int f(int x) { if (x); else x = 2; while (x < 5) x = x + 1; return x; }";
+
+        let tokens = lexer::tokenize(source).unwrap();
+        let ast = parser::parse(&tokens, false).unwrap();
+        let (ast, mut symbols, _) = semantic::analyze(&ast, false).unwrap();
+        let Program { items } = tackygen::generate(&ast, &mut symbols);
+
+        let Some(TopLevelItem::FunctionDefinition(fd)) = items.first() else {
+            panic!("expected a function definition");
+        };
+
+        let optimized = JumpThreader::optimize(&fd.instructions);
+
+        // one jump fewer for the empty then-branch
+        assert_eq!(count_jumps(&fd.instructions), 4);
+        assert_eq!(count_jumps(&optimized), 3);
+        assert!(matches!(
+            optimized.as_slice(),
+            [Instruction::JumpIfNotZero { .. }, Instruction::Label(_), ..]
+        ));
+    }
+
+    #[test]
+    fn test_thread_jumps() {
+        let label = |identifier: &str| Label {
+            identifier: identifier.to_string(),
+        };
+        let jump = |identifier| Instruction::Jump {
+            target: label(identifier),
+        };
+        let x = Value::Variable(Variable {
+            identifier: "x".to_string(),
+        });
+        let ret = Instruction::Return(Some(x.clone()));
+
+        let instructions = vec![
+            Instruction::JumpIfZero {
+                condition: x.clone(),
+                target: label("a"),
+            },
+            ret.clone(),
+            Instruction::Label(label("a")),
+            jump("b"),
+            Instruction::Label(label("b")),
+            Instruction::Label(label("c")),
+            jump("d"),
+            Instruction::Label(label("d")),
+            ret.clone(),
+            // a jump to itself is left alone
+            Instruction::Label(label("e")),
+            jump("e"),
+        ];
+
+        // the labels and jumps in between are left for unreachable code elimination
+        assert_eq!(
+            JumpThreader::optimize(&instructions),
+            vec![
+                Instruction::JumpIfZero {
+                    condition: x,
+                    target: label("d"),
+                },
+                ret.clone(),
+                Instruction::Label(label("a")),
+                jump("d"),
+                Instruction::Label(label("b")),
+                Instruction::Label(label("c")),
+                jump("d"),
+                Instruction::Label(label("d")),
+                ret,
+                Instruction::Label(label("e")),
+                jump("e"),
+            ]
+        );
+    }
+}
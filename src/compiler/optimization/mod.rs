@@ -0,0 +1,91 @@
+use crate::compiler::{
+    asm,
+    symbols::SymbolTable,
+    tacky::{Program, TopLevelItem},
+};
+
+mod constant_folding;
+mod jump_threading;
+mod peephole;
+mod unreachable_code;
+
+use constant_folding::ConstantFolder;
+use jump_threading::JumpThreader;
+use peephole::PeepholeOptimizer;
+use unreachable_code::UnreachableCodeEliminator;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptLevel {
+    O0,
+    O1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Optimizations {
+    pub fold_constants: bool,
+    pub eliminate_unreachable_code: bool,
+    /// Send jumps straight to where they end up and invert conditional jumps over jumps.
+    pub thread_jumps: bool,
+    /// Keep pseudos in registers instead of giving each one a stack slot.
+    pub allocate_registers: bool,
+    pub remove_redundant_moves: bool,
+}
+
+impl From<OptLevel> for Optimizations {
+    fn from(level: OptLevel) -> Self {
+        match level {
+            OptLevel::O0 => Self {
+                fold_constants: false,
+                eliminate_unreachable_code: false,
+                thread_jumps: false,
+                allocate_registers: false,
+                remove_redundant_moves: false,
+            },
+            OptLevel::O1 => Self {
+                fold_constants: true,
+                eliminate_unreachable_code: true,
+                thread_jumps: true,
+                allocate_registers: true,
+                remove_redundant_moves: true,
+            },
+        }
+    }
+}
+
+pub fn optimize(program: &Program, symbols: &SymbolTable, optimizations: Optimizations) -> Program {
+    let mut result = program.clone();
+
+    for item in result.items.iter_mut() {
+        if let TopLevelItem::FunctionDefinition(fd) = item {
+            if optimizations.fold_constants {
+                fd.instructions = ConstantFolder::optimize(&fd.instructions, symbols);
+            }
+
+            if optimizations.eliminate_unreachable_code {
+                fd.instructions = UnreachableCodeEliminator::optimize(&fd.instructions);
+            }
+
+            if optimizations.thread_jumps {
+                fd.instructions = JumpThreader::optimize(&fd.instructions);
+
+                // to remove what threading left behind
+                if optimizations.eliminate_unreachable_code {
+                    fd.instructions = UnreachableCodeEliminator::optimize(&fd.instructions);
+                }
+            }
+        }
+    }
+
+    result
+}
+
+pub fn optimize_assembly(
+    instructions: &[asm::Instruction],
+    optimizations: Optimizations,
+) -> Vec<asm::Instruction> {
+    if optimizations.remove_redundant_moves {
+        PeepholeOptimizer::optimize(instructions)
+    } else {
+        instructions.to_vec()
+    }
+}
@@ -0,0 +1,119 @@
+use crate::compiler::asm::Instruction;
+
+pub struct PeepholeOptimizer;
+
+impl PeepholeOptimizer {
+    /// Removes moves that can't change any value.
+    ///
+    /// Dropping a `movl` between the same register skips zeroing its upper half, which is fine
+    /// because a longword in a register is never read as a quadword.
+    pub fn optimize(instructions: &[Instruction]) -> Vec<Instruction> {
+        let mut result: Vec<Instruction> = Vec::new();
+
+        for instruction in instructions {
+            // comments from `--annotate` shouldn't change what gets removed
+            let previous = result
+                .iter()
+                .rev()
+                .find(|previous| !matches!(previous, Instruction::Comment(_)));
+
+            match (previous, instruction) {
+                (_, Instruction::Mov { src, dst, .. }) if src == dst => {}
+                // a reload of the location that was just stored from
+                (
+                    Some(Instruction::Mov {
+                        ty: stored_ty,
+                        src: stored_src,
+                        dst: stored_dst,
+                    }),
+                    Instruction::Mov { ty, src, dst },
+                ) if stored_ty == ty && stored_src == dst && stored_dst == src => {}
+                _ => result.push(instruction.clone()),
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::compiler::asm::{AssemblyType, Operand, Reg};
+
+    fn mov(ty: AssemblyType, src: Operand, dst: Operand) -> Instruction {
+        Instruction::Mov { ty, src, dst }
+    }
+
+    #[test]
+    fn test_remove_self_moves() {
+        let instructions = vec![
+            mov(
+                AssemblyType::Longword,
+                Operand::Reg(Reg::AX),
+                Operand::Reg(Reg::AX),
+            ),
+            mov(
+                AssemblyType::Quadword,
+                Operand::Stack(-8),
+                Operand::Stack(-8),
+            ),
+            Instruction::Ret,
+        ];
+
+        assert_eq!(
+            PeepholeOptimizer::optimize(&instructions),
+            vec![Instruction::Ret]
+        );
+    }
+
+    #[test]
+    fn test_remove_store_then_reload() {
+        let load = mov(
+            AssemblyType::Longword,
+            Operand::Stack(-4),
+            Operand::Reg(Reg::R10),
+        );
+
+        let instructions = vec![
+            load.clone(),
+            mov(
+                AssemblyType::Longword,
+                Operand::Reg(Reg::R10),
+                Operand::Stack(-4),
+            ),
+            Instruction::Ret,
+        ];
+
+        assert_eq!(
+            PeepholeOptimizer::optimize(&instructions),
+            vec![load, Instruction::Ret]
+        );
+    }
+
+    #[test]
+    fn test_keep_other_moves() {
+        let instructions = vec![
+            // moves of different sizes are left alone
+            mov(
+                AssemblyType::Quadword,
+                Operand::Stack(-8),
+                Operand::Reg(Reg::R10),
+            ),
+            mov(
+                AssemblyType::Longword,
+                Operand::Reg(Reg::R10),
+                Operand::Stack(-8),
+            ),
+            mov(
+                AssemblyType::Longword,
+                Operand::Reg(Reg::R10),
+                Operand::Stack(-12),
+            ),
+            Instruction::Ret,
+        ];
+
+        assert_eq!(PeepholeOptimizer::optimize(&instructions), instructions);
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::HashSet;
+
+use crate::compiler::tacky::Instruction;
+
+pub struct UnreachableCodeEliminator;
+
+impl UnreachableCodeEliminator {
+    pub fn optimize(instructions: &[Instruction]) -> Vec<Instruction> {
+        let mut result = instructions.to_vec();
+
+        // dropping a label can make the code after it unreachable, so repeat until stable
+        loop {
+            let optimized = Self::remove_unused_labels(&Self::remove_redundant_jumps(
+                &Self::remove_unreachable(&result),
+            ));
+
+            if optimized.len() == result.len() {
+                return optimized;
+            }
+
+            result = optimized;
+        }
+    }
+
+    /// Removes everything between a `Return`/`Jump` and the next `Label`.
+    fn remove_unreachable(instructions: &[Instruction]) -> Vec<Instruction> {
+        let mut result = Vec::new();
+        let mut reachable = true;
+
+        for instruction in instructions {
+            if let Instruction::Label(_) = instruction {
+                reachable = true;
+            }
+
+            if reachable {
+                result.push(instruction.clone());
+            }
+
+            if let Instruction::Return(_) | Instruction::Jump { .. } = instruction {
+                reachable = false;
+            }
+        }
+
+        result
+    }
+
+    /// Removes jumps to where execution would continue anyway, e.g. past the empty body of
+    /// `if (x);`. Conditions are values without side effects, so they can go too.
+    fn remove_redundant_jumps(instructions: &[Instruction]) -> Vec<Instruction> {
+        instructions
+            .iter()
+            .enumerate()
+            .filter(|(i, instruction)| {
+                let (Instruction::Jump { target }
+                | Instruction::JumpIfZero { target, .. }
+                | Instruction::JumpIfNotZero { target, .. }) = instruction
+                else {
+                    return true;
+                };
+
+                !instructions[i + 1..]
+                    .iter()
+                    .take_while(|next| {
+                        matches!(next, Instruction::Label(_) | Instruction::SourceLine(_))
+                    })
+                    .any(|next| next == &Instruction::Label(target.clone()))
+            })
+            .map(|(_, instruction)| instruction.clone())
+            .collect()
+    }
+
+    fn remove_unused_labels(instructions: &[Instruction]) -> Vec<Instruction> {
+        let mut targeted = HashSet::new();
+
+        for instruction in instructions {
+            match instruction {
+                Instruction::Jump { target }
+                | Instruction::JumpIfZero { target, .. }
+                | Instruction::JumpIfNotZero { target, .. } => {
+                    targeted.insert(target.identifier.clone());
+                }
+                Instruction::JumpTable {
+                    targets, default, ..
+                } => {
+                    targeted.extend(targets.iter().map(|t| t.identifier.clone()));
+                    targeted.insert(default.identifier.clone());
+                }
+                _ => {}
+            }
+        }
+
+        instructions
+            .iter()
+            .filter(|instruction| match instruction {
+                Instruction::Label(label) => targeted.contains(&label.identifier),
+                _ => true,
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::compiler::{
+        lexer, parser, semantic,
+        tacky::{Constant, Label, Program, TopLevelItem, Value, Variable},
+        tackygen,
+    };
+
+    #[test]
+    fn test_early_return() {
+        let source = "int main(void) { int a = 1; return a; a = 2; if (a) { a = 3; } return a; }";
+
+        let tokens = lexer::tokenize(source).unwrap();
+        let ast = parser::parse(&tokens, false).unwrap();
+        let (ast, mut symbols, _) = semantic::analyze(&ast, false).unwrap();
+        let Program { items } = tackygen::generate(&ast, &mut symbols);
+
+        let Some(TopLevelItem::FunctionDefinition(fd)) = items.first() else {
+            panic!("expected a function definition");
+        };
+
+        let optimized = UnreachableCodeEliminator::optimize(&fd.instructions);
+
+        assert!(optimized.len() < fd.instructions.len());
+        assert!(matches!(optimized.last(), Some(Instruction::Return(_))));
+        assert_eq!(
+            optimized
+                .iter()
+                .filter(|instruction| matches!(instruction, Instruction::Return(_)))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_targeted_labels_are_kept() {
+        let label = Label {
+            identifier: "loop".to_string(),
+        };
+
+        let instructions = vec![
+            Instruction::Label(label.clone()),
+            Instruction::JumpIfZero {
+                condition: Value::Constant(Constant::Int(0)),
+                target: label.clone(),
+            },
+            Instruction::Return(Some(Value::Constant(Constant::Int(1)))),
+            Instruction::Label(Label {
+                identifier: "unused".to_string(),
+            }),
+            Instruction::Return(Some(Value::Constant(Constant::Int(2)))),
+        ];
+
+        assert_eq!(
+            UnreachableCodeEliminator::optimize(&instructions),
+            instructions[..3].to_vec()
+        );
+    }
+
+    fn optimize_source(source: &str) -> Vec<Instruction> {
+        let tokens = lexer::tokenize(source).unwrap();
+        let ast = parser::parse(&tokens, false).unwrap();
+        let (ast, mut symbols, _) = semantic::analyze(&ast, false).unwrap();
+        let Program { items } = tackygen::generate(&ast, &mut symbols);
+
+        let Some(TopLevelItem::FunctionDefinition(fd)) = items.first() else {
+            panic!("expected a function definition");
+        };
+
+        UnreachableCodeEliminator::optimize(&fd.instructions)
+    }
+
+    #[test]
+    fn test_empty_if() {
+        let optimized = optimize_source("int f(int x) { if (x); if (x) {} else {} return x; }");
+
+        assert_eq!(
+            optimized,
+            vec![Instruction::Return(Some(Value::Variable(Variable {
+                identifier: "sem.var.0.x".to_string(),
+            })))]
+        );
+    }
+}
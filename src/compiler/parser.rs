@@ -2,129 +2,525 @@ use std::collections::VecDeque;
 
 use crate::compiler::{
     ast::{
-        AssignmentOperator, BinaryOperator, Block, BlockItem, Constant, Declaration, Expression,
-        ForInitializer, Function, FunctionDeclaration, Label, Program, Statement, StorageClass,
-        Type, UnaryOperator, Variable, VariableDeclaration,
+        AssignmentOperator, BinaryOperator, Block, BlockItem, Constant, Declaration,
+        EnumDeclaration, Enumerator, Expression, ForInitializer, Function, FunctionDeclaration,
+        Initializer, Label, Program, Statement, StorageClass, Type, UnaryOperator, Variable,
+        VariableDeclaration,
     },
+    error::{CompilerError, Span},
     token::Token,
 };
 
-pub fn parse(tokens: &[Token]) -> Result<Program, String> {
-    let mut tokens = VecDeque::from_iter(tokens.iter().cloned());
+/// The tokens still to be parsed, with where each one starts if that is known.
+struct Tokens {
+    tokens: VecDeque<(Token, Option<Span>)>,
+    /// Whether to accept a trailing comma after the last argument of a call.
+    lenient: bool,
+}
+
+impl Tokens {
+    fn front(&self) -> Option<&Token> {
+        self.tokens.front().map(|(token, _)| token)
+    }
+
+    fn get(&self, i: usize) -> Option<&Token> {
+        self.tokens.get(i).map(|(token, _)| token)
+    }
+
+    fn pop_front(&mut self) -> Option<Token> {
+        self.tokens.pop_front().map(|(token, _)| token)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// The line the next token is on.
+    fn line(&self) -> Option<usize> {
+        self.tokens
+            .front()
+            .and_then(|(_, span)| span.map(|span| span.line))
+    }
+}
+
+impl From<Vec<Token>> for Tokens {
+    fn from(tokens: Vec<Token>) -> Self {
+        Self {
+            tokens: tokens.into_iter().map(|token| (token, None)).collect(),
+            lenient: false,
+        }
+    }
+}
 
-    let program = parse_program(&mut tokens)?;
+/// With `lenient`, some common extensions to C are accepted too.
+pub fn parse(tokens: &[Token], lenient: bool) -> Result<Program, CompilerError> {
+    parse_tokens(Tokens {
+        lenient,
+        ..Tokens::from(tokens.to_vec())
+    })
+}
+
+/// Like `parse`, but records the source line of every block item.
+pub fn parse_with_spans(tokens: &[(Token, Span)], lenient: bool) -> Result<Program, CompilerError> {
+    parse_tokens(Tokens {
+        tokens: tokens
+            .iter()
+            .map(|(token, span)| (token.clone(), Some(*span)))
+            .collect(),
+        lenient,
+    })
+}
+
+fn parse_tokens(mut tokens: Tokens) -> Result<Program, CompilerError> {
+    let program = parse_program(&mut tokens).map_err(|message| CompilerError::Parse {
+        message,
+        span: None,
+    })?;
 
     if !tokens.is_empty() {
-        return Err("Expected EOF".to_string());
+        return Err(CompilerError::Parse {
+            message: "Expected EOF".to_string(),
+            span: None,
+        });
     }
 
     Ok(program)
 }
 
-fn parse_program(tokens: &mut VecDeque<Token>) -> Result<Program, String> {
+fn parse_program(tokens: &mut Tokens) -> Result<Program, String> {
     Ok(Program {
         declarations: parse_declarations(tokens)?,
     })
 }
 
-fn parse_declarations(tokens: &mut VecDeque<Token>) -> Result<Vec<Declaration>, String> {
+fn parse_declarations(tokens: &mut Tokens) -> Result<Vec<Declaration>, String> {
     let mut declarations = Vec::new();
 
     while !tokens.is_empty() {
-        declarations.push(parse_declaration(tokens)?);
+        declarations.extend(parse_declaration(tokens)?);
     }
 
     Ok(declarations)
 }
 
-fn parse_declaration(tokens: &mut VecDeque<Token>) -> Result<Declaration, String> {
-    let (ty, storage_class) = parse_type_and_storage_class(tokens)?;
+/// Parses a declaration, which can declare several names sharing the type specifiers and storage
+/// class, e.g. `static int a, *b = 0, f(void);`.
+fn parse_declaration(tokens: &mut Tokens) -> Result<Vec<Declaration>, String> {
+    let DeclarationSpecifiers {
+        ty: base_ty,
+        storage_class,
+        enum_declaration,
+        is_const,
+    } = parse_declaration_specifiers(tokens)?;
+    let declares_enum = enum_declaration.is_some();
+    let mut declarations: Vec<_> = enum_declaration
+        .into_iter()
+        .map(Declaration::Enum)
+        .collect();
+
+    // e.g. `enum Color { RED, GREEN };` only declares the enumerators
+    if declares_enum && tokens.front() == Some(&Token::Semicolon) {
+        tokens.pop_front();
+        return Ok(declarations);
+    }
+
+    loop {
+        let declarator = parse_declarator(tokens, false)?;
+        let (identifier, ty, parameters) = process_declarator(&declarator, base_ty.clone())?;
 
-    let Some(Token::Identifier(identifier)) = tokens.pop_front() else {
-        return Err("Expected identifier".to_string());
+        if let Type::Function { .. } = ty {
+            if tokens.front() == Some(&Token::OpenBrace) {
+                if declarations.len() > declares_enum as usize {
+                    return Err("Function definitions cannot share a declaration".to_string());
+                }
+
+                declarations.push(Declaration::Function(FunctionDeclaration {
+                    function: Function { identifier },
+                    parameters,
+                    body: Some(parse_block(tokens)?),
+                    ty,
+                    storage_class,
+                }));
+                return Ok(declarations);
+            }
+
+            declarations.push(Declaration::Function(FunctionDeclaration {
+                function: Function { identifier },
+                parameters,
+                body: None,
+                ty,
+                storage_class,
+            }));
+        } else {
+            let initializer = if let Some(Token::Equal) = tokens.front() {
+                tokens.pop_front();
+                Some(parse_initializer(tokens)?)
+            } else {
+                None
+            };
+
+            declarations.push(Declaration::Variable(VariableDeclaration {
+                variable: Variable { identifier },
+                initializer,
+                ty,
+                storage_class,
+                is_const: is_const_declarator(&declarator, is_const),
+            }));
+        }
+
+        match tokens.pop_front() {
+            Some(Token::Comma) => {}
+            Some(Token::Semicolon) => return Ok(declarations),
+            _ => return Err("Expected semicolon".to_string()),
+        }
+    }
+}
+
+fn parse_initializer(tokens: &mut Tokens) -> Result<Initializer, String> {
+    if tokens.front() != Some(&Token::OpenBrace) {
+        return Ok(Initializer::Single(parse_assignment_expression(tokens)?));
+    }
+
+    tokens.pop_front();
+
+    let mut initializers = vec![parse_initializer(tokens)?];
+
+    // a trailing comma is allowed
+    while let Some(Token::Comma) = tokens.front() {
+        tokens.pop_front();
+
+        if tokens.front() == Some(&Token::CloseBrace) {
+            break;
+        }
+
+        initializers.push(parse_initializer(tokens)?);
+    }
+
+    let Some(Token::CloseBrace) = tokens.pop_front() else {
+        return Err("Expected close brace".to_string());
+    };
+
+    Ok(Initializer::Compound(initializers))
+}
+
+/// The part of a declaration after the type specifiers, e.g. `*f(int a)` in `int *f(int a)`.
+#[derive(Debug, Clone, PartialEq)]
+enum Declarator {
+    Identifier(String),
+    /// A declarator without a name, e.g. `(*)(int)` in a parameter list.
+    Abstract,
+    Pointer(Box<Declarator>),
+    /// A `const` after a `*`, e.g. `*const p`.
+    Const(Box<Declarator>),
+    Array(Box<Declarator>, usize),
+    Function(Vec<(Type, Declarator)>, Box<Declarator>),
+}
+
+/// Only parameters can have abstract declarators.
+fn parse_declarator(tokens: &mut Tokens, allow_abstract: bool) -> Result<Declarator, String> {
+    if let Some(Token::Asterisk) = tokens.front() {
+        tokens.pop_front();
+        let is_const = skip_type_qualifiers(tokens);
+        let mut inner = parse_declarator(tokens, allow_abstract)?;
+        if is_const {
+            inner = Declarator::Const(Box::new(inner));
+        }
+        return Ok(Declarator::Pointer(Box::new(inner)));
+    }
+
+    let mut declarator = match tokens.front() {
+        Some(Token::Comma | Token::CloseParen | Token::OpenBracket) if allow_abstract => {
+            Declarator::Abstract
+        }
+        _ => parse_direct_declarator(tokens, allow_abstract)?,
     };
 
     if let Some(Token::OpenParen) = tokens.front() {
         tokens.pop_front();
         let parameters = parse_parameters(tokens)?;
-        let (parameter_variables, parameter_types) = parameters.into_iter().unzip();
 
         let Some(Token::CloseParen) = tokens.pop_front() else {
             return Err("Expected close parenthesis".to_string());
         };
 
-        let body = if let Some(Token::Semicolon) = tokens.front() {
+        return Ok(Declarator::Function(parameters, Box::new(declarator)));
+    }
+
+    while let Some(Token::OpenBracket) = tokens.front() {
+        tokens.pop_front();
+
+        // a parameter like `char *argv[]` is a pointer, so it doesn't need a size
+        let unsized_parameter = allow_abstract
+            && matches!(declarator, Declarator::Identifier(_) | Declarator::Abstract)
+            && tokens.front() == Some(&Token::CloseBracket);
+        if unsized_parameter {
             tokens.pop_front();
-            None
-        } else {
-            Some(parse_block(tokens)?)
-        };
+            declarator = Declarator::Pointer(Box::new(declarator));
+            continue;
+        }
 
-        Ok(Declaration::Function(FunctionDeclaration {
-            function: Function { identifier },
-            parameters: parameter_variables,
-            body,
-            ty: Type::Function {
-                return_type: Box::new(ty),
-                parameters: parameter_types,
+        declarator = Declarator::Array(Box::new(declarator), parse_array_size(tokens)?);
+    }
+
+    Ok(declarator)
+}
+
+/// Parses the name being declared, or a parenthesized declarator like the `(*fp)` in
+/// `int (*fp)(int)`.
+fn parse_direct_declarator(
+    tokens: &mut Tokens,
+    allow_abstract: bool,
+) -> Result<Declarator, String> {
+    match tokens.pop_front() {
+        Some(Token::Identifier(identifier)) => Ok(Declarator::Identifier(identifier)),
+        Some(Token::OpenParen) => {
+            let inner = parse_declarator(tokens, allow_abstract)?;
+
+            let Some(Token::CloseParen) = tokens.pop_front() else {
+                return Err("Expected close parenthesis".to_string());
+            };
+
+            Ok(inner)
+        }
+        _ => Err("Expected identifier".to_string()),
+    }
+}
+
+/// Parses the `3]` in `[3]`.
+fn parse_array_size(tokens: &mut Tokens) -> Result<usize, String> {
+    let size = match tokens.pop_front() {
+        Some(
+            Token::ConstantInt(value)
+            | Token::ConstantLong(value)
+            | Token::ConstantUInt(value)
+            | Token::ConstantULong(value),
+        ) => value
+            .parse::<usize>()
+            .map_err(|_| "Invalid array size".to_string())?,
+        _ => return Err("Expected constant array size".to_string()),
+    };
+
+    if size == 0 {
+        return Err("Array size must be positive".to_string());
+    }
+
+    let Some(Token::CloseBracket) = tokens.pop_front() else {
+        return Err("Expected close bracket".to_string());
+    };
+
+    Ok(size)
+}
+
+/// Applies `declarator` to `base_ty`, returning the declared name, its type and the names of its
+/// parameters, if it's a function.
+fn process_declarator(
+    declarator: &Declarator,
+    base_ty: Type,
+) -> Result<(String, Type, Vec<Variable>), String> {
+    match declarator {
+        Declarator::Identifier(identifier) => Ok((identifier.clone(), base_ty, vec![])),
+        Declarator::Abstract => Ok((String::new(), base_ty, vec![])),
+        Declarator::Pointer(inner) => process_declarator(inner, Type::Pointer(Box::new(base_ty))),
+        Declarator::Const(inner) => process_declarator(inner, base_ty),
+        Declarator::Array(inner, size) => process_declarator(
+            inner,
+            Type::Array {
+                element: Box::new(base_ty),
+                size: *size,
             },
-            storage_class,
-        }))
-    } else {
-        let initializer = if let Some(Token::Equal) = tokens.front() {
-            tokens.pop_front();
-            let expression = parse_expression(tokens, 0)?;
+        ),
+        Declarator::Function(parameters, inner) => {
+            let mut parameter_variables = Vec::new();
+            let mut parameter_types = Vec::new();
 
-            Some(expression)
-        } else {
-            None
-        };
+            for (parameter_base_ty, parameter_declarator) in parameters {
+                let (parameter, ty, _) =
+                    process_declarator(parameter_declarator, parameter_base_ty.clone())?;
 
-        let Some(Token::Semicolon) = tokens.pop_front() else {
-            return Err("Expected semicolon".to_string());
-        };
+                if let Type::Function { .. } = ty {
+                    return Err("Function parameters must be declared as pointers".to_string());
+                }
 
-        Ok(Declaration::Variable(VariableDeclaration {
-            variable: Variable { identifier },
-            initializer,
-            ty,
-            storage_class,
-        }))
+                parameter_variables.push(Variable {
+                    identifier: parameter,
+                });
+                parameter_types.push(ty);
+            }
+
+            let ty = Type::Function {
+                return_type: Box::new(base_ty),
+                parameters: parameter_types,
+            };
+
+            match inner.as_ref() {
+                Declarator::Identifier(identifier) => {
+                    Ok((identifier.clone(), ty, parameter_variables))
+                }
+                Declarator::Abstract => Ok((String::new(), ty, parameter_variables)),
+                // e.g. `(*fp)(int)`, the parameter names don't matter then
+                Declarator::Pointer(_) | Declarator::Const(_) => process_declarator(inner, ty),
+                Declarator::Array(..) => Err("Arrays of functions are invalid".to_string()),
+                Declarator::Function(..) => Err("Functions cannot return functions".to_string()),
+            }
+        }
+    }
+}
+
+/// Whether the name declared by `declarator` is itself `const`, given whether the type
+/// specifiers were, e.g. `x` in `const int x` and `p` in `int *const p`, but not `q` in
+/// `const int *q`.
+fn is_const_declarator(declarator: &Declarator, base_const: bool) -> bool {
+    match declarator {
+        Declarator::Identifier(_) | Declarator::Abstract => base_const,
+        Declarator::Pointer(inner) => is_const_declarator(inner, false),
+        Declarator::Const(inner) => is_const_declarator(inner, true),
+        // the elements of a const array are const
+        Declarator::Array(inner, _) => is_const_declarator(inner, base_const),
+        Declarator::Function(..) => false,
+    }
+}
+
+/// Parses a type name without an identifier, as used in casts and `sizeof`, e.g. `long *[3]`.
+fn parse_type(tokens: &mut Tokens) -> Result<Type, String> {
+    let mut ty = parse_type_specifiers(tokens)?;
+
+    while let Some(Token::Asterisk) = tokens.front() {
+        tokens.pop_front();
+        skip_type_qualifiers(tokens);
+        ty = Type::Pointer(Box::new(ty));
+    }
+
+    let mut sizes = Vec::new();
+    while let Some(Token::OpenBracket) = tokens.front() {
+        tokens.pop_front();
+        sizes.push(parse_array_size(tokens)?);
+    }
+
+    // the last dimension is the innermost one
+    for size in sizes.into_iter().rev() {
+        ty = Type::Array {
+            element: Box::new(ty),
+            size,
+        };
     }
+
+    Ok(ty)
 }
 
-fn parse_type(tokens: &mut VecDeque<Token>) -> Result<Type, String> {
+fn parse_type_specifiers(tokens: &mut Tokens) -> Result<Type, String> {
     let mut specifiers = Vec::new();
 
-    while let Some(&Token::IntKeyword | &Token::LongKeyword) = tokens.front() {
-        specifiers.push(tokens.pop_front().unwrap());
+    loop {
+        skip_type_qualifiers(tokens);
+        if !matches_type_specifier(tokens.front()) {
+            break;
+        }
+
+        if tokens.front() == Some(&Token::EnumKeyword) {
+            if parse_enum_specifier(tokens)?.enumerators.is_some() {
+                return Err("Enums can only be defined in declarations".to_string());
+            }
+            specifiers.push(Token::EnumKeyword);
+        } else {
+            specifiers.push(tokens.pop_front().unwrap());
+        }
     }
 
     parse_type_from_specifiers(&specifiers)
 }
 
 fn parse_type_from_specifiers(specifiers: &[Token]) -> Result<Type, String> {
-    match specifiers {
-        [Token::IntKeyword] => Ok(Type::Int),
-        [Token::IntKeyword, Token::LongKeyword]
-        | [Token::LongKeyword, Token::IntKeyword]
-        | [Token::LongKeyword] => Ok(Type::Long),
-        [] => Err("Expected type specifier".to_string()),
-        _ => Err("Invalid type specifier".to_string()),
+    if specifiers.is_empty() {
+        return Err("Expected type specifier".to_string());
+    }
+
+    // recognized so that they fail here, instead of as unknown identifiers further on
+    if specifiers.contains(&Token::StructKeyword) {
+        return Err("Structs are not supported yet".to_string());
+    }
+    if specifiers.contains(&Token::UnionKeyword) {
+        return Err("Unions are not supported yet".to_string());
+    }
+
+    let count = |token: Token| specifiers.iter().filter(|s| **s == token).count();
+    let ints = count(Token::IntKeyword);
+    let longs = count(Token::LongKeyword);
+    let unsigneds = count(Token::UnsignedKeyword);
+    let signeds = count(Token::SignedKeyword);
+    let chars = count(Token::CharKeyword);
+
+    if count(Token::BoolKeyword) > 0
+        || count(Token::DoubleKeyword) > 0
+        || count(Token::VoidKeyword) > 0
+        || count(Token::EnumKeyword) > 0
+    {
+        return match specifiers {
+            [Token::BoolKeyword] => Ok(Type::Bool),
+            [Token::DoubleKeyword] => Ok(Type::Double),
+            [Token::VoidKeyword] => Ok(Type::Void),
+            // enums are ints
+            [Token::EnumKeyword] => Ok(Type::Int),
+            _ => Err("Invalid type specifier".to_string()),
+        };
+    }
+
+    if unsigneds + signeds > 1 {
+        return Err("Invalid type specifier".to_string());
     }
+
+    // plain `char` is a distinct type from both `signed char` and `unsigned char`
+    if chars > 0 {
+        if chars > 1 || ints > 0 || longs > 0 {
+            return Err("Invalid type specifier".to_string());
+        }
+
+        return Ok(match (signeds, unsigneds) {
+            (0, 0) => Type::Char,
+            (_, 0) => Type::SChar,
+            _ => Type::UChar,
+        });
+    }
+
+    // `long long` is the same size as `long` here
+    if ints > 1 || longs > 2 {
+        return Err("Invalid type specifier".to_string());
+    }
+
+    Ok(match (longs, unsigneds) {
+        (0, 0) => Type::Int,
+        (_, 0) => Type::Long,
+        (0, _) => Type::UInt,
+        _ => Type::ULong,
+    })
 }
 
-fn parse_type_and_storage_class(
-    tokens: &mut VecDeque<Token>,
-) -> Result<(Type, Option<StorageClass>), String> {
+/// The type specifiers, qualifiers and storage class at the start of a declaration.
+struct DeclarationSpecifiers {
+    ty: Type,
+    storage_class: Option<StorageClass>,
+    /// The enum defined or referred to by its tag among the specifiers, if any.
+    enum_declaration: Option<EnumDeclaration>,
+    is_const: bool,
+}
+
+fn parse_declaration_specifiers(tokens: &mut Tokens) -> Result<DeclarationSpecifiers, String> {
     let mut type_tokens = Vec::new();
     let mut storage_classes = Vec::new();
+    let mut enum_declaration = None;
+    let mut is_const = false;
 
     loop {
         match tokens.front() {
-            Some(Token::IntKeyword | Token::LongKeyword) => {
+            Some(Token::EnumKeyword) => {
+                enum_declaration = Some(parse_enum_specifier(tokens)?);
+                type_tokens.push(Token::EnumKeyword);
+            }
+            token if matches_type_specifier(token) => {
                 type_tokens.push(tokens.pop_front().unwrap());
             }
+            token if matches_type_qualifier(token) => {
+                is_const |= tokens.pop_front() == Some(Token::ConstKeyword);
+            }
             Some(Token::StaticKeyword) => {
                 tokens.pop_front();
                 storage_classes.push(StorageClass::Static);
@@ -143,19 +539,121 @@ fn parse_type_and_storage_class(
         return Err("Expected at most one storage class".to_string());
     }
 
-    Ok((ty, storage_classes.pop()))
+    Ok(DeclarationSpecifiers {
+        ty,
+        storage_class: storage_classes.pop(),
+        enum_declaration,
+        is_const,
+    })
+}
+
+/// Parses `enum Tag`, `enum Tag { ... }` or `enum { ... }`.
+fn parse_enum_specifier(tokens: &mut Tokens) -> Result<EnumDeclaration, String> {
+    let Some(Token::EnumKeyword) = tokens.pop_front() else {
+        return Err("Expected enum keyword".to_string());
+    };
+
+    let tag = if let Some(Token::Identifier(tag)) = tokens.front() {
+        let tag = tag.clone();
+        tokens.pop_front();
+        Some(tag)
+    } else {
+        None
+    };
+
+    if tokens.front() != Some(&Token::OpenBrace) {
+        return match tag {
+            Some(_) => Ok(EnumDeclaration {
+                tag,
+                enumerators: None,
+            }),
+            None => Err("Expected enum tag or open brace".to_string()),
+        };
+    }
+
+    tokens.pop_front();
+
+    let mut enumerators = Vec::new();
+
+    loop {
+        let Some(Token::Identifier(name)) = tokens.pop_front() else {
+            return Err("Expected enumerator".to_string());
+        };
+
+        let value = if let Some(Token::Equal) = tokens.front() {
+            tokens.pop_front();
+            Some(parse_assignment_expression(tokens)?)
+        } else {
+            None
+        };
+
+        enumerators.push(Enumerator { name, value });
+
+        match tokens.pop_front() {
+            // a trailing comma is allowed
+            Some(Token::Comma) if tokens.front() == Some(&Token::CloseBrace) => {
+                tokens.pop_front();
+                break;
+            }
+            Some(Token::Comma) => {}
+            Some(Token::CloseBrace) => break,
+            _ => return Err("Expected comma or close brace".to_string()),
+        }
+    }
+
+    Ok(EnumDeclaration {
+        tag,
+        enumerators: Some(enumerators),
+    })
 }
 
 fn matches_type_specifier(tokens: Option<&Token>) -> bool {
-    matches!(tokens, Some(Token::IntKeyword | Token::LongKeyword))
+    matches!(
+        tokens,
+        Some(
+            Token::IntKeyword
+                | Token::LongKeyword
+                | Token::UnsignedKeyword
+                | Token::SignedKeyword
+                | Token::CharKeyword
+                | Token::DoubleKeyword
+                | Token::BoolKeyword
+                | Token::VoidKeyword
+                | Token::EnumKeyword
+                | Token::StructKeyword
+                | Token::UnionKeyword
+        )
+    )
+}
+
+/// Qualifiers are accepted anywhere C allows them, but only `const` on a variable itself has an
+/// effect yet.
+fn matches_type_qualifier(token: Option<&Token>) -> bool {
+    matches!(
+        token,
+        Some(Token::ConstKeyword | Token::VolatileKeyword | Token::RestrictKeyword)
+    )
+}
+
+/// Returns whether one of the qualifiers was `const`.
+fn skip_type_qualifiers(tokens: &mut Tokens) -> bool {
+    let mut is_const = false;
+    while matches_type_qualifier(tokens.front()) {
+        is_const |= tokens.pop_front() == Some(Token::ConstKeyword);
+    }
+    is_const
+}
+
+fn matches_start_of_type_name(token: Option<&Token>) -> bool {
+    matches_type_specifier(token) || matches_type_qualifier(token)
 }
 
 fn matches_start_of_declaration(token: Option<&Token>) -> bool {
     matches!(token, Some(Token::StaticKeyword | Token::ExternKeyword))
-        || matches_type_specifier(token)
+        || matches_start_of_type_name(token)
 }
 
-fn parse_parameters(tokens: &mut VecDeque<Token>) -> Result<Vec<(Variable, Type)>, String> {
+fn parse_parameters(tokens: &mut Tokens) -> Result<Vec<(Type, Declarator)>, String> {
     if let Some(Token::VoidKeyword) = tokens.front() {
         tokens.pop_front();
         return Ok(vec![]);
@@ -164,13 +662,22 @@ fn parse_parameters(tokens: &mut VecDeque<Token>) -> Result<Vec<(Variable, Type)
     let mut parameters = Vec::new();
 
     loop {
-        let ty = parse_type(tokens)?;
+        let DeclarationSpecifiers {
+            ty,
+            storage_class,
+            enum_declaration,
+            ..
+        } = parse_declaration_specifiers(tokens)?;
+        if storage_class.is_some() {
+            return Err("Function parameters cannot have a storage class".to_string());
+        }
+        if enum_declaration.is_some_and(|ed| ed.enumerators.is_some()) {
+            return Err("Enums can only be defined in declarations".to_string());
+        }
 
-        let Some(Token::Identifier(identifier)) = tokens.pop_front() else {
-            return Err("Expected identifier".to_string());
-        };
+        let declarator = parse_declarator(tokens, true)?;
 
-        parameters.push((Variable { identifier }, ty));
+        parameters.push((ty, declarator));
 
         if let Some(Token::Comma) = tokens.front() {
             tokens.pop_front();
@@ -182,37 +689,47 @@ fn parse_parameters(tokens: &mut VecDeque<Token>) -> Result<Vec<(Variable, Type)
     Ok(parameters)
 }
 
-fn parse_block(tokens: &mut VecDeque<Token>) -> Result<Block, String> {
+fn parse_block(tokens: &mut Tokens) -> Result<Block, String> {
     let Some(Token::OpenBrace) = tokens.pop_front() else {
         return Err("Expected open brace".to_string());
     };
 
     let mut items = vec![];
+    let mut lines = vec![];
 
     while let Some(t) = tokens.front() {
         if t == &Token::CloseBrace {
             break;
         }
 
-        items.push(parse_block_item(tokens)?);
+        let line = tokens.line();
+        for item in parse_block_item(tokens)? {
+            lines.push(line);
+            items.push(item);
+        }
     }
 
     let Some(Token::CloseBrace) = tokens.pop_front() else {
         return Err("Expected close brace".to_string());
     };
 
-    Ok(Block { items })
+    Ok(Block { items, lines })
 }
 
-fn parse_block_item(tokens: &mut VecDeque<Token>) -> Result<BlockItem, String> {
+/// A declaration of several names becomes one item per name.
+fn parse_block_item(tokens: &mut Tokens) -> Result<Vec<BlockItem>, String> {
     if matches_start_of_declaration(tokens.front()) {
-        parse_declaration(tokens).map(BlockItem::Declaration)
+        let declarations = parse_declaration(tokens)?;
+        Ok(declarations
+            .into_iter()
+            .map(BlockItem::Declaration)
+            .collect())
     } else {
-        parse_statement(tokens).map(BlockItem::Statement)
+        Ok(vec![BlockItem::Statement(parse_statement(tokens)?)])
     }
 }
 
-fn parse_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, String> {
+fn parse_statement(tokens: &mut Tokens) -> Result<Statement, String> {
     match tokens.front() {
         Some(Token::Semicolon) => parse_null_statement(tokens),
         Some(Token::ReturnKeyword) => parse_return_statement(tokens),
@@ -238,7 +755,7 @@ fn parse_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, String> {
     }
 }
 
-fn parse_null_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, String> {
+fn parse_null_statement(tokens: &mut Tokens) -> Result<Statement, String> {
     let Some(Token::Semicolon) = tokens.pop_front() else {
         return Err("Expected semicolon".to_string());
     };
@@ -246,12 +763,16 @@ fn parse_null_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, Strin
     Ok(Statement::Null)
 }
 
-fn parse_return_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, String> {
+fn parse_return_statement(tokens: &mut Tokens) -> Result<Statement, String> {
     let Some(Token::ReturnKeyword) = tokens.pop_front() else {
         return Err("Expected return keyword".to_string());
     };
 
-    let expression = parse_expression(tokens, 0)?;
+    let expression = if let Some(Token::Semicolon) = tokens.front() {
+        None
+    } else {
+        Some(parse_expression(tokens, 0)?)
+    };
 
     let Some(Token::Semicolon) = tokens.pop_front() else {
         return Err("Expected semicolon".to_string());
@@ -260,7 +781,7 @@ fn parse_return_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, Str
     Ok(Statement::Return(expression))
 }
 
-fn parse_if_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, String> {
+fn parse_if_statement(tokens: &mut Tokens) -> Result<Statement, String> {
     let Some(Token::IfKeyword) = tokens.pop_front() else {
         return Err("Expected if keyword".to_string());
     };
@@ -291,11 +812,11 @@ fn parse_if_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, String>
     })
 }
 
-fn parse_block_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, String> {
+fn parse_block_statement(tokens: &mut Tokens) -> Result<Statement, String> {
     Ok(Statement::Compound(parse_block(tokens)?))
 }
 
-fn parse_goto_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, String> {
+fn parse_goto_statement(tokens: &mut Tokens) -> Result<Statement, String> {
     let Some(Token::GotoKeyword) = tokens.pop_front() else {
         return Err("Expected goto keyword".to_string());
     };
@@ -311,7 +832,7 @@ fn parse_goto_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, Strin
     Ok(Statement::Goto(Label { identifier: label }))
 }
 
-fn parse_break_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, String> {
+fn parse_break_statement(tokens: &mut Tokens) -> Result<Statement, String> {
     let Some(Token::BreakKeyword) = tokens.pop_front() else {
         return Err("Expected break keyword".to_string());
     };
@@ -323,7 +844,7 @@ fn parse_break_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, Stri
     Ok(Statement::Break(None))
 }
 
-fn parse_continue_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, String> {
+fn parse_continue_statement(tokens: &mut Tokens) -> Result<Statement, String> {
     let Some(Token::ContinueKeyword) = tokens.pop_front() else {
         return Err("Expected continue keyword".to_string());
     };
@@ -335,7 +856,7 @@ fn parse_continue_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, S
     Ok(Statement::Continue(None))
 }
 
-fn parse_while_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, String> {
+fn parse_while_statement(tokens: &mut Tokens) -> Result<Statement, String> {
     let Some(Token::WhileKeyword) = tokens.pop_front() else {
         return Err("Expected while keyword".to_string());
     };
@@ -359,7 +880,7 @@ fn parse_while_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, Stri
     })
 }
 
-fn parse_do_while_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, String> {
+fn parse_do_while_statement(tokens: &mut Tokens) -> Result<Statement, String> {
     let Some(Token::DoKeyword) = tokens.pop_front() else {
         return Err("Expected do keyword".to_string());
     };
@@ -391,7 +912,7 @@ fn parse_do_while_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, S
     })
 }
 
-fn parse_for_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, String> {
+fn parse_for_statement(tokens: &mut Tokens) -> Result<Statement, String> {
     let Some(Token::ForKeyword) = tokens.pop_front() else {
         return Err("Expected for keyword".to_string());
     };
@@ -433,28 +954,47 @@ fn parse_for_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, String
     })
 }
 
-fn parse_for_initializer(tokens: &mut VecDeque<Token>) -> Result<Option<ForInitializer>, String> {
+fn parse_for_initializer(tokens: &mut Tokens) -> Result<Option<ForInitializer>, String> {
     if let Some(Token::Semicolon) = tokens.front() {
         tokens.pop_front();
         return Ok(None);
     }
 
     if matches_start_of_declaration(tokens.front()) {
-        let declaration = parse_declaration(tokens)?;
+        let mut variable_declarations = Vec::new();
+
+        for declaration in parse_declaration(tokens)? {
+            let vd = match declaration {
+                Declaration::Variable(vd) => vd,
+                // the type of the variables can name an enum, but not define one
+                Declaration::Enum(EnumDeclaration {
+                    enumerators: None, ..
+                }) => continue,
+                _ => return Err("Expected variable declaration".to_string()),
+            };
+
+            if vd.storage_class.is_some() {
+                return Err("For loop variable declaration cannot have a storage class".to_string());
+            }
 
-        let Declaration::Variable(vd) = declaration else {
-            return Err("Expected variable declaration".to_string());
-        };
+            variable_declarations.push(vd);
+        }
 
-        Ok(Some(ForInitializer::VariableDeclaration(vd)))
+        Ok(Some(ForInitializer::VariableDeclarations(
+            variable_declarations,
+        )))
     } else {
         let expression = parse_expression(tokens, 0)?;
-        tokens.pop_front();
+
+        let Some(Token::Semicolon) = tokens.pop_front() else {
+            return Err("Expected semicolon".to_string());
+        };
+
         Ok(Some(ForInitializer::Expression(expression)))
     }
 }
 
-fn parse_switch_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, String> {
+fn parse_switch_statement(tokens: &mut Tokens) -> Result<Statement, String> {
     let Some(Token::SwitchKeyword) = tokens.pop_front() else {
         return Err("Expected switch keyword".to_string());
     };
@@ -479,7 +1019,7 @@ fn parse_switch_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, Str
     })
 }
 
-fn parse_case_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, String> {
+fn parse_case_statement(tokens: &mut Tokens) -> Result<Statement, String> {
     let Some(Token::CaseKeyword) = tokens.pop_front() else {
         return Err("Expected case keyword".to_string());
     };
@@ -490,7 +1030,7 @@ fn parse_case_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, Strin
         return Err("Expected colon".to_string());
     };
 
-    let body = Box::new(parse_statement(tokens)?);
+    let body = Box::new(parse_label_body(tokens)?);
 
     Ok(Statement::Case {
         expression,
@@ -499,7 +1039,7 @@ fn parse_case_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, Strin
     })
 }
 
-fn parse_default_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, String> {
+fn parse_default_statement(tokens: &mut Tokens) -> Result<Statement, String> {
     let Some(Token::DefaultKeyword) = tokens.pop_front() else {
         return Err("Expected default keyword".to_string());
     };
@@ -508,12 +1048,12 @@ fn parse_default_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, St
         return Err("Expected colon".to_string());
     };
 
-    let body = Box::new(parse_statement(tokens)?);
+    let body = Box::new(parse_label_body(tokens)?);
 
     Ok(Statement::Default { body, label: None })
 }
 
-fn parse_labeled_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, String> {
+fn parse_labeled_statement(tokens: &mut Tokens) -> Result<Statement, String> {
     let Some(Token::Identifier(label)) = tokens.pop_front() else {
         return Err("Expected identifier".to_string());
     };
@@ -522,7 +1062,7 @@ fn parse_labeled_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, St
         return Err("Expected colon".to_string());
     };
 
-    let statement = parse_statement(tokens)?;
+    let statement = parse_label_body(tokens)?;
 
     Ok(Statement::Labeled(
         Label { identifier: label },
@@ -530,7 +1070,17 @@ fn parse_labeled_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, St
     ))
 }
 
-fn parse_expression_statement(tokens: &mut VecDeque<Token>) -> Result<Statement, String> {
+/// The statement after a label. Since C23 a label can also come right before a declaration or the
+/// end of a block, and then labels an empty statement.
+fn parse_label_body(tokens: &mut Tokens) -> Result<Statement, String> {
+    if tokens.front() == Some(&Token::CloseBrace) || matches_start_of_declaration(tokens.front()) {
+        return Ok(Statement::Null);
+    }
+
+    parse_statement(tokens)
+}
+
+fn parse_expression_statement(tokens: &mut Tokens) -> Result<Statement, String> {
     let expression = parse_expression(tokens, 0)?;
 
     let Some(Token::Semicolon) = tokens.pop_front() else {
@@ -540,13 +1090,28 @@ fn parse_expression_statement(tokens: &mut VecDeque<Token>) -> Result<Statement,
     Ok(Statement::Expression(expression))
 }
 
-fn parse_expression(
-    tokens: &mut VecDeque<Token>,
-    min_precedence: u8,
-) -> Result<Expression, String> {
+/// Parses an expression that can't contain a top-level comma, e.g. a function argument.
+fn parse_assignment_expression(tokens: &mut Tokens) -> Result<Expression, String> {
+    parse_expression(tokens, 1)
+}
+
+/// Parses the digits of an integer constant, which the lexer has already converted to decimal.
+fn parse_integer<T: std::str::FromStr<Err = std::num::ParseIntError>>(
+    value: &str,
+) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|e: std::num::ParseIntError| match e.kind() {
+            std::num::IntErrorKind::PosOverflow => "Integer constant is too large".to_string(),
+            _ => "Invalid integer".to_string(),
+        })
+}
+
+fn parse_expression(tokens: &mut Tokens, min_precedence: u8) -> Result<Expression, String> {
     let mut left = parse_factor(tokens)?;
     while let Some(t) = tokens.front() {
         let precedence = match t {
+            Token::Comma => 0,
             Token::Equal
             | Token::PlusEqual
             | Token::MinusEqual
@@ -577,6 +1142,15 @@ fn parse_expression(
         }
 
         match t {
+            Token::Comma => {
+                tokens.pop_front();
+                let right = parse_expression(tokens, precedence + 1)?;
+                left = Expression::Comma {
+                    lhs: Box::new(left),
+                    rhs: Box::new(right),
+                    ty: None,
+                };
+            }
             Token::Equal
             | Token::PlusEqual
             | Token::MinusEqual
@@ -602,12 +1176,14 @@ fn parse_expression(
                     return Err("Expected question mark".to_string());
                 };
 
+                // `?` and `:` bracket the middle operand like parentheses
                 let then_expr = parse_expression(tokens, 0)?;
 
                 let Some(Token::Colon) = tokens.pop_front() else {
                     return Err("Expected colon".to_string());
                 };
 
+                // at the same precedence, so `a ? b : c ? d : e` nests to the right
                 let else_expr = parse_expression(tokens, precedence)?;
 
                 left = Expression::Conditional {
@@ -632,12 +1208,12 @@ fn parse_expression(
     Ok(left)
 }
 
-fn parse_factor(tokens: &mut VecDeque<Token>) -> Result<Expression, String> {
+fn parse_factor(tokens: &mut Tokens) -> Result<Expression, String> {
     let mut factor = match tokens.front().cloned() {
         Some(Token::OpenParen) => {
             tokens.pop_front();
 
-            if matches_type_specifier(tokens.front()) {
+            if matches_start_of_type_name(tokens.front()) {
                 let target_ty = parse_type(tokens)?;
 
                 let Some(Token::CloseParen) = tokens.pop_front() else {
@@ -662,7 +1238,7 @@ fn parse_factor(tokens: &mut VecDeque<Token>) -> Result<Expression, String> {
         Some(Token::ConstantInt(value)) => {
             tokens.pop_front();
 
-            let value_i64: i64 = value.parse().map_err(|_| "Invalid integer".to_string())?;
+            let value_i64: i64 = parse_integer(&value)?;
 
             if let Ok(value_i32) = value_i64.try_into() {
                 Expression::Constant {
@@ -679,77 +1255,211 @@ fn parse_factor(tokens: &mut VecDeque<Token>) -> Result<Expression, String> {
         Some(Token::ConstantLong(value)) => {
             tokens.pop_front();
 
-            let value_i64: i64 = value.parse().map_err(|_| "Invalid integer".to_string())?;
+            let value_i64: i64 = parse_integer(&value)?;
 
             Expression::Constant {
                 c: Constant::ConstantLong(value_i64),
                 ty: None,
             }
         }
-        Some(Token::Identifier(identifier)) => {
+        Some(Token::ConstantUInt(value)) => {
             tokens.pop_front();
 
-            if let Some(Token::OpenParen) = tokens.front() {
-                tokens.pop_front();
-
-                let mut arguments = vec![];
-
-                if tokens.front() != Some(&Token::CloseParen) {
-                    loop {
-                        arguments.push(parse_expression(tokens, 0)?);
-
-                        if let Some(Token::Comma) = tokens.front() {
-                            tokens.pop_front();
-                        } else {
-                            break;
-                        }
-                    }
-                }
-
-                let Some(Token::CloseParen) = tokens.pop_front() else {
-                    return Err("Expected close parenthesis".to_string());
-                };
+            let value_u64: u64 = parse_integer(&value)?;
 
-                Expression::FunctionCall {
-                    function: Function { identifier },
-                    arguments,
+            if let Ok(value_u32) = value_u64.try_into() {
+                Expression::Constant {
+                    c: Constant::ConstantUInt(value_u32),
                     ty: None,
                 }
             } else {
-                Expression::Variable {
-                    v: Variable { identifier },
+                Expression::Constant {
+                    c: Constant::ConstantULong(value_u64),
                     ty: None,
                 }
             }
         }
-        Some(
-            Token::Tilde | Token::Minus | Token::Exclamation | Token::PlusPlus | Token::MinusMinus,
-        ) => {
-            let op = parse_unary_prefix_operator(tokens)?;
-            let inner = parse_factor(tokens)?;
-            Expression::Unary {
-                op,
-                expr: Box::new(inner),
+        Some(Token::ConstantULong(value)) => {
+            tokens.pop_front();
+
+            let value_u64: u64 = parse_integer(&value)?;
+
+            Expression::Constant {
+                c: Constant::ConstantULong(value_u64),
+                ty: None,
+            }
+        }
+        Some(Token::ConstantDouble(value)) => {
+            tokens.pop_front();
+
+            let value_f64: f64 = value
+                .parse()
+                .map_err(|_| "Invalid floating constant".to_string())?;
+
+            Expression::Constant {
+                c: Constant::ConstantDouble(value_f64),
+                ty: None,
+            }
+        }
+        Some(Token::ConstantChar(c)) => {
+            tokens.pop_front();
+
+            Expression::Constant {
+                c: Constant::ConstantInt(c as i32),
+                ty: None,
+            }
+        }
+        Some(Token::Identifier(identifier)) => {
+            tokens.pop_front();
+
+            if let Some(Token::OpenParen) = tokens.front() {
+                Expression::FunctionCall {
+                    function: Function { identifier },
+                    arguments: parse_arguments(tokens)?,
+                    ty: None,
+                }
+            } else {
+                Expression::Variable {
+                    v: Variable { identifier },
+                    ty: None,
+                }
+            }
+        }
+        Some(Token::SizeofKeyword) => {
+            tokens.pop_front();
+
+            if tokens.front() == Some(&Token::OpenParen)
+                && matches_start_of_type_name(tokens.get(1))
+            {
+                tokens.pop_front();
+                let target_ty = parse_type(tokens)?;
+
+                let Some(Token::CloseParen) = tokens.pop_front() else {
+                    return Err("Expected close parenthesis".to_string());
+                };
+
+                Expression::SizeOfType {
+                    target_ty,
+                    ty: None,
+                }
+            } else {
+                let inner = parse_factor(tokens)?;
+
+                Expression::SizeOf {
+                    expr: Box::new(inner),
+                    ty: None,
+                }
+            }
+        }
+        Some(Token::Asterisk) => {
+            tokens.pop_front();
+            let inner = parse_factor(tokens)?;
+
+            Expression::Dereference {
+                expr: Box::new(inner),
+                ty: None,
+            }
+        }
+        Some(Token::Ampersand) => {
+            tokens.pop_front();
+            let inner = parse_factor(tokens)?;
+
+            Expression::AddrOf {
+                expr: Box::new(inner),
+                ty: None,
+            }
+        }
+        Some(
+            Token::Plus
+            | Token::Tilde
+            | Token::Minus
+            | Token::Exclamation
+            | Token::PlusPlus
+            | Token::MinusMinus,
+        ) => {
+            let op = parse_unary_prefix_operator(tokens)?;
+            let inner = parse_factor(tokens)?;
+            Expression::Unary {
+                op,
+                expr: Box::new(inner),
                 ty: None,
             }
         }
         _ => return Err("Expected factor".to_string()),
     };
 
-    while let Some(Token::PlusPlus | Token::MinusMinus) = tokens.front() {
-        let op = parse_unary_postfix_operator(tokens)?;
-        factor = Expression::Unary {
-            op,
-            expr: Box::new(factor),
-            ty: None,
+    loop {
+        factor = match tokens.front() {
+            Some(Token::PlusPlus | Token::MinusMinus) => Expression::Unary {
+                op: parse_unary_postfix_operator(tokens)?,
+                expr: Box::new(factor),
+                ty: None,
+            },
+            Some(Token::OpenBracket) => {
+                tokens.pop_front();
+                let index = parse_expression(tokens, 0)?;
+
+                let Some(Token::CloseBracket) = tokens.pop_front() else {
+                    return Err("Expected close bracket".to_string());
+                };
+
+                Expression::Subscript {
+                    expr: Box::new(factor),
+                    index: Box::new(index),
+                    ty: None,
+                }
+            }
+            // there are no structs or unions to have members yet
+            Some(Token::Dot | Token::Arrow) => {
+                return Err("Member access is not supported yet".to_string());
+            }
+            // anything else that is called has to be a function pointer
+            Some(Token::OpenParen) => Expression::IndirectCall {
+                arguments: parse_arguments(tokens)?,
+                callee: Box::new(factor),
+                ty: None,
+            },
+            _ => break,
         };
     }
 
     Ok(factor)
 }
 
-fn parse_unary_prefix_operator(tokens: &mut VecDeque<Token>) -> Result<UnaryOperator, String> {
+/// Parses the parenthesized arguments of a function call.
+fn parse_arguments(tokens: &mut Tokens) -> Result<Vec<Expression>, String> {
+    let Some(Token::OpenParen) = tokens.pop_front() else {
+        return Err("Expected open parenthesis".to_string());
+    };
+
+    let mut arguments = vec![];
+
+    if tokens.front() != Some(&Token::CloseParen) {
+        loop {
+            arguments.push(parse_assignment_expression(tokens)?);
+
+            if let Some(Token::Comma) = tokens.front() {
+                tokens.pop_front();
+            } else {
+                break;
+            }
+
+            if tokens.lenient && tokens.front() == Some(&Token::CloseParen) {
+                break;
+            }
+        }
+    }
+
+    let Some(Token::CloseParen) = tokens.pop_front() else {
+        return Err("Expected close parenthesis".to_string());
+    };
+
+    Ok(arguments)
+}
+
+fn parse_unary_prefix_operator(tokens: &mut Tokens) -> Result<UnaryOperator, String> {
     match tokens.pop_front() {
+        Some(Token::Plus) => Ok(UnaryOperator::Plus),
         Some(Token::Tilde) => Ok(UnaryOperator::Complement),
         Some(Token::Minus) => Ok(UnaryOperator::Negate),
         Some(Token::Exclamation) => Ok(UnaryOperator::Not),
@@ -759,7 +1469,7 @@ fn parse_unary_prefix_operator(tokens: &mut VecDeque<Token>) -> Result<UnaryOper
     }
 }
 
-fn parse_unary_postfix_operator(tokens: &mut VecDeque<Token>) -> Result<UnaryOperator, String> {
+fn parse_unary_postfix_operator(tokens: &mut Tokens) -> Result<UnaryOperator, String> {
     match tokens.pop_front() {
         Some(Token::PlusPlus) => Ok(UnaryOperator::PostfixIncrement),
         Some(Token::MinusMinus) => Ok(UnaryOperator::PostfixDecrement),
@@ -767,7 +1477,7 @@ fn parse_unary_postfix_operator(tokens: &mut VecDeque<Token>) -> Result<UnaryOpe
     }
 }
 
-fn parse_binary_operator(tokens: &mut VecDeque<Token>) -> Result<BinaryOperator, String> {
+fn parse_binary_operator(tokens: &mut Tokens) -> Result<BinaryOperator, String> {
     match tokens.pop_front() {
         Some(Token::Plus) => Ok(BinaryOperator::Add),
         Some(Token::Minus) => Ok(BinaryOperator::Subtract),
@@ -791,7 +1501,7 @@ fn parse_binary_operator(tokens: &mut VecDeque<Token>) -> Result<BinaryOperator,
     }
 }
 
-fn parse_assignment_operator(tokens: &mut VecDeque<Token>) -> Result<AssignmentOperator, String> {
+fn parse_assignment_operator(tokens: &mut Tokens) -> Result<AssignmentOperator, String> {
     match tokens.pop_front() {
         Some(Token::Equal) => Ok(AssignmentOperator::Assign),
         Some(Token::PlusEqual) => Ok(AssignmentOperator::AddAssign),
@@ -834,12 +1544,13 @@ mod tests {
                 },
                 parameters: vec![],
                 body: Some(Block {
-                    items: vec![BlockItem::Statement(Statement::Return(
+                    items: vec![BlockItem::Statement(Statement::Return(Some(
                         Expression::Constant {
                             c: Constant::ConstantInt(42),
                             ty: None,
                         },
-                    ))],
+                    )))],
+                    lines: vec![None],
                 }),
                 ty: Type::Function {
                     return_type: Box::new(Type::Int),
@@ -849,7 +1560,7 @@ mod tests {
             })],
         };
 
-        assert_eq!(parse(&tokens), Ok(expected));
+        assert_eq!(parse(&tokens, false), Ok(expected));
     }
 
     #[test]
@@ -866,6 +1577,987 @@ mod tests {
             Token::CloseBrace,
         ];
 
-        assert!(parse(&tokens).is_err());
+        assert!(parse(&tokens, false).is_err());
+    }
+
+    #[test]
+    fn test_parse_storage_class_errors() {
+        use crate::compiler::lexer::tokenize;
+
+        let test_cases = [
+            (
+                "int f(static int x);",
+                "Function parameters cannot have a storage class",
+            ),
+            (
+                "int f(int a, extern long b);",
+                "Function parameters cannot have a storage class",
+            ),
+            (
+                "int main(void) { for (static int i = 0; i < 3; i++) ; }",
+                "For loop variable declaration cannot have a storage class",
+            ),
+            (
+                "int main(void) { for (extern int i; ; ) ; }",
+                "For loop variable declaration cannot have a storage class",
+            ),
+        ];
+
+        for (source, message) in test_cases {
+            let error = parse(&tokenize(source).unwrap(), false).unwrap_err();
+            assert_eq!(error.message(), message, "{source}");
+        }
+    }
+
+    #[test]
+    fn test_parse_function_pointers() {
+        use crate::compiler::lexer::tokenize;
+
+        let test_cases = [
+            ("int (*fp)(int);", "fp", "int(int) *"),
+            (
+                "int (*table[3])(int, long);",
+                "table",
+                "int(int, long) *[3]",
+            ),
+            ("int (*pick(int i))(long);", "pick", "int(long) *(int)"),
+            (
+                "int apply(int (*)(int), int *);",
+                "apply",
+                "int(int(int) *, int *)",
+            ),
+        ];
+
+        for (source, name, ty) in test_cases {
+            let mut tokens = Tokens::from(tokenize(source).unwrap());
+            let [declaration] = &parse_declaration(&mut tokens).unwrap()[..] else {
+                panic!("expected a single declaration: {source}");
+            };
+            let (identifier, declared_ty) = match declaration.clone() {
+                Declaration::Variable(vd) => (vd.variable.identifier, vd.ty),
+                Declaration::Function(fd) => (fd.function.identifier, fd.ty),
+                Declaration::Enum(_) => panic!("expected a variable or function: {source}"),
+            };
+
+            assert_eq!(identifier, name, "{source}");
+            assert_eq!(declared_ty.to_string(), ty, "{source}");
+        }
+
+        let mut tokens = Tokens::from(tokenize("(*fp)(1)").unwrap());
+        let Ok(Expression::IndirectCall {
+            callee, arguments, ..
+        }) = parse_expression(&mut tokens, 0)
+        else {
+            panic!("expected an indirect call");
+        };
+        assert!(matches!(*callee, Expression::Dereference { .. }));
+        assert_eq!(arguments.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_conditional_and_assignment_associativity() {
+        use crate::compiler::lexer::tokenize;
+
+        let expression = |source: &str| {
+            let mut tokens = Tokens::from(tokenize(source).unwrap());
+            let expression = parse_expression(&mut tokens, 0).unwrap();
+            assert!(tokens.is_empty(), "{source}");
+            expression
+        };
+
+        let test_cases = [
+            ("a ? b : c ? d : e", "a ? b : (c ? d : e)"),
+            ("a ? b ? c : d : e", "a ? (b ? c : d) : e"),
+            ("a ? b = c : d", "a ? (b = c) : d"),
+            ("a = b ? c : d", "a = (b ? c : d)"),
+            ("a = b = c", "a = (b = c)"),
+            ("x += a ? b : c ? d : e", "x += (a ? b : (c ? d : e))"),
+            ("a || b ? c : d", "(a || b) ? c : d"),
+            ("a ? b : c, d", "(a ? b : c), d"),
+        ];
+
+        for (source, parenthesized) in test_cases {
+            assert_eq!(expression(source), expression(parenthesized), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_parse_for_initializer_errors() {
+        use crate::compiler::lexer::tokenize;
+
+        let test_cases = [
+            ("int main(void) { for (1 + ; ; ) ; }", "Expected factor"),
+            (
+                "int main(void) { int i; for (i = 0 i < 3; i++) ; }",
+                "Expected semicolon",
+            ),
+            (
+                "int main(void) { for (enum E { A } e = A; ; ) ; }",
+                "Expected variable declaration",
+            ),
+        ];
+
+        for (source, message) in test_cases {
+            let error = parse(&tokenize(source).unwrap(), false).unwrap_err();
+            assert_eq!(error.message(), message, "{source}");
+        }
+
+        // naming an enum is fine
+        let source = "enum E { A }; int main(void) { for (enum E e = A; ; ) ; }";
+        assert!(parse(&tokenize(source).unwrap(), false).is_ok());
+    }
+
+    #[test]
+    fn test_parse_integer_constant_errors() {
+        let test_cases = [
+            (
+                Token::ConstantInt("99999999999999999999".to_string()),
+                "Integer constant is too large",
+            ),
+            (
+                Token::ConstantLong("9223372036854775808".to_string()),
+                "Integer constant is too large",
+            ),
+            (
+                Token::ConstantULong("18446744073709551616".to_string()),
+                "Integer constant is too large",
+            ),
+            (Token::ConstantInt("12a".to_string()), "Invalid integer"),
+            (Token::ConstantUInt("".to_string()), "Invalid integer"),
+        ];
+
+        for (token, message) in test_cases {
+            let mut tokens = Tokens::from(vec![token.clone()]);
+            assert_eq!(
+                parse_factor(&mut tokens),
+                Err(message.to_string()),
+                "{token:?}"
+            );
+        }
+
+        let mut tokens = Tokens::from(vec![Token::ConstantUInt(
+            "18446744073709551615".to_string(),
+        )]);
+        assert_eq!(
+            parse_factor(&mut tokens),
+            Ok(Expression::Constant {
+                c: Constant::ConstantULong(u64::MAX),
+                ty: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_char_constant() {
+        let mut tokens = Tokens::from(vec![Token::ConstantChar('A')]);
+
+        assert_eq!(
+            parse_expression(&mut tokens, 0),
+            Ok(Expression::Constant {
+                c: Constant::ConstantInt(65),
+                ty: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_comma() {
+        let variable = |identifier: &str| Expression::Variable {
+            v: Variable {
+                identifier: identifier.to_string(),
+            },
+            ty: None,
+        };
+
+        // a, f(b, c)
+        let mut tokens = Tokens::from(vec![
+            Token::Identifier("a".to_string()),
+            Token::Comma,
+            Token::Identifier("f".to_string()),
+            Token::OpenParen,
+            Token::Identifier("b".to_string()),
+            Token::Comma,
+            Token::Identifier("c".to_string()),
+            Token::CloseParen,
+        ]);
+
+        assert_eq!(
+            parse_expression(&mut tokens, 0),
+            Ok(Expression::Comma {
+                lhs: Box::new(variable("a")),
+                rhs: Box::new(Expression::FunctionCall {
+                    function: Function {
+                        identifier: "f".to_string(),
+                    },
+                    arguments: vec![variable("b"), variable("c")],
+                    ty: None,
+                }),
+                ty: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_sizeof() {
+        let mut tokens = Tokens::from(vec![
+            Token::SizeofKeyword,
+            Token::OpenParen,
+            Token::LongKeyword,
+            Token::CloseParen,
+        ]);
+
+        assert_eq!(
+            parse_expression(&mut tokens, 0),
+            Ok(Expression::SizeOfType {
+                target_ty: Type::Long,
+                ty: None,
+            })
+        );
+
+        // sizeof (x) + 1
+        let mut tokens = Tokens::from(vec![
+            Token::SizeofKeyword,
+            Token::OpenParen,
+            Token::Identifier("x".to_string()),
+            Token::CloseParen,
+            Token::Plus,
+            Token::ConstantInt("1".to_string()),
+        ]);
+
+        assert_eq!(
+            parse_expression(&mut tokens, 0),
+            Ok(Expression::Binary {
+                op: BinaryOperator::Add,
+                lhs: Box::new(Expression::SizeOf {
+                    expr: Box::new(Expression::Variable {
+                        v: Variable {
+                            identifier: "x".to_string(),
+                        },
+                        ty: None,
+                    }),
+                    ty: None,
+                }),
+                rhs: Box::new(Expression::Constant {
+                    c: Constant::ConstantInt(1),
+                    ty: None,
+                }),
+                ty: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_unary_plus() {
+        let mut tokens = Tokens::from(vec![
+            Token::Plus,
+            Token::Plus,
+            Token::ConstantInt("42".to_string()),
+        ]);
+
+        assert_eq!(
+            parse_expression(&mut tokens, 0),
+            Ok(Expression::Unary {
+                op: UnaryOperator::Plus,
+                expr: Box::new(Expression::Unary {
+                    op: UnaryOperator::Plus,
+                    expr: Box::new(Expression::Constant {
+                        c: Constant::ConstantInt(42),
+                        ty: None,
+                    }),
+                    ty: None,
+                }),
+                ty: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_labels_before_declarations_and_block_end() {
+        let tokens = crate::compiler::lexer::tokenize(
+            "{ start: int x = 1; switch (x) { default: } goto start; end: }",
+        )
+        .unwrap();
+        let block = parse_block(&mut Tokens::from(tokens)).unwrap();
+
+        let label = |identifier: &str| Label {
+            identifier: identifier.to_string(),
+        };
+        assert_eq!(
+            block.items[0],
+            BlockItem::Statement(Statement::Labeled(
+                label("start"),
+                Box::new(Statement::Null)
+            ))
+        );
+        assert!(matches!(block.items[1], BlockItem::Declaration(_)));
+        assert!(matches!(
+            &block.items[2],
+            BlockItem::Statement(Statement::Switch { body, .. })
+                if **body == Statement::Compound(Block {
+                    items: vec![BlockItem::Statement(Statement::Default {
+                        body: Box::new(Statement::Null),
+                        label: None,
+                    })],
+                    lines: vec![None],
+                })
+        ));
+        assert_eq!(
+            block.items[4],
+            BlockItem::Statement(Statement::Labeled(label("end"), Box::new(Statement::Null)))
+        );
+    }
+
+    #[test]
+    fn test_parse_multiple_declarators() {
+        use crate::compiler::lexer::tokenize;
+
+        let variable = |identifier: &str, ty: Type, initializer: Option<i32>| {
+            Declaration::Variable(VariableDeclaration {
+                variable: Variable {
+                    identifier: identifier.to_string(),
+                },
+                initializer: initializer.map(|n| {
+                    Initializer::Single(Expression::Constant {
+                        c: Constant::ConstantInt(n),
+                        ty: None,
+                    })
+                }),
+                ty,
+                storage_class: None,
+                is_const: false,
+            })
+        };
+
+        let mut tokens = Tokens::from(tokenize("int a, b, c = 3;").unwrap());
+        assert_eq!(
+            parse_declaration(&mut tokens),
+            Ok(vec![
+                variable("a", Type::Int, None),
+                variable("b", Type::Int, None),
+                variable("c", Type::Int, Some(3)),
+            ])
+        );
+
+        let mut tokens = Tokens::from(tokenize("long x = 1, *p, f(void);").unwrap());
+        let declarations = parse_declaration(&mut tokens).unwrap();
+        assert_eq!(declarations[0], variable("x", Type::Long, Some(1)));
+        assert_eq!(
+            declarations[1],
+            variable("p", Type::Pointer(Box::new(Type::Long)), None)
+        );
+        assert!(matches!(
+            &declarations[2],
+            Declaration::Function(fd) if fd.function.identifier == "f" && fd.body.is_none()
+        ));
+
+        let test_cases = [
+            (
+                "int x, f(void) { return 0; }",
+                "Function definitions cannot share a declaration",
+            ),
+            ("int a, ;", "Expected identifier"),
+            ("int a b;", "Expected semicolon"),
+        ];
+
+        for (source, error) in test_cases {
+            let mut tokens = Tokens::from(tokenize(source).unwrap());
+            assert_eq!(
+                parse_declaration(&mut tokens),
+                Err(error.to_string()),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_enums() {
+        use crate::compiler::lexer::tokenize;
+
+        let constant = |n: i32| Expression::Constant {
+            c: Constant::ConstantInt(n),
+            ty: None,
+        };
+        let enumerator = |name: &str, value: Option<i32>| Enumerator {
+            name: name.to_string(),
+            value: value.map(constant),
+        };
+
+        let mut tokens = Tokens::from(tokenize("enum Color { RED, GREEN = 5, BLUE, };").unwrap());
+        assert_eq!(
+            parse_declaration(&mut tokens),
+            Ok(vec![Declaration::Enum(EnumDeclaration {
+                tag: Some("Color".to_string()),
+                enumerators: Some(vec![
+                    enumerator("RED", None),
+                    enumerator("GREEN", Some(5)),
+                    enumerator("BLUE", None),
+                ]),
+            })])
+        );
+
+        let mut tokens = Tokens::from(tokenize("static enum { A } a = A;").unwrap());
+        assert_eq!(
+            parse_declaration(&mut tokens),
+            Ok(vec![
+                Declaration::Enum(EnumDeclaration {
+                    tag: None,
+                    enumerators: Some(vec![enumerator("A", None)]),
+                }),
+                Declaration::Variable(VariableDeclaration {
+                    variable: Variable {
+                        identifier: "a".to_string(),
+                    },
+                    initializer: Some(Initializer::Single(Expression::Variable {
+                        v: Variable {
+                            identifier: "A".to_string(),
+                        },
+                        ty: None,
+                    })),
+                    ty: Type::Int,
+                    storage_class: Some(StorageClass::Static),
+                    is_const: false,
+                }),
+            ])
+        );
+
+        // a tag alone is just another name for int, but its use is kept to check the tag exists
+        let mut declarations = parse(
+            &tokenize("enum Color f(enum Color c) { return (enum Color)c; }").unwrap(),
+            false,
+        )
+        .unwrap()
+        .declarations;
+        assert_eq!(
+            declarations.remove(0),
+            Declaration::Enum(EnumDeclaration {
+                tag: Some("Color".to_string()),
+                enumerators: None,
+            })
+        );
+        assert_eq!(
+            declarations,
+            parse(&tokenize("int f(int c) { return (int)c; }").unwrap(), false)
+                .unwrap()
+                .declarations
+        );
+
+        let test_cases = [
+            ("enum;", "Expected enum tag or open brace"),
+            ("enum E {};", "Expected enumerator"),
+            ("enum E { A B };", "Expected comma or close brace"),
+            ("enum E long x;", "Invalid type specifier"),
+            (
+                "int f(enum { A } a);",
+                "Enums can only be defined in declarations",
+            ),
+            (
+                "long x = sizeof(enum { A });",
+                "Enums can only be defined in declarations",
+            ),
+        ];
+
+        for (source, error) in test_cases {
+            let mut tokens = Tokens::from(tokenize(source).unwrap());
+            assert_eq!(
+                parse_declaration(&mut tokens),
+                Err(error.to_string()),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_structs_and_unions_unsupported() {
+        use crate::compiler::lexer::tokenize;
+
+        let test_cases = [
+            ("struct Foo x;", "Structs are not supported yet"),
+            (
+                "static struct S { int a; } s;",
+                "Structs are not supported yet",
+            ),
+            ("int f(struct S *s);", "Structs are not supported yet"),
+            (
+                "int main(void) { struct Foo *p; return 0; }",
+                "Structs are not supported yet",
+            ),
+            (
+                "long f(void) { return sizeof(union U); }",
+                "Unions are not supported yet",
+            ),
+            (
+                "int f(int *p) { return p->x; }",
+                "Member access is not supported yet",
+            ),
+            (
+                "int f(int a) { return a.x; }",
+                "Member access is not supported yet",
+            ),
+        ];
+
+        for (source, error) in test_cases {
+            assert_eq!(
+                parse(&tokenize(source).unwrap(), false).map_err(|e| e.message().to_string()),
+                Err(error.to_string()),
+                "{source}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_type_qualifiers() {
+        use crate::compiler::lexer::tokenize;
+
+        let test_cases = [
+            // only a const variable itself is parsed differently
+            ("const int *x = 0;", "int *x = 0;"),
+            ("volatile long y;", "long y;"),
+            ("int const volatile *z;", "int *z;"),
+            (
+                "int puts(const char *restrict s, char *const *p);",
+                "int puts(char *s, char **p);",
+            ),
+            (
+                "long f(void) { return (const long)sizeof(volatile int *const); }",
+                "long f(void) { return (long)sizeof(int *); }",
+            ),
+        ];
+
+        for (qualified, unqualified) in test_cases {
+            assert_eq!(
+                parse(&tokenize(qualified).unwrap(), false),
+                parse(&tokenize(unqualified).unwrap(), false),
+                "{qualified}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_const_variables() {
+        use crate::compiler::lexer::tokenize;
+
+        let test_cases = [
+            ("const int x = 5;", true),
+            ("int const x[2];", true),
+            ("int *const x = 0;", true),
+            ("int *x;", false),
+            ("const int *x;", false),
+            ("int *const *x;", false),
+            ("static volatile long x;", false),
+        ];
+
+        for (source, expected) in test_cases {
+            let Ok(Program { declarations }) = parse(&tokenize(source).unwrap(), false) else {
+                panic!("{source}");
+            };
+            let [Declaration::Variable(declaration)] = declarations.as_slice() else {
+                panic!("{source}");
+            };
+            assert_eq!(declaration.is_const, expected, "{source}");
+        }
+    }
+
+    #[test]
+    fn test_parse_trailing_comma_in_call() {
+        use crate::compiler::lexer::tokenize;
+
+        let tokens = tokenize("int g(void) { return f(1, 2,); }").unwrap();
+        assert_eq!(
+            parse(&tokens, true),
+            parse(&tokenize("int g(void) { return f(1, 2); }").unwrap(), false)
+        );
+        assert!(parse(&tokens, false).is_err());
+
+        // there still has to be an argument before it
+        assert!(parse(&tokenize("int g(void) { return f(,); }").unwrap(), true).is_err());
+    }
+
+    #[test]
+    fn test_parse_unsized_array_parameters() {
+        use crate::compiler::lexer::tokenize;
+
+        let parse_source = |source| parse(&tokenize(source).unwrap(), false);
+
+        assert_eq!(
+            parse_source("int main(int argc, char *argv[]);"),
+            parse_source("int main(int argc, char **argv);")
+        );
+        assert_eq!(
+            parse_source("int f(int a[][3], long []);"),
+            parse_source("int f(int (*a)[3], long *);")
+        );
+
+        // only the parameter itself can be left without a size
+        assert!(parse_source("int x[];").is_err());
+        assert!(parse_source("int f(int a[3][]);").is_err());
+        assert!(parse_source("int f(int (*p)[]);").is_err());
+        assert!(parse_source("int main(void) { int x[]; return 0; }").is_err());
+    }
+
+    #[test]
+    fn test_parse_bare_return() {
+        let mut tokens = Tokens::from(vec![Token::ReturnKeyword, Token::Semicolon]);
+
+        assert_eq!(parse_statement(&mut tokens), Ok(Statement::Return(None)));
+    }
+
+    #[test]
+    fn test_parse_type_from_specifiers() {
+        use Token::{IntKeyword, LongKeyword, UnsignedKeyword};
+
+        let test_cases = vec![
+            (vec![UnsignedKeyword], Ok(Type::UInt)),
+            (vec![IntKeyword, UnsignedKeyword], Ok(Type::UInt)),
+            (vec![UnsignedKeyword, LongKeyword], Ok(Type::ULong)),
+            (
+                vec![LongKeyword, UnsignedKeyword, IntKeyword],
+                Ok(Type::ULong),
+            ),
+            (vec![IntKeyword, LongKeyword], Ok(Type::Long)),
+            (
+                vec![UnsignedKeyword, UnsignedKeyword],
+                Err("Invalid type specifier".to_string()),
+            ),
+            (
+                vec![UnsignedKeyword, Token::BoolKeyword],
+                Err("Invalid type specifier".to_string()),
+            ),
+            (vec![Token::DoubleKeyword], Ok(Type::Double)),
+            (
+                vec![LongKeyword, Token::DoubleKeyword],
+                Err("Invalid type specifier".to_string()),
+            ),
+            (vec![Token::CharKeyword], Ok(Type::Char)),
+            (
+                vec![Token::SignedKeyword, Token::CharKeyword],
+                Ok(Type::SChar),
+            ),
+            (vec![Token::CharKeyword, UnsignedKeyword], Ok(Type::UChar)),
+            (vec![Token::SignedKeyword, LongKeyword], Ok(Type::Long)),
+            (
+                vec![Token::CharKeyword, IntKeyword],
+                Err("Invalid type specifier".to_string()),
+            ),
+            (
+                vec![Token::SignedKeyword, UnsignedKeyword],
+                Err("Invalid type specifier".to_string()),
+            ),
+        ];
+
+        for (specifiers, expected) in test_cases {
+            assert_eq!(parse_type_from_specifiers(&specifiers), expected);
+        }
+    }
+
+    fn permutations(tokens: &[Token]) -> Vec<Vec<Token>> {
+        if tokens.is_empty() {
+            return vec![vec![]];
+        }
+
+        let mut result = Vec::new();
+        for i in 0..tokens.len() {
+            let mut rest = tokens.to_vec();
+            let first = rest.remove(i);
+            for mut permutation in permutations(&rest) {
+                permutation.insert(0, first.clone());
+                result.push(permutation);
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_parse_type_from_specifier_permutations() {
+        use Token::{IntKeyword, LongKeyword, UnsignedKeyword};
+
+        let test_cases = vec![
+            (vec![LongKeyword, LongKeyword], Ok(Type::Long)),
+            (vec![LongKeyword, LongKeyword, IntKeyword], Ok(Type::Long)),
+            (
+                vec![UnsignedKeyword, LongKeyword, LongKeyword],
+                Ok(Type::ULong),
+            ),
+            (
+                vec![UnsignedKeyword, LongKeyword, LongKeyword, IntKeyword],
+                Ok(Type::ULong),
+            ),
+            (
+                vec![UnsignedKeyword, LongKeyword, IntKeyword],
+                Ok(Type::ULong),
+            ),
+            (vec![UnsignedKeyword, IntKeyword], Ok(Type::UInt)),
+            (vec![LongKeyword, IntKeyword], Ok(Type::Long)),
+            (
+                vec![IntKeyword, IntKeyword],
+                Err("Invalid type specifier".to_string()),
+            ),
+            (
+                vec![LongKeyword, LongKeyword, LongKeyword],
+                Err("Invalid type specifier".to_string()),
+            ),
+            (
+                vec![LongKeyword, LongKeyword, IntKeyword, IntKeyword],
+                Err("Invalid type specifier".to_string()),
+            ),
+            (
+                vec![UnsignedKeyword, UnsignedKeyword, LongKeyword, LongKeyword],
+                Err("Invalid type specifier".to_string()),
+            ),
+            (
+                vec![LongKeyword, LongKeyword, Token::DoubleKeyword],
+                Err("Invalid type specifier".to_string()),
+            ),
+        ];
+
+        for (specifiers, expected) in test_cases {
+            for permutation in permutations(&specifiers) {
+                assert_eq!(
+                    parse_type_from_specifiers(&permutation),
+                    expected,
+                    "{permutation:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_pointer_declarators() {
+        // long *(*p);
+        let mut tokens = Tokens::from(vec![
+            Token::LongKeyword,
+            Token::Asterisk,
+            Token::OpenParen,
+            Token::Asterisk,
+            Token::Identifier("p".to_string()),
+            Token::CloseParen,
+            Token::Semicolon,
+        ]);
+
+        assert_eq!(
+            parse_declaration(&mut tokens),
+            Ok(vec![Declaration::Variable(VariableDeclaration {
+                variable: Variable {
+                    identifier: "p".to_string(),
+                },
+                initializer: None,
+                ty: Type::Pointer(Box::new(Type::Pointer(Box::new(Type::Long)))),
+                storage_class: None,
+                is_const: false,
+            })])
+        );
+
+        // int *f(int *x);
+        let mut tokens = Tokens::from(vec![
+            Token::IntKeyword,
+            Token::Asterisk,
+            Token::Identifier("f".to_string()),
+            Token::OpenParen,
+            Token::IntKeyword,
+            Token::Asterisk,
+            Token::Identifier("x".to_string()),
+            Token::CloseParen,
+            Token::Semicolon,
+        ]);
+
+        let int_pointer = Type::Pointer(Box::new(Type::Int));
+
+        assert_eq!(
+            parse_declaration(&mut tokens),
+            Ok(vec![Declaration::Function(FunctionDeclaration {
+                function: Function {
+                    identifier: "f".to_string(),
+                },
+                parameters: vec![Variable {
+                    identifier: "x".to_string(),
+                }],
+                body: None,
+                ty: Type::Function {
+                    return_type: Box::new(int_pointer.clone()),
+                    parameters: vec![int_pointer],
+                },
+                storage_class: None,
+            })])
+        );
+
+        // int (*f)(void);
+        let mut tokens = Tokens::from(vec![
+            Token::IntKeyword,
+            Token::OpenParen,
+            Token::Asterisk,
+            Token::Identifier("f".to_string()),
+            Token::CloseParen,
+            Token::OpenParen,
+            Token::VoidKeyword,
+            Token::CloseParen,
+            Token::Semicolon,
+        ]);
+
+        assert_eq!(
+            parse_declaration(&mut tokens),
+            Ok(vec![Declaration::Variable(VariableDeclaration {
+                variable: Variable {
+                    identifier: "f".to_string(),
+                },
+                initializer: None,
+                ty: Type::Pointer(Box::new(Type::Function {
+                    return_type: Box::new(Type::Int),
+                    parameters: vec![],
+                })),
+                storage_class: None,
+                is_const: false,
+            })])
+        );
+    }
+
+    #[test]
+    fn test_parse_arrays() {
+        // int *a[2][3];
+        let mut tokens = Tokens::from(vec![
+            Token::IntKeyword,
+            Token::Asterisk,
+            Token::Identifier("a".to_string()),
+            Token::OpenBracket,
+            Token::ConstantInt("2".to_string()),
+            Token::CloseBracket,
+            Token::OpenBracket,
+            Token::ConstantInt("3".to_string()),
+            Token::CloseBracket,
+            Token::Semicolon,
+        ]);
+
+        assert_eq!(
+            parse_declaration(&mut tokens),
+            Ok(vec![Declaration::Variable(VariableDeclaration {
+                variable: Variable {
+                    identifier: "a".to_string(),
+                },
+                initializer: None,
+                ty: Type::Array {
+                    element: Box::new(Type::Array {
+                        element: Box::new(Type::Pointer(Box::new(Type::Int))),
+                        size: 3,
+                    }),
+                    size: 2,
+                },
+                storage_class: None,
+                is_const: false,
+            })])
+        );
+
+        // *a[1]
+        let mut tokens = Tokens::from(vec![
+            Token::Asterisk,
+            Token::Identifier("a".to_string()),
+            Token::OpenBracket,
+            Token::ConstantInt("1".to_string()),
+            Token::CloseBracket,
+        ]);
+
+        assert_eq!(
+            parse_expression(&mut tokens, 0),
+            Ok(Expression::Dereference {
+                expr: Box::new(Expression::Subscript {
+                    expr: Box::new(Expression::Variable {
+                        v: Variable {
+                            identifier: "a".to_string(),
+                        },
+                        ty: None,
+                    }),
+                    index: Box::new(Expression::Constant {
+                        c: Constant::ConstantInt(1),
+                        ty: None,
+                    }),
+                    ty: None,
+                }),
+                ty: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_compound_initializer() {
+        // {{1}, {2,},}
+        let mut tokens = Tokens::from(vec![
+            Token::OpenBrace,
+            Token::OpenBrace,
+            Token::ConstantInt("1".to_string()),
+            Token::CloseBrace,
+            Token::Comma,
+            Token::OpenBrace,
+            Token::ConstantInt("2".to_string()),
+            Token::Comma,
+            Token::CloseBrace,
+            Token::Comma,
+            Token::CloseBrace,
+        ]);
+
+        let constant = |n| {
+            Initializer::Single(Expression::Constant {
+                c: Constant::ConstantInt(n),
+                ty: None,
+            })
+        };
+
+        assert_eq!(
+            parse_initializer(&mut tokens),
+            Ok(Initializer::Compound(vec![
+                Initializer::Compound(vec![constant(1)]),
+                Initializer::Compound(vec![constant(2)]),
+            ]))
+        );
+        assert!(tokens.is_empty());
+
+        let mut tokens = Tokens::from(vec![Token::OpenBrace, Token::CloseBrace]);
+        assert!(parse_initializer(&mut tokens).is_err());
+    }
+
+    #[test]
+    fn test_mangled_programs_do_not_panic() {
+        use crate::compiler::lexer::tokenize;
+
+        let programs = [
+            "static long a[2][3] = {{1, 2}, {3}}; extern int b;",
+            "int f(int x, char *const *p, double (*g)(int)) { return g(x) ? *p[x] : -x; }",
+            "int main(void) { for (int i = 0; i < 10; i++) { if (i % 2) continue; else break; } }",
+            "enum E { A = 1, B, }; int f(enum E e) { switch (e) { case A: return sizeof(long); default: goto end; } end: return (int)e; }",
+            "int main(void) { int x = 1; do x <<= 2; while (x && !(x >= 100)); return x++ + --x; }",
+            "unsigned long f(void) { return 4294967296ul * 'a' + 1.5e3 / (unsigned char)3; }",
+        ];
+        let programs: Vec<Vec<Token>> = programs
+            .iter()
+            .map(|program| tokenize(program).unwrap())
+            .collect();
+        let all_tokens: Vec<Token> = programs.iter().flatten().cloned().collect();
+
+        // xorshift, so that failures can be reproduced
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % bound as u64) as usize
+        };
+
+        for _ in 0..5000 {
+            let mut tokens = programs[next(programs.len())].clone();
+
+            for _ in 0..1 + next(4) {
+                let i = next(tokens.len());
+                match next(3) {
+                    0 => {
+                        tokens.remove(i);
+                    }
+                    1 => tokens.insert(i, all_tokens[next(all_tokens.len())].clone()),
+                    _ => tokens.truncate(i),
+                }
+
+                if tokens.is_empty() {
+                    break;
+                }
+            }
+
+            // errors are fine, this only checks that there is no panic
+            let _ = parse(&tokens, true);
+        }
     }
 }
@@ -1,7 +1,9 @@
 pub const TAC_VAR_PREFIX: &str = "tac.var";
 pub const TAC_LABEL_PREFIX: &str = "tac.label";
+pub const TAC_CONST_PREFIX: &str = "tac.const";
 pub const SEMANTIC_VAR_PREFIX: &str = "sem.var";
 pub const SEMANTIC_LABEL_PREFIX: &str = "sem.label";
 pub const SEMANTIC_LOOP_PREFIX: &str = "sem.loop";
 pub const SEMANTIC_SWITCH_PREFIX: &str = "sem.switch";
 pub const SEMANTIC_CASE_PREFIX: &str = "sem.case";
+pub const ASM_LABEL_PREFIX: &str = "asm.label";
@@ -0,0 +1,365 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::compiler::{
+    asm::{Instruction, JumpTable, Label, Operand, Reg},
+    ast::Type,
+    symbols::{Symbol, SymbolAttributes, SymbolTable},
+};
+
+/// Callee-saved, so values kept in them survive function calls, and never used by codegen otherwise.
+pub const ALLOCATABLE_REGISTERS: [Reg; 5] = [Reg::BX, Reg::R12, Reg::R13, Reg::R14, Reg::R15];
+
+/// Assigns registers to pseudos by greedily coloring their interference graph.
+///
+/// Pseudos that can't be colored are left out of the result and end up on the stack.
+pub fn allocate(
+    instructions: &[Instruction],
+    jump_tables: &[JumpTable],
+    symbols: &SymbolTable,
+) -> HashMap<String, Reg> {
+    let address_taken = instructions
+        .iter()
+        .filter_map(|instruction| match instruction {
+            Instruction::Lea { src, .. } => pseudo(src),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+
+    let mut pseudos = Vec::new();
+    let mut indices = HashMap::new();
+
+    let mut uses = Vec::with_capacity(instructions.len());
+    let mut defs = Vec::with_capacity(instructions.len());
+
+    for instruction in instructions {
+        let (used, defined) = uses_and_defs(instruction);
+
+        let mut index = |name: &str| -> Option<usize> {
+            if !is_allocatable(name, symbols, &address_taken) {
+                return None;
+            }
+
+            Some(*indices.entry(name.to_string()).or_insert_with(|| {
+                pseudos.push(name.to_string());
+                pseudos.len() - 1
+            }))
+        };
+
+        uses.push(used.into_iter().filter_map(&mut index).collect::<Vec<_>>());
+        defs.push(
+            defined
+                .into_iter()
+                .filter_map(&mut index)
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    let live_out = liveness(instructions, jump_tables, &uses, &defs);
+
+    let mut interference = vec![HashSet::new(); pseudos.len()];
+    for (i, instruction) in instructions.iter().enumerate() {
+        // the source of a move may share a register with its destination, unless the
+        // move truncates it
+        let moved = match instruction {
+            Instruction::Mov {
+                src: Operand::Pseudo(src),
+                dst: Operand::Pseudo(dst),
+                ..
+            } if symbols.get(src).map(|s| &s.ty) == symbols.get(dst).map(|s| &s.ty) => {
+                indices.get(src).copied()
+            }
+            _ => None,
+        };
+
+        for &def in &defs[i] {
+            for &live in &live_out[i] {
+                if live != def && Some(live) != moved {
+                    interference[def].insert(live);
+                    interference[live].insert(def);
+                }
+            }
+        }
+    }
+
+    let mut occurrences = vec![0; pseudos.len()];
+    for index in uses.iter().chain(&defs).flatten() {
+        occurrences[*index] += 1;
+    }
+
+    // frequently used pseudos get the first pick
+    let mut order = (0..pseudos.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&index| std::cmp::Reverse(occurrences[index]));
+
+    let mut colors: Vec<Option<Reg>> = vec![None; pseudos.len()];
+    for index in order {
+        colors[index] = ALLOCATABLE_REGISTERS.into_iter().find(|reg| {
+            interference[index]
+                .iter()
+                .all(|&neighbor| colors[neighbor] != Some(*reg))
+        });
+    }
+
+    pseudos
+        .into_iter()
+        .zip(colors)
+        .filter_map(|(name, color)| color.map(|reg| (name, reg)))
+        .collect()
+}
+
+/// Static variables, constants, variables whose address is taken and doubles (which would need
+/// SSE registers) live in memory no matter what.
+fn is_allocatable(name: &str, symbols: &SymbolTable, address_taken: &HashSet<&str>) -> bool {
+    !address_taken.contains(name)
+        && !matches!(
+            symbols.get(name),
+            Some(Symbol {
+                attrs: SymbolAttributes::Static { .. } | SymbolAttributes::Constant(_),
+                ..
+            }) | Some(Symbol {
+                ty: Type::Double,
+                ..
+            })
+        )
+}
+
+fn pseudo(operand: &Operand) -> Option<&str> {
+    match operand {
+        Operand::Pseudo(name) => Some(name),
+        _ => None,
+    }
+}
+
+fn uses_and_defs(instruction: &Instruction) -> (Vec<&str>, Vec<&str>) {
+    match instruction {
+        Instruction::Mov { src, dst, .. }
+        | Instruction::Movsx { src, dst, .. }
+        | Instruction::MovZeroExtend { src, dst, .. }
+        | Instruction::Lea { src, dst }
+        | Instruction::Cvttsd2si { src, dst, .. }
+        | Instruction::Cvtsi2sd { src, dst, .. } => (
+            pseudo(src).into_iter().collect(),
+            pseudo(dst).into_iter().collect(),
+        ),
+        Instruction::Binary { src, dst, .. } => (
+            pseudo(src).into_iter().chain(pseudo(dst)).collect(),
+            pseudo(dst).into_iter().collect(),
+        ),
+        Instruction::Cmp { src, dst, .. } => {
+            (pseudo(src).into_iter().chain(pseudo(dst)).collect(), vec![])
+        }
+        // `set` only writes the lowest byte, so the rest of the value is still needed
+        Instruction::Unary { dst, .. }
+        | Instruction::Sal { dst, .. }
+        | Instruction::Sar { dst, .. }
+        | Instruction::Shr { dst, .. }
+        | Instruction::SetCC { dst, .. } => (
+            pseudo(dst).into_iter().collect(),
+            pseudo(dst).into_iter().collect(),
+        ),
+        Instruction::Idiv { operand, .. }
+        | Instruction::Div { operand, .. }
+        | Instruction::Push(operand)
+        | Instruction::IndirectCall(operand) => (pseudo(operand).into_iter().collect(), vec![]),
+        Instruction::Cdq(_)
+        | Instruction::Jmp { .. }
+        | Instruction::JmpCC { .. }
+        | Instruction::JmpTable { .. }
+        | Instruction::Label(_)
+        | Instruction::AllocateStack(_)
+        | Instruction::DeallocateStack(_)
+        | Instruction::Pop(_)
+        | Instruction::Call { .. }
+        | Instruction::Ret
+        | Instruction::SourceLine(_)
+        | Instruction::Comment(_) => (vec![], vec![]),
+    }
+}
+
+fn successors(
+    instructions: &[Instruction],
+    jump_tables: &[JumpTable],
+    labels: &HashMap<&str, usize>,
+    i: usize,
+) -> Vec<usize> {
+    let label_index = |label: &Label| labels[label.identifier.as_str()];
+    let next = (i + 1 < instructions.len()).then_some(i + 1);
+
+    match &instructions[i] {
+        Instruction::Jmp { target } => vec![label_index(target)],
+        Instruction::JmpCC { target, .. } => {
+            let mut successors = vec![label_index(target)];
+            successors.extend(next);
+            successors
+        }
+        Instruction::JmpTable { table, .. } => jump_tables
+            .iter()
+            .find(|jt| jt.label == *table)
+            .unwrap()
+            .targets
+            .iter()
+            .map(label_index)
+            .collect(),
+        Instruction::Ret => vec![],
+        _ => next.into_iter().collect(),
+    }
+}
+
+/// Returns the pseudos live after each instruction.
+fn liveness(
+    instructions: &[Instruction],
+    jump_tables: &[JumpTable],
+    uses: &[Vec<usize>],
+    defs: &[Vec<usize>],
+) -> Vec<HashSet<usize>> {
+    let labels = instructions
+        .iter()
+        .enumerate()
+        .filter_map(|(i, instruction)| match instruction {
+            Instruction::Label(label) => Some((label.identifier.as_str(), i)),
+            _ => None,
+        })
+        .collect::<HashMap<_, _>>();
+
+    let successors = (0..instructions.len())
+        .map(|i| successors(instructions, jump_tables, &labels, i))
+        .collect::<Vec<_>>();
+
+    let mut live_in = vec![HashSet::new(); instructions.len()];
+    let mut live_out = vec![HashSet::new(); instructions.len()];
+
+    // loops feed liveness back to earlier instructions, so repeat until stable
+    let mut changed = true;
+    while changed {
+        changed = false;
+
+        for i in (0..instructions.len()).rev() {
+            let out = successors[i]
+                .iter()
+                .flat_map(|&s| live_in[s].iter().copied())
+                .collect::<HashSet<_>>();
+
+            let mut input = out
+                .iter()
+                .copied()
+                .filter(|pseudo| !defs[i].contains(pseudo))
+                .collect::<HashSet<_>>();
+            input.extend(uses[i].iter().copied());
+
+            if input != live_in[i] || out != live_out[i] {
+                changed = true;
+                live_in[i] = input;
+                live_out[i] = out;
+            }
+        }
+    }
+
+    live_out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::compiler::{asm::AssemblyType, ast::Type};
+
+    fn symbols(names: &[&str]) -> SymbolTable {
+        let mut symbols = SymbolTable::new();
+
+        for name in names {
+            symbols.insert(
+                name.to_string(),
+                Symbol {
+                    ty: Type::Int,
+                    attrs: SymbolAttributes::Local,
+                },
+            );
+        }
+
+        symbols
+    }
+
+    fn mov(src: Operand, dst: &str) -> Instruction {
+        Instruction::Mov {
+            ty: AssemblyType::Longword,
+            src,
+            dst: Operand::Pseudo(dst.to_string()),
+        }
+    }
+
+    fn add(src: &str, dst: &str) -> Instruction {
+        Instruction::Binary {
+            op: crate::compiler::asm::BinaryOperator::Add,
+            ty: AssemblyType::Longword,
+            src: Operand::Pseudo(src.to_string()),
+            dst: Operand::Pseudo(dst.to_string()),
+        }
+    }
+
+    fn cmp_zero(dst: &str) -> Instruction {
+        Instruction::Cmp {
+            ty: AssemblyType::Longword,
+            src: Operand::Imm(0),
+            dst: Operand::Pseudo(dst.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_simultaneously_live_pseudos_get_different_registers() {
+        let instructions = vec![
+            mov(Operand::Imm(1), "a"),
+            mov(Operand::Imm(2), "b"),
+            add("b", "a"),
+            mov(Operand::Imm(3), "c"),
+            add("a", "c"),
+            mov(Operand::Pseudo("c".to_string()), "d"),
+            Instruction::Ret,
+        ];
+
+        let registers = allocate(&instructions, &[], &symbols(&["a", "b", "c", "d"]));
+
+        assert_eq!(registers.len(), 4);
+        assert_ne!(registers["a"], registers["b"]);
+        assert_ne!(registers["a"], registers["c"]);
+    }
+
+    #[test]
+    fn test_spills_when_out_of_registers() {
+        let names = ["a", "b", "c", "d", "e", "f"];
+
+        let mut instructions = names
+            .iter()
+            .map(|name| mov(Operand::Imm(1), name))
+            .collect::<Vec<_>>();
+        instructions.extend(names.iter().map(|name| add(name, "a")));
+        instructions.push(Instruction::Ret);
+
+        let registers = allocate(&instructions, &[], &symbols(&names));
+
+        assert_eq!(registers.len(), ALLOCATABLE_REGISTERS.len());
+    }
+
+    #[test]
+    fn test_loop_keeps_values_live() {
+        let top = Label {
+            identifier: "top".to_string(),
+        };
+
+        // `a` is last read before `b` is written, but the back edge keeps it live
+        let instructions = vec![
+            mov(Operand::Imm(1), "a"),
+            Instruction::Label(top.clone()),
+            cmp_zero("a"),
+            mov(Operand::Imm(2), "b"),
+            cmp_zero("b"),
+            Instruction::JmpCC {
+                cc: crate::compiler::asm::ConditionCode::NE,
+                target: top,
+            },
+            Instruction::Ret,
+        ];
+
+        let registers = allocate(&instructions, &[], &symbols(&["a", "b"]));
+
+        assert_ne!(registers["a"], registers["b"]);
+    }
+}
@@ -0,0 +1,195 @@
+use crate::compiler::{
+    ast::{BinaryOperator, Constant, Expression, Type, UnaryOperator},
+    tacky,
+};
+
+/// Folds an integer constant expression, like an enumerator value or a case label, to a single
+/// constant of the type C gives it.
+///
+/// Returns `None` if the expression is not constant, involves doubles, or traps.
+pub fn fold_integer_constant(expr: &Expression) -> Option<Constant> {
+    Some(match evaluate(expr)? {
+        tacky::Constant::Int(n) => Constant::ConstantInt(n),
+        tacky::Constant::Long(n) => Constant::ConstantLong(n),
+        tacky::Constant::UInt(n) => Constant::ConstantUInt(n),
+        tacky::Constant::ULong(n) => Constant::ConstantULong(n),
+    })
+}
+
+fn evaluate(expr: &Expression) -> Option<tacky::Constant> {
+    match expr {
+        Expression::Constant { c, .. } => match c {
+            Constant::ConstantInt(n) => Some(tacky::Constant::Int(*n)),
+            Constant::ConstantLong(n) => Some(tacky::Constant::Long(*n)),
+            Constant::ConstantUInt(n) => Some(tacky::Constant::UInt(*n)),
+            Constant::ConstantULong(n) => Some(tacky::Constant::ULong(*n)),
+            Constant::ConstantDouble(_) => None,
+        },
+        Expression::Cast {
+            target_ty: target_ty @ (Type::Int | Type::Long | Type::UInt | Type::ULong),
+            expr,
+            ..
+        } => Some(tacky::Constant::new(evaluate(expr)?.value(), target_ty)),
+        Expression::Unary { op, expr, .. } => {
+            let c = evaluate(expr)?;
+
+            Some(match op {
+                UnaryOperator::Plus => c,
+                UnaryOperator::Complement => {
+                    c.apply_unary(tacky::UnaryOperator::Complement, &c.ty())
+                }
+                UnaryOperator::Negate => c.apply_unary(tacky::UnaryOperator::Negate, &c.ty()),
+                UnaryOperator::Not => c.apply_unary(tacky::UnaryOperator::Not, &Type::Int),
+                _ => return None,
+            })
+        }
+        Expression::Binary { op, lhs, rhs, .. } => {
+            let (lhs, rhs) = (evaluate(lhs)?, evaluate(rhs)?);
+
+            let op = match op {
+                BinaryOperator::LogicalAnd => {
+                    return Some(tacky::Constant::Int(
+                        (lhs.value() != 0 && rhs.value() != 0) as i32,
+                    ));
+                }
+                BinaryOperator::LogicalOr => {
+                    return Some(tacky::Constant::Int(
+                        (lhs.value() != 0 || rhs.value() != 0) as i32,
+                    ));
+                }
+                // shifts keep the type of their left operand
+                BinaryOperator::ShiftLeft => {
+                    return lhs.apply_binary(tacky::BinaryOperator::ShiftLeft, rhs, &lhs.ty());
+                }
+                BinaryOperator::ShiftRight => {
+                    return lhs.apply_binary(tacky::BinaryOperator::ShiftRight, rhs, &lhs.ty());
+                }
+                op => binary_operator(*op),
+            };
+
+            let common = common_type(&lhs.ty(), &rhs.ty());
+            let lhs = tacky::Constant::new(lhs.value(), &common);
+            let rhs = tacky::Constant::new(rhs.value(), &common);
+
+            let ty = match op {
+                tacky::BinaryOperator::Equal
+                | tacky::BinaryOperator::NotEqual
+                | tacky::BinaryOperator::LessThan
+                | tacky::BinaryOperator::LessOrEqual
+                | tacky::BinaryOperator::GreaterThan
+                | tacky::BinaryOperator::GreaterOrEqual => Type::Int,
+                _ => common,
+            };
+
+            lhs.apply_binary(op, rhs, &ty)
+        }
+        Expression::Conditional {
+            condition,
+            then_expr,
+            else_expr,
+            ..
+        } => {
+            let condition = evaluate(condition)?;
+            let (then_value, else_value) = (evaluate(then_expr)?, evaluate(else_expr)?);
+            let common = common_type(&then_value.ty(), &else_value.ty());
+
+            let value = if condition.value() != 0 {
+                then_value
+            } else {
+                else_value
+            };
+
+            Some(tacky::Constant::new(value.value(), &common))
+        }
+        _ => None,
+    }
+}
+
+fn binary_operator(op: BinaryOperator) -> tacky::BinaryOperator {
+    match op {
+        BinaryOperator::Add => tacky::BinaryOperator::Add,
+        BinaryOperator::Subtract => tacky::BinaryOperator::Subtract,
+        BinaryOperator::Multiply => tacky::BinaryOperator::Multiply,
+        BinaryOperator::Divide => tacky::BinaryOperator::Divide,
+        BinaryOperator::Remainder => tacky::BinaryOperator::Remainder,
+        BinaryOperator::BitwiseAnd => tacky::BinaryOperator::BitwiseAnd,
+        BinaryOperator::BitwiseOr => tacky::BinaryOperator::BitwiseOr,
+        BinaryOperator::BitwiseXor => tacky::BinaryOperator::BitwiseXor,
+        BinaryOperator::ShiftLeft => tacky::BinaryOperator::ShiftLeft,
+        BinaryOperator::ShiftRight => tacky::BinaryOperator::ShiftRight,
+        BinaryOperator::Equal => tacky::BinaryOperator::Equal,
+        BinaryOperator::NotEqual => tacky::BinaryOperator::NotEqual,
+        BinaryOperator::LessThan => tacky::BinaryOperator::LessThan,
+        BinaryOperator::LessOrEqual => tacky::BinaryOperator::LessOrEqual,
+        BinaryOperator::GreaterThan => tacky::BinaryOperator::GreaterThan,
+        BinaryOperator::GreaterOrEqual => tacky::BinaryOperator::GreaterOrEqual,
+        BinaryOperator::LogicalAnd | BinaryOperator::LogicalOr => {
+            unreachable!("{op:?} has no tacky operator")
+        }
+    }
+}
+
+/// The usual arithmetic conversions for the integer types constants can have.
+fn common_type(ty1: &Type, ty2: &Type) -> Type {
+    if ty1 == ty2 {
+        ty1.clone()
+    } else if ty1.size() == ty2.size() {
+        // same rank, the unsigned type wins
+        if ty1.is_signed() {
+            ty2.clone()
+        } else {
+            ty1.clone()
+        }
+    } else if ty1.size() > ty2.size() {
+        ty1.clone()
+    } else {
+        ty2.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::ast::{BlockItem, Declaration, Statement};
+
+    fn fold(source: &str) -> Option<Constant> {
+        let source = format!("int main(void) {{ return {source}; }}");
+        let tokens = crate::compiler::lexer::tokenize(&source).unwrap();
+        let ast = crate::compiler::parser::parse(&tokens, false).unwrap();
+
+        let Declaration::Function(main) = &ast.declarations[0] else {
+            unreachable!()
+        };
+        let BlockItem::Statement(Statement::Return(Some(expr))) =
+            &main.body.as_ref().unwrap().items[0]
+        else {
+            unreachable!()
+        };
+
+        fold_integer_constant(expr)
+    }
+
+    #[test]
+    fn test_fold_integer_constant() {
+        let test_cases = [
+            ("1 + 1", Some(Constant::ConstantInt(2))),
+            ("-(-1)", Some(Constant::ConstantInt(1))),
+            ("-1", Some(Constant::ConstantInt(-1))),
+            ("~0u", Some(Constant::ConstantUInt(u32::MAX))),
+            ("-1 < 0u", Some(Constant::ConstantInt(0))),
+            ("1 << 40L", None),
+            ("1L << 40", Some(Constant::ConstantLong(1 << 40))),
+            ("2147483647 + 1L", Some(Constant::ConstantLong(2147483648))),
+            ("(long)-1 + 1u", Some(Constant::ConstantLong(0))),
+            ("1 ? 2 : 3L", Some(Constant::ConstantLong(2))),
+            ("!5 || 0", Some(Constant::ConstantInt(0))),
+            ("1 / 0", None),
+            ("1.5", None),
+            ("x + 1", None),
+        ];
+
+        for (source, expected) in test_cases {
+            assert_eq!(fold(source), expected, "{source}");
+        }
+    }
+}
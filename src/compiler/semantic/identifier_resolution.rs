@@ -1,9 +1,11 @@
 use crate::compiler::{
     ast::{
-        Block, BlockItem, Declaration, Expression, ForInitializer, Function, FunctionDeclaration,
-        Program, Statement, StorageClass, UnaryOperator, Variable, VariableDeclaration,
+        Block, BlockItem, Constant, Declaration, EnumDeclaration, Enumerator, Expression,
+        ForInitializer, Function, FunctionDeclaration, Initializer, Program, Statement,
+        StorageClass, UnaryOperator, Variable, VariableDeclaration,
     },
     prefixes::SEMANTIC_VAR_PREFIX,
+    semantic::constant_expression::fold_integer_constant,
 };
 use std::collections::HashMap;
 
@@ -12,17 +14,23 @@ struct IdentifierMapEntry {
     new_name: String,
     from_current_scope: bool,
     has_linkage: bool,
+    /// The value of an enum constant, which replaces every use of it.
+    constant: Option<i32>,
 }
 
 #[derive(Debug, Clone)]
 struct IdentifierMap {
     map: HashMap<String, IdentifierMapEntry>,
+    /// Enum tags live in a namespace of their own, mapped to whether they're from the current
+    /// scope.
+    tags: HashMap<String, bool>,
 }
 
 impl IdentifierMap {
     fn new() -> Self {
         Self {
             map: HashMap::new(),
+            tags: HashMap::new(),
         }
     }
 
@@ -44,6 +52,9 @@ impl IdentifierMap {
         for (_, entry) in clone.map.iter_mut() {
             entry.from_current_scope = false;
         }
+        for (_, from_current_scope) in clone.tags.iter_mut() {
+            *from_current_scope = false;
+        }
 
         clone
     }
@@ -95,6 +106,7 @@ impl IdentifierResolver {
             Declaration::Function(fd) => {
                 Declaration::Function(self.handle_top_level_function_declaration(fd, map)?)
             }
+            Declaration::Enum(ed) => Declaration::Enum(Self::handle_enum_declaration(ed, map)?),
         })
     }
 
@@ -103,16 +115,35 @@ impl IdentifierResolver {
         declaration: &VariableDeclaration,
         map: &mut IdentifierMap,
     ) -> Result<VariableDeclaration, String> {
+        if let Some(entry) = map.get(&declaration.variable.identifier) {
+            if entry.from_current_scope && entry.constant.is_some() {
+                return Err(format!(
+                    "Duplicate declaration of identifier {}",
+                    declaration.variable.identifier
+                ));
+            }
+        }
+
         map.insert(
             declaration.variable.identifier.clone(),
             IdentifierMapEntry {
                 new_name: declaration.variable.identifier.clone(),
                 from_current_scope: true,
                 has_linkage: true,
+                constant: None,
             },
         );
 
-        Ok(declaration.clone())
+        let initializer = if let Some(initializer) = &declaration.initializer {
+            Some(Self::handle_initializer(initializer, map)?)
+        } else {
+            None
+        };
+
+        Ok(VariableDeclaration {
+            initializer,
+            ..declaration.clone()
+        })
     }
 
     fn handle_top_level_function_declaration(
@@ -135,6 +166,7 @@ impl IdentifierResolver {
                 new_name: declaration.function.identifier.clone(),
                 from_current_scope: true,
                 has_linkage: true,
+                constant: None,
             },
         );
 
@@ -165,6 +197,11 @@ impl IdentifierResolver {
         parameter: &Variable,
         map: &mut IdentifierMap,
     ) -> Result<Variable, String> {
+        // an unnamed parameter can't be referred to
+        if parameter.identifier.is_empty() {
+            return Ok(self.fresh_variable(None));
+        }
+
         if let Some(entry) = map.get(&parameter.identifier) {
             if entry.from_current_scope {
                 return Err(format!(
@@ -181,6 +218,7 @@ impl IdentifierResolver {
                 new_name: fresh.identifier.clone(),
                 from_current_scope: true,
                 has_linkage: false,
+                constant: None,
             },
         );
 
@@ -220,6 +258,7 @@ impl IdentifierResolver {
             Declaration::Function(fd) => {
                 Declaration::Function(self.handle_block_level_function_declaration(fd, map)?)
             }
+            Declaration::Enum(ed) => Declaration::Enum(Self::handle_enum_declaration(ed, map)?),
         })
     }
 
@@ -246,6 +285,7 @@ impl IdentifierResolver {
                     new_name: declaration.variable.identifier.clone(),
                     from_current_scope: true,
                     has_linkage: true,
+                    constant: None,
                 },
             );
 
@@ -258,11 +298,12 @@ impl IdentifierResolver {
                     new_name: fresh.identifier.clone(),
                     from_current_scope: true,
                     has_linkage: false,
+                    constant: None,
                 },
             );
 
             let initializer = if let Some(initializer) = &declaration.initializer {
-                Some(Self::handle_expression(initializer, map)?)
+                Some(Self::handle_initializer(initializer, map)?)
             } else {
                 None
             };
@@ -272,6 +313,7 @@ impl IdentifierResolver {
                 initializer,
                 ty: declaration.ty.clone(),
                 storage_class: declaration.storage_class,
+                is_const: declaration.is_const,
             })
         }
     }
@@ -295,13 +337,117 @@ impl IdentifierResolver {
         self.handle_top_level_function_declaration(declaration, map)
     }
 
+    /// Numbers the enumerators, each one after the previous unless it has a value of its own.
+    fn handle_enum_declaration(
+        declaration: &EnumDeclaration,
+        map: &mut IdentifierMap,
+    ) -> Result<EnumDeclaration, String> {
+        if let Some(tag) = &declaration.tag {
+            match (&declaration.enumerators, map.tags.get(tag)) {
+                (Some(_), Some(true)) => return Err(format!("Redefinition of enum {tag}")),
+                (Some(_), _) => {
+                    map.tags.insert(tag.clone(), true);
+                }
+                (None, None) => return Err(format!("Use of undeclared enum {tag}")),
+                (None, Some(_)) => {}
+            }
+        }
+
+        let Some(declared_enumerators) = &declaration.enumerators else {
+            return Ok(declaration.clone());
+        };
+
+        let mut enumerators = Vec::new();
+        let mut next = Some(0);
+
+        for enumerator in declared_enumerators {
+            if let Some(entry) = map.get(&enumerator.name) {
+                if entry.from_current_scope {
+                    return Err(format!(
+                        "Duplicate declaration of identifier {}",
+                        enumerator.name
+                    ));
+                }
+            }
+
+            let value = match &enumerator.value {
+                Some(expr) => {
+                    let expr = Self::handle_expression(expr, map)?;
+
+                    if let Expression::Constant {
+                        c: Constant::ConstantDouble(_),
+                        ..
+                    } = expr
+                    {
+                        return Err(format!(
+                            "Value of enumerator {} is not an integer",
+                            enumerator.name
+                        ));
+                    }
+
+                    let Some(c) = fold_integer_constant(&expr) else {
+                        return Err(format!(
+                            "Non-constant value of enumerator {}",
+                            enumerator.name
+                        ));
+                    };
+
+                    Self::enumerator_value(&c)
+                }
+                None => next,
+            };
+
+            let Some(value) = value else {
+                return Err(format!(
+                    "Value of enumerator {} is out of range of int",
+                    enumerator.name
+                ));
+            };
+            next = value.checked_add(1);
+
+            map.insert(
+                enumerator.name.clone(),
+                IdentifierMapEntry {
+                    new_name: enumerator.name.clone(),
+                    from_current_scope: true,
+                    has_linkage: false,
+                    constant: Some(value),
+                },
+            );
+
+            enumerators.push(Enumerator {
+                name: enumerator.name.clone(),
+                value: Some(Expression::Constant {
+                    c: Constant::ConstantInt(value),
+                    ty: None,
+                }),
+            });
+        }
+
+        Ok(EnumDeclaration {
+            tag: declaration.tag.clone(),
+            enumerators: Some(enumerators),
+        })
+    }
+
+    /// The value of `c`, if it fits in an int.
+    fn enumerator_value(c: &Constant) -> Option<i32> {
+        match c {
+            Constant::ConstantInt(n) => Some(*n),
+            Constant::ConstantLong(n) => i32::try_from(*n).ok(),
+            Constant::ConstantUInt(n) => i32::try_from(*n).ok(),
+            Constant::ConstantULong(n) => i32::try_from(*n).ok(),
+            Constant::ConstantDouble(_) => None,
+        }
+    }
+
     fn handle_statement(
         &mut self,
         statement: &Statement,
         map: &IdentifierMap,
     ) -> Result<Statement, String> {
         Ok(match statement {
-            Statement::Return(expr) => Statement::Return(Self::handle_expression(expr, map)?),
+            Statement::Return(expr) => Statement::Return(Self::handle_opt_expression(expr, map)?),
             Statement::Expression(expr) => {
                 Statement::Expression(Self::handle_expression(expr, map)?)
             }
@@ -353,13 +499,16 @@ impl IdentifierResolver {
                 let mut inner_map = map.clone_rescoped();
 
                 let initializer = match initializer {
-                    Some(ForInitializer::VariableDeclaration(declaration)) => {
-                        Some(ForInitializer::VariableDeclaration(
-                            self.handle_block_level_variable_declaration(
+                    Some(ForInitializer::VariableDeclarations(declarations)) => {
+                        let mut resolved = Vec::new();
+                        for declaration in declarations {
+                            resolved.push(self.handle_block_level_variable_declaration(
                                 declaration,
                                 &mut inner_map,
-                            )?,
-                        ))
+                            )?);
+                        }
+
+                        Some(ForInitializer::VariableDeclarations(resolved))
                     }
                     Some(ForInitializer::Expression(expr)) => Some(ForInitializer::Expression(
                         Self::handle_expression(expr, map)?,
@@ -395,7 +544,7 @@ impl IdentifierResolver {
                 body,
                 label,
             } => Statement::Case {
-                expression: Self::handle_expression(expression, map)?,
+                expression: Self::handle_case_expression(expression, map)?,
                 body: Box::new(self.handle_statement(body, map)?),
                 label: label.clone(),
             },
@@ -410,11 +559,47 @@ impl IdentifierResolver {
         })
     }
 
+    /// Folds the label to a constant where possible, since enumerators and arithmetic on
+    /// constants are allowed there.
+    fn handle_case_expression(
+        expression: &Expression,
+        map: &IdentifierMap,
+    ) -> Result<Expression, String> {
+        let expression = Self::handle_expression(expression, map)?;
+
+        Ok(match fold_integer_constant(&expression) {
+            Some(c) => Expression::Constant { c, ty: None },
+            None => expression,
+        })
+    }
+
+    fn handle_initializer(
+        initializer: &Initializer,
+        map: &IdentifierMap,
+    ) -> Result<Initializer, String> {
+        Ok(match initializer {
+            Initializer::Single(expr) => Initializer::Single(Self::handle_expression(expr, map)?),
+            Initializer::Compound(initializers) => Initializer::Compound(
+                initializers
+                    .iter()
+                    .map(|initializer| Self::handle_initializer(initializer, map))
+                    .collect::<Result<_, _>>()?,
+            ),
+        })
+    }
+
     fn handle_expression(expr: &Expression, map: &IdentifierMap) -> Result<Expression, String> {
         Ok(match expr {
             Expression::Constant { .. } => expr.clone(),
             Expression::Variable { v, ty } => {
                 if let Some(entry) = map.get(&v.identifier) {
+                    if let Some(value) = entry.constant {
+                        return Ok(Expression::Constant {
+                            c: Constant::ConstantInt(value),
+                            ty: ty.clone(),
+                        });
+                    }
+
                     Expression::Variable {
                         v: Variable {
                             identifier: entry.new_name.clone(),
@@ -426,18 +611,21 @@ impl IdentifierResolver {
                 }
             }
             Expression::Unary { op, expr, ty } => {
+                // after resolving, so enum constants aren't lvalues
+                let expr = Self::handle_expression(expr, map)?;
+
                 if let UnaryOperator::PrefixIncrement
                 | UnaryOperator::PrefixDecrement
                 | UnaryOperator::PostfixIncrement
                 | UnaryOperator::PostfixDecrement = *op
                 {
-                    let Expression::Variable { .. } = **expr else {
+                    if !expr.is_lvalue() {
                         return Err("Invalid lvalue in increment/decrement".to_string());
-                    };
+                    }
                 }
                 Expression::Unary {
                     op: *op,
-                    expr: Box::new(Self::handle_expression(expr, map)?),
+                    expr: Box::new(expr),
                     ty: ty.clone(),
                 }
             }
@@ -448,12 +636,14 @@ impl IdentifierResolver {
                 ty: ty.clone(),
             },
             Expression::Assignment { op, lhs, rhs, ty } => {
-                let Expression::Variable { .. } = **lhs else {
+                let lhs = Self::handle_expression(lhs, map)?;
+
+                if !lhs.is_lvalue() {
                     return Err("Invalid lvalue in assignment".to_string());
-                };
+                }
                 Expression::Assignment {
                     op: *op,
-                    lhs: Box::new(Self::handle_expression(lhs, map)?),
+                    lhs: Box::new(lhs),
                     rhs: Box::new(Self::handle_expression(rhs, map)?),
                     ty: ty.clone(),
                 }
@@ -469,12 +659,39 @@ impl IdentifierResolver {
                 else_expr: Box::new(Self::handle_expression(else_expr, map)?),
                 ty: ty.clone(),
             },
+            Expression::SizeOf { expr, ty } => Expression::SizeOf {
+                expr: Box::new(Self::handle_expression(expr, map)?),
+                ty: ty.clone(),
+            },
+            Expression::SizeOfType { .. } => expr.clone(),
+            Expression::AddrOf { expr, ty } => Expression::AddrOf {
+                expr: Box::new(Self::handle_expression(expr, map)?),
+                ty: ty.clone(),
+            },
+            Expression::Dereference { expr, ty } => Expression::Dereference {
+                expr: Box::new(Self::handle_expression(expr, map)?),
+                ty: ty.clone(),
+            },
+            Expression::Subscript { expr, index, ty } => Expression::Subscript {
+                expr: Box::new(Self::handle_expression(expr, map)?),
+                index: Box::new(Self::handle_expression(index, map)?),
+                ty: ty.clone(),
+            },
+            Expression::Comma { lhs, rhs, ty } => Expression::Comma {
+                lhs: Box::new(Self::handle_expression(lhs, map)?),
+                rhs: Box::new(Self::handle_expression(rhs, map)?),
+                ty: ty.clone(),
+            },
             Expression::FunctionCall {
                 function,
                 arguments,
                 ty,
             } => {
                 if let Some(entry) = map.get(&function.identifier) {
+                    if entry.constant.is_some() {
+                        return Err("Called object is not a function".to_string());
+                    }
+
                     let new_name = entry.new_name.clone();
                     let mut new_arguments = Vec::new();
 
@@ -493,6 +710,18 @@ impl IdentifierResolver {
                     return Err(format!("Function {} not declared", function.identifier));
                 }
             }
+            Expression::IndirectCall {
+                callee,
+                arguments,
+                ty,
+            } => Expression::IndirectCall {
+                callee: Box::new(Self::handle_expression(callee, map)?),
+                arguments: arguments
+                    .iter()
+                    .map(|argument| Self::handle_expression(argument, map))
+                    .collect::<Result<_, _>>()?,
+                ty: ty.clone(),
+            },
             Expression::Cast {
                 target_ty,
                 expr,
@@ -516,3 +745,173 @@ impl IdentifierResolver {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    fn analyze(source: &str) -> Result<(), String> {
+        let tokens = crate::compiler::lexer::tokenize(source).unwrap();
+        let ast = crate::compiler::parser::parse(&tokens, false).unwrap();
+        crate::compiler::semantic::analyze(&ast, false)
+            .map(|_| ())
+            .map_err(|e| e.message().to_string())
+    }
+
+    #[test]
+    fn test_enum_errors() {
+        let test_cases = [
+            ("enum E { A, A };", "Duplicate declaration of identifier A"),
+            (
+                "enum E { A }; int A;",
+                "Duplicate declaration of identifier A",
+            ),
+            (
+                "enum E { A }; int A(void);",
+                "Duplicate declaration of identifier A",
+            ),
+            (
+                "int main(void) { int x = 1; enum { A = x }; return 0; }",
+                "Non-constant value of enumerator A",
+            ),
+            (
+                "enum { A = 2147483647, B };",
+                "Value of enumerator B is out of range of int",
+            ),
+            (
+                "enum { A = 2147483648L };",
+                "Value of enumerator A is out of range of int",
+            ),
+            (
+                "enum { A = 1.5 };",
+                "Value of enumerator A is not an integer",
+            ),
+            (
+                "enum { A = -2147483649L };",
+                "Value of enumerator A is out of range of int",
+            ),
+            // negating an unsigned constant wraps around
+            (
+                "enum { A = -1u };",
+                "Value of enumerator A is out of range of int",
+            ),
+            (
+                "int main(void) { int x = 1; enum { A = -x }; return 0; }",
+                "Non-constant value of enumerator A",
+            ),
+            (
+                "enum { A = 2147483647 + 1L };",
+                "Value of enumerator A is out of range of int",
+            ),
+            ("enum { A = 1 / 0 };", "Non-constant value of enumerator A"),
+            ("enum E { A }; enum E { B };", "Redefinition of enum E"),
+            ("enum X y;", "Use of undeclared enum X"),
+            (
+                "int main(void) { enum E { A }; return 0; } enum E e;",
+                "Use of undeclared enum E",
+            ),
+            (
+                "enum { A }; int main(void) { A = 1; return 0; }",
+                "Invalid lvalue in assignment",
+            ),
+            (
+                "enum { A }; int main(void) { return A++; }",
+                "Invalid lvalue in increment/decrement",
+            ),
+            (
+                "enum { A }; int main(void) { return A(); }",
+                "Called object is not a function",
+            ),
+        ];
+
+        for (source, error) in test_cases {
+            assert_eq!(analyze(source), Err(error.to_string()), "{source}");
+        }
+
+        // later enumerators can use earlier ones, and inner scopes can shadow them
+        assert_eq!(
+            analyze(
+                "enum { A = 2147483646, B, C = B }; int x = C;
+int main(void) { int A = B; { enum { B = 1 }; return A + B; } }"
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            analyze("enum { A = -1, B, C = -2147483648L, D = -A };"),
+            Ok(())
+        );
+        assert_eq!(
+            analyze("enum { A = 1 + 1, B = -(-1), C = A * 4 - ~B, D = A < B ? 1u : 2 };"),
+            Ok(())
+        );
+        // an inner scope can define a tag again
+        assert_eq!(
+            analyze("enum E { A }; enum E e; int main(void) { enum E { B } e = B; return e; }"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_increment_decrement_lvalues() {
+        let test_cases = [
+            "int main(void) { return 1++; }",
+            "int main(void) { int a = 1, b = 2; return (a + b)--; }",
+            "int f(void); int main(void) { return f()++; }",
+            "int main(void) { return --(1 + 2); }",
+            // only the innermost operand is invalid
+            "int main(void) { int a = 0; return a + -(a++ * 2++); }",
+        ];
+
+        for source in test_cases {
+            assert_eq!(
+                analyze(source),
+                Err("Invalid lvalue in increment/decrement".to_string()),
+                "{source}"
+            );
+        }
+
+        assert_eq!(
+            analyze(
+                "int main(void) {
+    int a = 1, *p = &a, arr[2] = {1, 2};
+    a++;
+    --a;
+    (*p)++;
+    ++p[0];
+    arr[1]--;
+    return a + arr[1];
+}"
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_functions_defined_after_their_use() {
+        // like in C99, a function has to be declared before it's called
+        assert_eq!(
+            analyze(
+                "int main(void) { return helper(); }
+int helper(void) { return 1; }"
+            ),
+            Err("Function helper not declared".to_string())
+        );
+
+        assert_eq!(
+            analyze(
+                "int helper(void);
+int main(void) { return helper(); }
+int helper(void) { return 1; }"
+            ),
+            Ok(())
+        );
+
+        // a declaration in a block only covers that block
+        assert_eq!(
+            analyze(
+                "int main(void) { int helper(void); return helper(); }
+int other(void) { return helper(); }
+int helper(void) { return 1; }"
+            ),
+            Err("Function helper not declared".to_string())
+        );
+    }
+}
@@ -289,3 +289,121 @@ impl LabelResolver {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_goto_into_and_out_of_switch() {
+        let source = "int f(int x) {
+    goto inside;
+    switch (x) {
+        case 1:
+        inside:
+            x = 2;
+            goto done;
+        default:
+            x = 3;
+    }
+done:
+    return x;
+}";
+        let tokens = crate::compiler::lexer::tokenize(source).unwrap();
+        let ast = crate::compiler::parser::parse(&tokens, false).unwrap();
+        let program = LabelResolver::analyze(&ast).unwrap();
+
+        let Declaration::Function(FunctionDeclaration {
+            body: Some(body), ..
+        }) = &program.declarations[0]
+        else {
+            panic!("expected a function definition");
+        };
+
+        let BlockItem::Statement(Statement::Goto(inside)) = &body.items[0] else {
+            panic!("expected a goto");
+        };
+        let BlockItem::Statement(Statement::Switch { body: switch, .. }) = &body.items[1] else {
+            panic!("expected a switch");
+        };
+        let Statement::Compound(switch) = &**switch else {
+            panic!("expected a compound switch body");
+        };
+        let BlockItem::Statement(Statement::Case { body: case, .. }) = &switch.items[0] else {
+            panic!("expected a case");
+        };
+
+        assert!(inside.identifier.starts_with(SEMANTIC_LABEL_PREFIX));
+        assert!(matches!(&**case, Statement::Labeled(label, _) if label == inside));
+
+        let BlockItem::Statement(Statement::Goto(done)) = &switch.items[1] else {
+            panic!("expected a goto");
+        };
+        assert!(
+            matches!(&body.items[2], BlockItem::Statement(Statement::Labeled(label, _)) if label == done)
+        );
+    }
+
+    fn analyze(source: &str) -> Result<Program, String> {
+        let tokens = crate::compiler::lexer::tokenize(source).unwrap();
+        let ast = crate::compiler::parser::parse(&tokens, false).unwrap();
+        LabelResolver::analyze(&ast)
+    }
+
+    #[test]
+    fn test_forward_goto() {
+        let program = analyze(
+            "int f(int x) {
+    goto end;
+    x = 1;
+    if (x) {
+        while (x) {
+            goto nested;
+        }
+    } else {
+        nested: x = 2;
+    }
+end:
+    return x;
+}",
+        )
+        .unwrap();
+
+        let Declaration::Function(FunctionDeclaration {
+            body: Some(body), ..
+        }) = &program.declarations[0]
+        else {
+            panic!("expected a function definition");
+        };
+
+        let BlockItem::Statement(Statement::Goto(end)) = &body.items[0] else {
+            panic!("expected a goto");
+        };
+        assert!(
+            matches!(&body.items[3], BlockItem::Statement(Statement::Labeled(label, _)) if label == end)
+        );
+    }
+
+    #[test]
+    fn test_label_errors() {
+        let test_cases = [
+            (
+                "int f(void) { goto missing; return 0; }",
+                "Label missing not declared",
+            ),
+            (
+                "int f(void) { a: ; { a: ; } return 0; }",
+                "Label a already declared",
+            ),
+            // labels are local to their function
+            (
+                "int f(void) { a: return 0; } int g(void) { goto a; }",
+                "Label a not declared",
+            ),
+        ];
+
+        for (source, message) in test_cases {
+            assert_eq!(analyze(source).unwrap_err(), message, "{source}");
+        }
+    }
+}
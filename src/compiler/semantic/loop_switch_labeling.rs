@@ -215,3 +215,88 @@ impl LoopSwitchLabeler {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::compiler::ast::FunctionDeclaration;
+
+    fn analyze(source: &str) -> Result<Program, String> {
+        let tokens = crate::compiler::lexer::tokenize(source).unwrap();
+        let ast = crate::compiler::parser::parse(&tokens, false).unwrap();
+        LoopSwitchLabeler::analyze(&ast)
+    }
+
+    #[test]
+    fn test_break_and_continue_errors() {
+        let test_cases = [
+            (
+                "int f(int x) { switch (x) { default: continue; } return 0; }",
+                "Continue statement outside of loop",
+            ),
+            (
+                "int f(void) { continue; }",
+                "Continue statement outside of loop",
+            ),
+            (
+                "int f(void) { break; }",
+                "Break statement outside of loop or switch",
+            ),
+        ];
+
+        for (source, message) in test_cases {
+            assert_eq!(analyze(source).unwrap_err(), message, "{source}");
+        }
+
+        assert!(analyze("int f(int x) { switch (x) { default: break; } return 0; }").is_ok());
+    }
+
+    #[test]
+    fn test_continue_inside_switch_targets_loop() {
+        let program = analyze(
+            "int f(int x) { while (x) { switch (x) { default: continue; } break; } return 0; }",
+        )
+        .unwrap();
+
+        let Declaration::Function(FunctionDeclaration {
+            body: Some(body), ..
+        }) = &program.declarations[0]
+        else {
+            panic!("expected a function definition");
+        };
+        let BlockItem::Statement(Statement::While {
+            body: loop_body,
+            label: Some(loop_label),
+            ..
+        }) = &body.items[0]
+        else {
+            panic!("expected a labeled while loop");
+        };
+        let Statement::Compound(loop_body) = &**loop_body else {
+            panic!("expected a compound loop body");
+        };
+        let BlockItem::Statement(Statement::Switch {
+            body: switch_body, ..
+        }) = &loop_body.items[0]
+        else {
+            panic!("expected a switch");
+        };
+        let Statement::Compound(switch_body) = &**switch_body else {
+            panic!("expected a compound switch body");
+        };
+        let BlockItem::Statement(Statement::Default { body: default, .. }) = &switch_body.items[0]
+        else {
+            panic!("expected a default case");
+        };
+
+        assert_eq!(**default, Statement::Continue(Some(loop_label.clone())));
+        // the break after the switch belongs to the loop too
+        assert_eq!(
+            loop_body.items[1],
+            BlockItem::Statement(Statement::Break(Some(LoopOrSwitchLabel::Loop(
+                loop_label.clone()
+            ))))
+        );
+    }
+}
@@ -1,18 +1,25 @@
-use crate::compiler::{ast::Program, symbols::SymbolTable};
+use crate::compiler::{ast::Program, error::CompilerError, symbols::SymbolTable};
 
+mod constant_expression;
 mod identifier_resolution;
 mod label_resolution;
 mod loop_switch_labeling;
+mod return_check;
 mod switch_case_collection;
 mod type_check;
 
 use identifier_resolution::IdentifierResolver;
 use label_resolution::LabelResolver;
 use loop_switch_labeling::LoopSwitchLabeler;
+use return_check::ReturnChecker;
 use switch_case_collection::SwitchCaseCollector;
 use type_check::TypeChecker;
 
-pub fn analyze(program: &Program) -> Result<(Program, SymbolTable), String> {
+/// Also returns the warnings about the program. With `strict`, the first one is an error instead.
+pub fn analyze(
+    program: &Program,
+    strict: bool,
+) -> Result<(Program, SymbolTable, Vec<String>), CompilerError> {
     IdentifierResolver::analyze(program)
         .and_then(|program| LabelResolver::analyze(&program))
         .and_then(|program| LoopSwitchLabeler::analyze(&program))
@@ -20,4 +27,16 @@ pub fn analyze(program: &Program) -> Result<(Program, SymbolTable), String> {
         .and_then(|(program, symbols)| {
             SwitchCaseCollector::analyze(&program).map(|program| (program, symbols))
         })
+        .and_then(|(program, symbols)| {
+            let warnings = ReturnChecker::analyze(&program);
+
+            match warnings.first() {
+                Some(warning) if strict => Err(warning.clone()),
+                _ => Ok((program, symbols, warnings)),
+            }
+        })
+        .map_err(|message| CompilerError::Semantic {
+            message,
+            span: None,
+        })
 }
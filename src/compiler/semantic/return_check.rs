@@ -0,0 +1,246 @@
+use crate::compiler::ast::{
+    Block, BlockItem, Constant, Declaration, Expression, FunctionDeclaration, Program, Statement,
+    Type,
+};
+
+/// Finds functions that can reach the end of their body without returning a value.
+///
+/// The analysis is conservative: a path counts as reachable unless it obviously isn't, so some
+/// functions that always return are still reported.
+pub struct ReturnChecker;
+
+impl ReturnChecker {
+    pub fn analyze(program: &Program) -> Vec<String> {
+        program
+            .declarations
+            .iter()
+            .filter_map(|declaration| match declaration {
+                Declaration::Function(fd) => Self::check_function_declaration(fd),
+                Declaration::Variable(_) | Declaration::Enum(_) => None,
+            })
+            .collect()
+    }
+
+    fn check_function_declaration(fd: &FunctionDeclaration) -> Option<String> {
+        let body = fd.body.as_ref()?;
+
+        if let Type::Function { return_type, .. } = &fd.ty {
+            if **return_type == Type::Void {
+                return None;
+            }
+        }
+
+        // falling off the end of main returns 0
+        if fd.function.identifier == "main" || !Self::block_falls_through(body) {
+            return None;
+        }
+
+        Some(format!(
+            "Control may reach the end of non-void function {}",
+            fd.function.identifier
+        ))
+    }
+
+    fn block_falls_through(block: &Block) -> bool {
+        let mut reachable = true;
+
+        for item in &block.items {
+            if let BlockItem::Statement(statement) = item {
+                // a label can be jumped to even if the code before it never finishes
+                reachable = (reachable || Self::contains_label(statement))
+                    && Self::falls_through(statement);
+            }
+        }
+
+        reachable
+    }
+
+    /// Whether control can continue with the statement after this one.
+    fn falls_through(statement: &Statement) -> bool {
+        match statement {
+            Statement::Return(_)
+            | Statement::Goto(_)
+            | Statement::Break(_)
+            | Statement::Continue(_) => false,
+            Statement::Expression(_) | Statement::Null => true,
+            Statement::If {
+                then_branch,
+                else_branch,
+                ..
+            } => match else_branch {
+                Some(else_branch) => {
+                    Self::falls_through(then_branch) || Self::falls_through(else_branch)
+                }
+                None => true,
+            },
+            Statement::Labeled(_, body)
+            | Statement::Case { body, .. }
+            | Statement::Default { body, .. } => Self::falls_through(body),
+            Statement::Compound(block) => Self::block_falls_through(block),
+            Statement::While {
+                condition, body, ..
+            } => !Self::is_always_true(condition) || Self::contains_break(body),
+            Statement::DoWhile {
+                body, condition, ..
+            } => {
+                // the condition is only checked if the body gets to the end of an iteration
+                let reaches_condition = Self::falls_through(body) || Self::contains_continue(body);
+                (reaches_condition && !Self::is_always_true(condition))
+                    || Self::contains_break(body)
+            }
+            Statement::For {
+                condition, body, ..
+            } => {
+                let infinite = condition.as_ref().is_none_or(Self::is_always_true);
+                !infinite || Self::contains_break(body)
+            }
+            Statement::Switch { body, .. } => {
+                !Self::contains_default(body)
+                    || Self::falls_through(body)
+                    || Self::contains_break(body)
+            }
+        }
+    }
+
+    fn is_always_true(condition: &Expression) -> bool {
+        match condition {
+            Expression::Constant { c, .. } => match c {
+                Constant::ConstantInt(n) => *n != 0,
+                Constant::ConstantLong(n) => *n != 0,
+                Constant::ConstantUInt(n) => *n != 0,
+                Constant::ConstantULong(n) => *n != 0,
+                Constant::ConstantDouble(d) => *d != 0.0,
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether `statement` has a `break` for the enclosing loop or switch.
+    fn contains_break(statement: &Statement) -> bool {
+        Self::any_statement(statement, false, &|statement| {
+            matches!(statement, Statement::Break(_))
+        })
+    }
+
+    /// Whether `statement` has a `continue`. Those of nested loops count too, which can only cause
+    /// extra warnings.
+    fn contains_continue(statement: &Statement) -> bool {
+        Self::any_statement(statement, true, &|statement| {
+            matches!(statement, Statement::Continue(_))
+        })
+    }
+
+    fn contains_label(statement: &Statement) -> bool {
+        Self::any_statement(statement, true, &|statement| {
+            matches!(
+                statement,
+                Statement::Labeled(..) | Statement::Case { .. } | Statement::Default { .. }
+            )
+        })
+    }
+
+    /// Whether the `default` of the switch with this body is in it.
+    fn contains_default(statement: &Statement) -> bool {
+        Self::any_statement(statement, false, &|statement| {
+            matches!(statement, Statement::Default { .. })
+        })
+    }
+
+    /// Whether `predicate` holds for `statement` or any statement inside it, optionally skipping
+    /// the bodies of nested loops and switches.
+    fn any_statement(
+        statement: &Statement,
+        enter_loops: bool,
+        predicate: &dyn Fn(&Statement) -> bool,
+    ) -> bool {
+        if predicate(statement) {
+            return true;
+        }
+
+        match statement {
+            Statement::If {
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                Self::any_statement(then_branch, enter_loops, predicate)
+                    || else_branch
+                        .as_ref()
+                        .is_some_and(|s| Self::any_statement(s, enter_loops, predicate))
+            }
+            Statement::Labeled(_, body)
+            | Statement::Case { body, .. }
+            | Statement::Default { body, .. } => Self::any_statement(body, enter_loops, predicate),
+            Statement::Compound(block) => block.items.iter().any(|item| match item {
+                BlockItem::Statement(s) => Self::any_statement(s, enter_loops, predicate),
+                BlockItem::Declaration(_) => false,
+            }),
+            Statement::While { body, .. }
+            | Statement::DoWhile { body, .. }
+            | Statement::For { body, .. }
+            | Statement::Switch { body, .. } => {
+                enter_loops && Self::any_statement(body, enter_loops, predicate)
+            }
+            Statement::Return(_)
+            | Statement::Expression(_)
+            | Statement::Goto(_)
+            | Statement::Break(_)
+            | Statement::Continue(_)
+            | Statement::Null => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn warnings(source: &str) -> Vec<String> {
+        let tokens = crate::compiler::lexer::tokenize(source).unwrap();
+        let ast = crate::compiler::parser::parse(&tokens, false).unwrap();
+        ReturnChecker::analyze(&ast)
+    }
+
+    #[test]
+    fn test_all_paths_return() {
+        let sources = [
+            "int f(int x) { if (x) return 1; else return 2; }",
+            "int f(int x) { while (1) { if (x) return x; } }",
+            "int f(int x) { for (;;) { x++; continue; } }",
+            "int f(int x) { switch (x) { case 1: return 1; default: return 0; } }",
+            "int f(int x) { goto end; x = 1; end: return x; }",
+            "int f(void) { { return 1; } }",
+            "int main(void) { }",
+            "void f(int x) { if (x) return; }",
+            "int f(int x) { do { return 1; } while (x); }",
+            "int f(int x) { do { x--; } while (1); }",
+        ];
+
+        for source in sources {
+            assert_eq!(warnings(source), Vec::<String>::new(), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_missing_return() {
+        let sources = [
+            "int f(int x) { if (x) return 1; }",
+            "int f(int x) { while (x) { return x; } }",
+            "int f(int x) { while (1) { if (x) break; } }",
+            "int f(int x) { switch (x) { case 1: return 1; } }",
+            "int f(int x) { switch (x) { default: break; } return 0; x = 1; lbl: ; }",
+            "int f(int x) { return 1; lbl: x = 2; }",
+            "int f(int x) { do { x--; } while (x); }",
+            "int f(int x) { do { if (x) continue; return 1; } while (x); }",
+            "int f(int x) { do { if (x) break; return 1; } while (1); }",
+        ];
+
+        for source in sources {
+            assert_eq!(
+                warnings(source),
+                vec!["Control may reach the end of non-void function f".to_string()],
+                "{source}"
+            );
+        }
+    }
+}
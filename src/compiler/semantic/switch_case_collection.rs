@@ -27,10 +27,11 @@ impl SwitchCaseCollector {
                 if let Some(body) = &fd.body {
                     let (new_body, cases) = collector.handle_block(body)?;
 
-                    if cases.is_some() {
-                        return Err(
-                            "Unexpected switch case outside of switch statement".to_string()
-                        );
+                    if let Some(cases) = cases {
+                        return Err(match cases.cases.first() {
+                            Some((c, _)) => format!("Case {c} outside of switch statement"),
+                            None => "Default case outside of switch statement".to_string(),
+                        });
                     }
 
                     fd.body = Some(new_body);
@@ -51,6 +52,8 @@ impl SwitchCaseCollector {
         SwitchCaseLabel { identifier: name }
     }
 
+    /// The type checker has already converted every case value to the type of its switch
+    /// expression, so values that are equal after that conversion count as duplicates.
     fn merge_and_verify_switch_cases(
         lhs: &Option<SwitchCases>,
         rhs: &Option<SwitchCases>,
@@ -139,13 +142,19 @@ impl SwitchCaseCollector {
                     return Err("Non-constant expression in switch case".to_string());
                 };
 
-                let case_label = self.fresh_switch_case_label(Some(&format!(
-                    "value.{}",
-                    match c {
-                        Constant::ConstantInt(n) => n.to_string(),
-                        Constant::ConstantLong(n) => n.to_string(),
+                let value = match c {
+                    Constant::ConstantInt(n) => n.to_string(),
+                    Constant::ConstantLong(n) => n.to_string(),
+                    Constant::ConstantUInt(n) => n.to_string(),
+                    Constant::ConstantULong(n) => n.to_string(),
+                    Constant::ConstantDouble(_) => {
+                        unreachable!("case values are converted to the switch expression's type")
                     }
-                )));
+                };
+
+                // labels can't contain a minus sign
+                let value = value.replace('-', "m");
+                let case_label = self.fresh_switch_case_label(Some(&format!("value.{value}")));
                 let (new_body, inner_cases) = self.handle_statement(body)?;
 
                 let merged = Self::merge_and_verify_switch_cases(
@@ -289,3 +298,81 @@ impl SwitchCaseCollector {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    fn analyze(source: &str) -> Result<(), String> {
+        let tokens = crate::compiler::lexer::tokenize(source).unwrap();
+        let ast = crate::compiler::parser::parse(&tokens, false).unwrap();
+        crate::compiler::semantic::analyze(&ast, false)
+            .map(|_| ())
+            .map_err(|e| e.message().to_string())
+    }
+
+    #[test]
+    fn test_duplicate_cases_after_conversion() {
+        let source = "int main(void) {
+    int x = 0;
+    switch (x) {
+        case 4294967296L: return 1;
+        case 0: return 2;
+    }
+    return 3;
+}";
+        assert_eq!(
+            analyze(source),
+            Err("Duplicate case value in switch statement".to_string())
+        );
+
+        // the values differ as longs
+        let source = source.replace("int x", "long x");
+        assert_eq!(analyze(&source), Ok(()));
+    }
+
+    #[test]
+    fn test_duplicate_cases_in_nested_statements() {
+        let source = "int main(void) {
+    int x = 1;
+    switch (x) {
+        case 1: return 1;
+        default:
+            if (x) { while (x) { case 1: return 2; } }
+    }
+    return 0;
+}";
+        assert_eq!(
+            analyze(source),
+            Err("Duplicate case value in switch statement".to_string())
+        );
+
+        // the inner cases belong to the inner switch
+        let source = "int main(void) {
+    int x = 1;
+    switch (x) {
+        case 1:
+            switch (x) {
+                case 1: return 5;
+                default: return 6;
+            }
+        default: return 7;
+    }
+}";
+        assert_eq!(analyze(source), Ok(()));
+    }
+
+    #[test]
+    fn test_cases_outside_of_switch() {
+        assert_eq!(
+            analyze("int main(void) { case 1: ; return 0; }"),
+            Err("Case 1 outside of switch statement".to_string())
+        );
+        assert_eq!(
+            analyze("int main(void) { while (1) { case 4294967296L: break; } return 0; }"),
+            Err("Case 4294967296L outside of switch statement".to_string())
+        );
+        assert_eq!(
+            analyze("int main(void) { default: ; return 0; }"),
+            Err("Default case outside of switch statement".to_string())
+        );
+    }
+}
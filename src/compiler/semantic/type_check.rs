@@ -1,8 +1,10 @@
+use std::collections::HashSet;
+
 use crate::compiler::{
     ast::{
-        BinaryOperator, Block, BlockItem, Constant, Declaration, Expression, ForInitializer,
-        FunctionDeclaration, Program, Statement, StorageClass, Type, UnaryOperator::Not,
-        VariableDeclaration,
+        AssignmentOperator, BinaryOperator, Block, BlockItem, Constant, Declaration,
+        EnumDeclaration, Enumerator, Expression, ForInitializer, FunctionDeclaration, Initializer,
+        Program, Statement, StorageClass, Type, UnaryOperator, Variable, VariableDeclaration,
     },
     constant_conversion::convert_constant_to_type,
     symbols::{Symbol, SymbolAttributes, SymbolInitialValue, SymbolStaticInitial, SymbolTable},
@@ -10,6 +12,8 @@ use crate::compiler::{
 
 pub struct TypeChecker {
     symbols: SymbolTable,
+    /// The resolved names of the variables declared `const`.
+    const_variables: HashSet<String>,
 }
 
 impl TypeChecker {
@@ -24,14 +28,33 @@ impl TypeChecker {
     fn new() -> Self {
         Self {
             symbols: SymbolTable::new(),
+            const_variables: HashSet::new(),
         }
     }
 
+    /// `_Bool` and character operands are promoted to `int` before any arithmetic.
+    fn promote(&self, expr: &Expression) -> Expression {
+        self.convert_to_type(expr, &promoted_type(&expr.ty().unwrap()))
+    }
+
     fn get_common_type(&self, ty1: &Type, ty2: &Type) -> Type {
+        let (ty1, ty2) = (&promoted_type(ty1), &promoted_type(ty2));
+
         if ty1 == ty2 {
             ty1.clone()
+        } else if *ty1 == Type::Double || *ty2 == Type::Double {
+            Type::Double
+        } else if ty1.size() == ty2.size() {
+            // same rank, the unsigned type wins
+            if ty1.is_signed() {
+                ty2.clone()
+            } else {
+                ty1.clone()
+            }
+        } else if ty1.size() > ty2.size() {
+            ty1.clone()
         } else {
-            Type::Long
+            ty2.clone()
         }
     }
 
@@ -47,10 +70,142 @@ impl TypeChecker {
         }
     }
 
-    fn convert_constant_to_static_initial(&self, c: &Constant, ty: &Type) -> SymbolStaticInitial {
-        match convert_constant_to_type(c, ty) {
-            Constant::ConstantInt(n) => SymbolStaticInitial::Int(n),
-            Constant::ConstantLong(n) => SymbolStaticInitial::Long(n),
+    /// Like `convert_to_type`, but only allows the implicit conversions of an assignment.
+    fn convert_by_assignment(&self, expr: &Expression, ty: &Type) -> Result<Expression, String> {
+        let expr_ty = expr.ty().unwrap();
+
+        if expr_ty == *ty
+            || (expr_ty.is_arithmetic() && ty.is_arithmetic())
+            || (matches!(ty, Type::Pointer(_)) && is_null_pointer_constant(expr))
+            || (matches!(expr_ty, Type::Pointer(_)) && *ty == Type::Bool)
+        {
+            Ok(self.convert_to_type(expr, ty))
+        } else {
+            Err("Cannot convert type for assignment".to_string())
+        }
+    }
+
+    fn get_common_pointer_type(&self, lhs: &Expression, rhs: &Expression) -> Result<Type, String> {
+        let ty_lhs = lhs.ty().unwrap();
+        let ty_rhs = rhs.ty().unwrap();
+
+        if ty_lhs == ty_rhs || is_null_pointer_constant(rhs) {
+            Ok(ty_lhs)
+        } else if is_null_pointer_constant(lhs) {
+            Ok(ty_rhs)
+        } else {
+            Err("Expressions have incompatible types".to_string())
+        }
+    }
+
+    /// Flattens an initializer of a static variable into the values of its scalars.
+    fn convert_static_initializer(
+        &self,
+        initializer: &Initializer,
+        ty: &Type,
+    ) -> Result<Vec<SymbolStaticInitial>, String> {
+        match (ty, initializer) {
+            (Type::Array { element, size }, Initializer::Compound(initializers)) => {
+                if initializers.len() > *size {
+                    return Err("Too many elements in initializer".to_string());
+                }
+
+                let mut initials = Vec::new();
+                for initializer in initializers {
+                    initials.extend(self.convert_static_initializer(initializer, element)?);
+                }
+
+                let missing = (size - initializers.len()) as i64 * element.size().unwrap();
+                if missing > 0 {
+                    initials.push(SymbolStaticInitial::Zero(missing));
+                }
+
+                Ok(initials)
+            }
+            (Type::Array { .. }, Initializer::Single(_)) => {
+                Err("Arrays cannot be initialized with a scalar".to_string())
+            }
+            (_, Initializer::Compound(_)) => {
+                Err("Scalars cannot have a compound initializer".to_string())
+            }
+            (_, Initializer::Single(Expression::Constant { c, ty: _ })) => {
+                Ok(vec![self.convert_constant_to_static_initial(c, ty)?])
+            }
+            (_, Initializer::Single(_)) => Err("Non-constant initializer".to_string()),
+        }
+    }
+
+    fn convert_constant_to_static_initial(
+        &self,
+        c: &Constant,
+        ty: &Type,
+    ) -> Result<SymbolStaticInitial, String> {
+        if matches!(ty, Type::Pointer(_)) && !is_zero_constant(c) {
+            return Err(
+                "Pointers can only be statically initialized to a null pointer".to_string(),
+            );
+        }
+
+        Ok(match (ty, convert_constant_to_type(c, ty)) {
+            (Type::Bool, Constant::ConstantInt(n)) => SymbolStaticInitial::Bool(n != 0),
+            (Type::Char | Type::SChar, Constant::ConstantInt(n)) => {
+                SymbolStaticInitial::Char(n as i8)
+            }
+            (Type::UChar, Constant::ConstantInt(n)) => SymbolStaticInitial::UChar(n as u8),
+            (_, Constant::ConstantInt(n)) => SymbolStaticInitial::Int(n),
+            (_, Constant::ConstantLong(n)) => SymbolStaticInitial::Long(n),
+            (_, Constant::ConstantUInt(n)) => SymbolStaticInitial::UInt(n),
+            (_, Constant::ConstantULong(n)) => SymbolStaticInitial::ULong(n),
+            (_, Constant::ConstantDouble(d)) => SymbolStaticInitial::Double(d),
+        })
+    }
+
+    /// Type checks the initializer of a local variable, filling in zeros for missing elements.
+    fn handle_initializer(
+        &mut self,
+        initializer: &Initializer,
+        ty: &Type,
+    ) -> Result<Initializer, String> {
+        match (ty, initializer) {
+            (Type::Array { element, size }, Initializer::Compound(initializers)) => {
+                if initializers.len() > *size {
+                    return Err("Too many elements in initializer".to_string());
+                }
+
+                let mut typed = Vec::new();
+                for initializer in initializers {
+                    typed.push(self.handle_initializer(initializer, element)?);
+                }
+
+                while typed.len() < *size {
+                    typed.push(Self::zero_initializer(element));
+                }
+
+                Ok(Initializer::Compound(typed))
+            }
+            (Type::Array { .. }, Initializer::Single(_)) => {
+                Err("Arrays cannot be initialized with a scalar".to_string())
+            }
+            (_, Initializer::Compound(_)) => {
+                Err("Scalars cannot have a compound initializer".to_string())
+            }
+            (_, Initializer::Single(expr)) => {
+                let typed = self.handle_expression(expr)?;
+
+                Ok(Initializer::Single(self.convert_by_assignment(&typed, ty)?))
+            }
+        }
+    }
+
+    fn zero_initializer(ty: &Type) -> Initializer {
+        match ty {
+            Type::Array { element, size } => {
+                Initializer::Compound(vec![Self::zero_initializer(element); *size])
+            }
+            _ => Initializer::Single(Expression::Constant {
+                c: convert_constant_to_type(&Constant::ConstantInt(0), ty),
+                ty: Some(ty.clone()),
+            }),
         }
     }
 
@@ -75,6 +230,31 @@ impl TypeChecker {
             Declaration::Function(fd) => {
                 Declaration::Function(self.handle_function_declaration(fd)?)
             }
+            Declaration::Enum(ed) => Declaration::Enum(self.handle_enum_declaration(ed)?),
+        })
+    }
+
+    /// Enumerators are int constants by now, so this only fills in their type.
+    fn handle_enum_declaration(
+        &mut self,
+        declaration: &EnumDeclaration,
+    ) -> Result<EnumDeclaration, String> {
+        let Some(declared_enumerators) = &declaration.enumerators else {
+            return Ok(declaration.clone());
+        };
+
+        let mut enumerators = Vec::new();
+
+        for enumerator in declared_enumerators {
+            enumerators.push(Enumerator {
+                name: enumerator.name.clone(),
+                value: self.handle_opt_expression(&enumerator.value)?,
+            });
+        }
+
+        Ok(EnumDeclaration {
+            tag: declaration.tag.clone(),
+            enumerators: Some(enumerators),
         })
     }
 
@@ -82,9 +262,12 @@ impl TypeChecker {
         &mut self,
         declaration: &VariableDeclaration,
     ) -> Result<VariableDeclaration, String> {
+        validate_type(&declaration.ty)?;
+        self.record_const(declaration);
+
         let mut initial = match &declaration.initializer {
-            Some(Expression::Constant { c, ty: _ }) => SymbolInitialValue::Initial(
-                self.convert_constant_to_static_initial(c, &declaration.ty),
+            Some(initializer) => SymbolInitialValue::Initial(
+                self.convert_static_initializer(initializer, &declaration.ty)?,
             ),
             None => {
                 if declaration.storage_class == Some(StorageClass::Extern) {
@@ -93,7 +276,6 @@ impl TypeChecker {
                     SymbolInitialValue::Tentative
                 }
             }
-            _ => return Err("Non-constant initializer".to_string()),
         };
 
         let mut global = declaration.storage_class != Some(StorageClass::Static);
@@ -109,7 +291,7 @@ impl TypeChecker {
             let SymbolAttributes::Static {
                 initial: entry_initial,
                 global: entry_global,
-            } = entry.attrs
+            } = entry.attrs.clone()
             else {
                 unreachable!()
             };
@@ -134,10 +316,10 @@ impl TypeChecker {
 
                     initial = entry_initial;
                 }
-                SymbolInitialValue::Tentative => {
-                    if !matches!(initial, SymbolInitialValue::Initial(_)) {
-                        initial = SymbolInitialValue::Tentative;
-                    }
+                SymbolInitialValue::Tentative
+                    if !matches!(initial, SymbolInitialValue::Initial(_)) =>
+                {
+                    initial = SymbolInitialValue::Tentative;
                 }
                 _ => {}
             };
@@ -158,10 +340,12 @@ impl TypeChecker {
         &mut self,
         declaration: &FunctionDeclaration,
     ) -> Result<FunctionDeclaration, String> {
+        let ty = Self::adjust_function_type(&declaration.ty)?;
+
         let Type::Function {
             return_type,
             parameters,
-        } = &declaration.ty
+        } = &ty
         else {
             unreachable!()
         };
@@ -171,7 +355,7 @@ impl TypeChecker {
         let mut global = declaration.storage_class != Some(StorageClass::Static);
 
         if let Some(entry) = self.symbols.get(&declaration.function.identifier) {
-            if entry.ty != declaration.ty {
+            if entry.ty != ty {
                 return Err(format!(
                     "Incompatible redeclaration of function {}",
                     declaration.function.identifier
@@ -208,7 +392,7 @@ impl TypeChecker {
         self.symbols.insert(
             declaration.function.identifier.clone(),
             Symbol {
-                ty: declaration.ty.clone(),
+                ty: ty.clone(),
                 attrs: SymbolAttributes::Function {
                     defined: already_defined || has_body,
                     global,
@@ -242,11 +426,39 @@ impl TypeChecker {
             function: declaration.function.clone(),
             parameters: declaration.parameters.clone(),
             body,
-            ty: declaration.ty.clone(),
+            ty: ty.clone(),
             storage_class: declaration.storage_class,
         })
     }
 
+    /// Array parameters are really pointers to the first element, and arrays can't be returned.
+    fn adjust_function_type(ty: &Type) -> Result<Type, String> {
+        let Type::Function {
+            return_type,
+            parameters,
+        } = ty
+        else {
+            unreachable!()
+        };
+
+        if let Type::Array { .. } = **return_type {
+            return Err("Functions cannot return arrays".to_string());
+        }
+
+        validate_type(ty)?;
+
+        Ok(Type::Function {
+            return_type: return_type.clone(),
+            parameters: parameters
+                .iter()
+                .map(|parameter| match parameter {
+                    Type::Array { element, .. } => Type::Pointer(element.clone()),
+                    _ => parameter.clone(),
+                })
+                .collect(),
+        })
+    }
+
     fn handle_block(
         &mut self,
         block: &Block,
@@ -275,20 +487,25 @@ impl TypeChecker {
         enclosing: &EnclosingContext,
     ) -> Result<Statement, String> {
         Ok(match statement {
-            Statement::Return(expr) => {
-                let typed_expr = self.handle_expression(expr)?;
-                let converted_expr =
-                    self.convert_to_type(&typed_expr, &enclosing.function_return_type);
+            Statement::Return(expr) => match (expr, &enclosing.function_return_type) {
+                (None, Type::Void) => Statement::Return(None),
+                (Some(_), Type::Void) => {
+                    return Err("Void function cannot return a value".to_string())
+                }
+                (None, _) => return Err("Non-void function must return a value".to_string()),
+                (Some(expr), return_type) => {
+                    let typed_expr = self.handle_expression(expr)?;
 
-                Statement::Return(converted_expr)
-            }
+                    Statement::Return(Some(self.convert_by_assignment(&typed_expr, return_type)?))
+                }
+            },
             Statement::Expression(expr) => Statement::Expression(self.handle_expression(expr)?),
             Statement::If {
                 condition,
                 then_branch,
                 else_branch,
             } => Statement::If {
-                condition: self.handle_expression(condition)?,
+                condition: self.handle_condition(condition)?,
                 then_branch: Box::new(self.handle_statement(then_branch, enclosing)?),
                 else_branch: if let Some(else_branch) = else_branch {
                     Some(Box::new(self.handle_statement(else_branch, enclosing)?))
@@ -306,7 +523,7 @@ impl TypeChecker {
                 body,
                 label,
             } => Statement::While {
-                condition: self.handle_expression(condition)?,
+                condition: self.handle_condition(condition)?,
                 body: Box::new(self.handle_statement(body, enclosing)?),
                 label: label.clone(),
             },
@@ -316,7 +533,7 @@ impl TypeChecker {
                 label,
             } => Statement::DoWhile {
                 body: Box::new(self.handle_statement(body, enclosing)?),
-                condition: self.handle_expression(condition)?,
+                condition: self.handle_condition(condition)?,
                 label: label.clone(),
             },
             Statement::For {
@@ -327,15 +544,14 @@ impl TypeChecker {
                 label,
             } => {
                 let initializer = match initializer {
-                    Some(ForInitializer::VariableDeclaration(vd)) => {
-                        if vd.storage_class.is_some() {
-                            return Err("For loop variable declaration cannot have storage class"
-                                .to_string());
+                    Some(ForInitializer::VariableDeclarations(declarations)) => {
+                        // the parser rejects storage classes here
+                        let mut typed = Vec::new();
+                        for vd in declarations {
+                            typed.push(self.handle_block_level_variable_declaration(vd)?);
                         }
 
-                        Some(ForInitializer::VariableDeclaration(
-                            self.handle_block_level_variable_declaration(vd)?,
-                        ))
+                        Some(ForInitializer::VariableDeclarations(typed))
                     }
                     Some(ForInitializer::Expression(expr)) => {
                         Some(ForInitializer::Expression(self.handle_expression(expr)?))
@@ -343,7 +559,10 @@ impl TypeChecker {
                     None => None,
                 };
 
-                let condition = self.handle_opt_expression(condition)?;
+                let condition = match condition {
+                    Some(condition) => Some(self.handle_condition(condition)?),
+                    None => None,
+                };
                 let post = self.handle_opt_expression(post)?;
                 let body = Box::new(self.handle_statement(body, enclosing)?);
 
@@ -361,7 +580,12 @@ impl TypeChecker {
                 cases,
                 label,
             } => {
-                let expression = self.handle_expression(expression)?;
+                let typed = self.handle_expression(expression)?;
+                if !typed.ty().unwrap().is_integer() {
+                    return Err("Switch expression must have integer type".to_string());
+                }
+
+                let expression = self.promote(&typed);
                 let body = Box::new(self.handle_statement(
                     body,
                     &EnclosingContext {
@@ -387,9 +611,13 @@ impl TypeChecker {
                 };
 
                 let Some(switch_expr_type) = &enclosing.switch_expr_type else {
-                    return Err("Unexpected switch case outside of switch statement".to_string());
+                    return Err(format!("Case {c} outside of switch statement"));
                 };
 
+                if let Constant::ConstantDouble(_) = c {
+                    return Err("Case value must be an integer".to_string());
+                }
+
                 let converted_c = convert_constant_to_type(c, switch_expr_type);
 
                 Statement::Case {
@@ -401,10 +629,16 @@ impl TypeChecker {
                     label: label.clone(),
                 }
             }
-            Statement::Default { body, label } => Statement::Default {
-                body: Box::new(self.handle_statement(body, enclosing)?),
-                label: label.clone(),
-            },
+            Statement::Default { body, label } => {
+                if enclosing.switch_expr_type.is_none() {
+                    return Err("Default case outside of switch statement".to_string());
+                }
+
+                Statement::Default {
+                    body: Box::new(self.handle_statement(body, enclosing)?),
+                    label: label.clone(),
+                }
+            }
 
             Statement::Null | Statement::Goto(_) | Statement::Break(_) | Statement::Continue(_) => {
                 statement.clone()
@@ -423,6 +657,7 @@ impl TypeChecker {
             Declaration::Function(function_declaration) => {
                 Declaration::Function(self.handle_function_declaration(function_declaration)?)
             }
+            Declaration::Enum(ed) => Declaration::Enum(self.handle_enum_declaration(ed)?),
         })
     }
 
@@ -430,6 +665,9 @@ impl TypeChecker {
         &mut self,
         declaration: &VariableDeclaration,
     ) -> Result<VariableDeclaration, String> {
+        validate_type(&declaration.ty)?;
+        self.record_const(declaration);
+
         Ok(match declaration.storage_class {
             Some(StorageClass::Extern) => {
                 if declaration.initializer.is_some() {
@@ -462,18 +700,10 @@ impl TypeChecker {
             }
             Some(StorageClass::Static) => {
                 let initial = match &declaration.initializer {
-                    Some(Expression::Constant { c, ty: _ }) => SymbolInitialValue::Initial(
-                        self.convert_constant_to_static_initial(c, &declaration.ty),
+                    Some(initializer) => SymbolInitialValue::Initial(
+                        self.convert_static_initializer(initializer, &declaration.ty)?,
                     ),
-                    None => SymbolInitialValue::Initial(self.convert_constant_to_static_initial(
-                        &Constant::ConstantInt(0),
-                        &declaration.ty,
-                    )),
-                    _ => {
-                        return Err(
-                            "Non-constant initializer on block-level static variable".to_string()
-                        )
-                    }
+                    None => SymbolInitialValue::Tentative,
                 };
 
                 self.symbols.insert(
@@ -498,10 +728,8 @@ impl TypeChecker {
                     },
                 );
 
-                let initializer = if let Some(expr) = &declaration.initializer {
-                    let typed = self.handle_expression(expr)?;
-                    let converted = self.convert_to_type(&typed, &declaration.ty);
-                    Some(converted)
+                let initializer = if let Some(initializer) = &declaration.initializer {
+                    Some(self.handle_initializer(initializer, &declaration.ty)?)
                 } else {
                     None
                 };
@@ -511,57 +739,146 @@ impl TypeChecker {
                     initializer,
                     ty: declaration.ty.clone(),
                     storage_class: declaration.storage_class,
+                    is_const: declaration.is_const,
                 }
             }
         })
     }
 
+    fn record_const(&mut self, declaration: &VariableDeclaration) {
+        if declaration.is_const {
+            self.const_variables
+                .insert(declaration.variable.identifier.clone());
+        }
+    }
+
+    /// Only assignments to a const variable itself are caught, not through pointers.
+    fn check_not_const(&self, lvalue: &Expression) -> Result<(), String> {
+        match lvalue {
+            Expression::Variable { v, .. } if self.const_variables.contains(&v.identifier) => {
+                // block-scope names are suffixed with the name from the source
+                let name = v.identifier.rsplit('.').next().unwrap();
+                Err(format!("Assignment to const variable {name}"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Arrays decay to a pointer to their first element wherever their value is used, and
+    /// functions to a pointer to the function.
     fn handle_expression(&mut self, expr: &Expression) -> Result<Expression, String> {
+        let typed = self.handle_expression_without_decay(expr)?;
+
+        Ok(match typed.ty() {
+            Some(Type::Array { element, .. }) => Expression::AddrOf {
+                expr: Box::new(typed),
+                ty: Some(Type::Pointer(element)),
+            },
+            Some(ty @ Type::Function { .. }) => Expression::AddrOf {
+                expr: Box::new(typed),
+                ty: Some(Type::Pointer(Box::new(ty))),
+            },
+            _ => typed,
+        })
+    }
+
+    /// Converts the arguments of a call to the types of the parameters.
+    fn handle_arguments(
+        &mut self,
+        callee: &str,
+        arguments: &[Expression],
+        parameters: &[Type],
+    ) -> Result<Vec<Expression>, String> {
+        if parameters.len() != arguments.len() {
+            return Err(format!(
+                "{callee} expects {} arguments, got {}",
+                parameters.len(),
+                arguments.len()
+            ));
+        }
+
+        let mut converted_arguments = Vec::new();
+
+        for (argument, parameter_ty) in arguments.iter().zip(parameters.iter()) {
+            let typed = self.handle_expression(argument)?;
+
+            converted_arguments.push(self.convert_by_assignment(&typed, parameter_ty)?);
+        }
+
+        Ok(converted_arguments)
+    }
+
+    fn handle_expression_without_decay(&mut self, expr: &Expression) -> Result<Expression, String> {
         Ok(match expr {
             Expression::FunctionCall {
                 function,
                 arguments,
                 ty: _,
             } => {
-                let entry = self.symbols.get(&function.identifier).unwrap().clone();
+                // identifier resolution already rejects undeclared names, but this pass can be
+                // run on its own
+                let entry = self
+                    .symbols
+                    .get(&function.identifier)
+                    .ok_or_else(|| format!("Function {} not declared", function.identifier))?
+                    .clone();
 
                 let Type::Function {
                     return_type,
                     parameters,
                 } = entry.ty
                 else {
-                    return Err(format!("{} is not a function", function.identifier));
+                    // a variable, which has to hold a function pointer
+                    return self.handle_expression_without_decay(&Expression::IndirectCall {
+                        callee: Box::new(Expression::Variable {
+                            v: Variable {
+                                identifier: function.identifier.clone(),
+                            },
+                            ty: None,
+                        }),
+                        arguments: arguments.clone(),
+                        ty: None,
+                    });
                 };
 
-                if parameters.len() != arguments.len() {
-                    return Err(format!(
-                        "Function {} expects {} arguments, got {}",
-                        function.identifier,
-                        parameters.len(),
-                        arguments.len()
-                    ));
-                }
-
-                let mut converted_arguments = Vec::new();
-
-                for (argument, parameter_ty) in arguments.iter().zip(parameters.iter()) {
-                    let typed = self.handle_expression(argument)?;
-
-                    converted_arguments.push(self.convert_to_type(&typed, parameter_ty));
-                }
+                let callee = format!("Function {}", function.identifier);
 
                 Expression::FunctionCall {
                     function: function.clone(),
-                    arguments: converted_arguments,
-                    ty: Some(*return_type.clone()),
+                    arguments: self.handle_arguments(&callee, arguments, &parameters)?,
+                    ty: Some(*return_type),
                 }
             }
-            Expression::Variable { v, ty: _ } => {
-                let entry = self.symbols.get(&v.identifier).unwrap();
+            Expression::IndirectCall {
+                callee,
+                arguments,
+                ty: _,
+            } => {
+                let typed_callee = self.handle_expression(callee)?;
+
+                let Some(Type::Pointer(referenced)) = typed_callee.ty() else {
+                    return Err("Called object is not a function".to_string());
+                };
 
-                if let Type::Function { .. } = entry.ty {
-                    return Err(format!("{} is not a variable", v.identifier));
+                let Type::Function {
+                    return_type,
+                    parameters,
+                } = *referenced
+                else {
+                    return Err("Called object is not a function".to_string());
+                };
+
+                Expression::IndirectCall {
+                    callee: Box::new(typed_callee),
+                    arguments: self.handle_arguments("Function pointer", arguments, &parameters)?,
+                    ty: Some(*return_type),
                 }
+            }
+            Expression::Variable { v, ty: _ } => {
+                let entry = self
+                    .symbols
+                    .get(&v.identifier)
+                    .ok_or_else(|| format!("Variable {} not declared", v.identifier))?;
 
                 Expression::Variable {
                     v: v.clone(),
@@ -570,13 +887,50 @@ impl TypeChecker {
             }
             Expression::Unary { op, expr, ty: _ } => {
                 let typed = self.handle_expression(expr)?;
+
+                if !typed.ty().unwrap().is_scalar() {
+                    return Err("Invalid operand to unary operator".to_string());
+                }
+
+                let typed = match op {
+                    UnaryOperator::PrefixIncrement
+                    | UnaryOperator::PrefixDecrement
+                    | UnaryOperator::PostfixIncrement
+                    | UnaryOperator::PostfixDecrement => {
+                        if !typed.is_lvalue() {
+                            return Err("Arrays cannot be incremented or decremented".to_string());
+                        }
+
+                        self.check_not_const(&typed)?;
+
+                        if typed.ty() == Some(Type::Bool) {
+                            return Err("Cannot increment or decrement a _Bool".to_string());
+                        }
+
+                        validate_pointer_arithmetic(&typed.ty().unwrap())?;
+
+                        typed
+                    }
+                    UnaryOperator::Not => typed,
+                    UnaryOperator::Negate | UnaryOperator::Complement | UnaryOperator::Plus => {
+                        if let Some(Type::Pointer(_)) = typed.ty() {
+                            return Err("Invalid operand to unary operator".to_string());
+                        }
+
+                        if *op == UnaryOperator::Complement && typed.ty() == Some(Type::Double) {
+                            return Err("Operator requires integer operands".to_string());
+                        }
+
+                        self.promote(&typed)
+                    }
+                };
                 let ty = typed.ty().unwrap();
 
                 Expression::Unary {
                     op: *op,
                     expr: Box::new(typed),
                     ty: Some(match op {
-                        Not => Type::Int,
+                        UnaryOperator::Not => Type::Int,
                         _ => ty,
                     }),
                 }
@@ -590,6 +944,10 @@ impl TypeChecker {
                 let typed_lhs = self.handle_expression(lhs)?;
                 let typed_rhs = self.handle_expression(rhs)?;
 
+                if !typed_lhs.ty().unwrap().is_scalar() || !typed_rhs.ty().unwrap().is_scalar() {
+                    return Err("Invalid operands to binary operator".to_string());
+                }
+
                 if let BinaryOperator::LogicalAnd | BinaryOperator::LogicalOr = op {
                     Expression::Binary {
                         op: *op,
@@ -597,12 +955,23 @@ impl TypeChecker {
                         rhs: Box::new(typed_rhs),
                         ty: Some(Type::Int),
                     }
+                } else if matches!(typed_lhs.ty(), Some(Type::Pointer(_)))
+                    || matches!(typed_rhs.ty(), Some(Type::Pointer(_)))
+                {
+                    self.handle_pointer_binary(*op, &typed_lhs, &typed_rhs)?
                 } else {
+                    let typed_lhs = self.promote(&typed_lhs);
+                    let typed_rhs = self.promote(&typed_rhs);
+
                     let ty_lhs = typed_lhs.ty().unwrap();
                     let ty_rhs = typed_rhs.ty().unwrap();
 
                     let common = self.get_common_type(&ty_lhs, &ty_rhs);
 
+                    if common == Type::Double && requires_integer_operands(*op) {
+                        return Err("Operator requires integer operands".to_string());
+                    }
+
                     let converted_lhs = self.convert_to_type(&typed_lhs, &common);
                     let converted_rhs = self.convert_to_type(&typed_rhs, &common);
 
@@ -643,9 +1012,61 @@ impl TypeChecker {
                 let typed_lhs = self.handle_expression(lhs)?;
                 let typed_rhs = self.handle_expression(rhs)?;
 
+                if !typed_lhs.is_lvalue() {
+                    return Err("Arrays are not assignable".to_string());
+                }
+
+                self.check_not_const(&typed_lhs)?;
+
                 let ty_lhs = typed_lhs.ty().unwrap();
 
-                let converted_rhs = self.convert_to_type(&typed_rhs, &ty_lhs);
+                let converted_rhs = if *op == AssignmentOperator::Assign {
+                    self.convert_by_assignment(&typed_rhs, &ty_lhs)?
+                } else {
+                    let ty_rhs = typed_rhs.ty().unwrap();
+
+                    if !ty_rhs.is_arithmetic() {
+                        return Err("Invalid operands to compound assignment".to_string());
+                    }
+
+                    if (ty_lhs == Type::Double || ty_rhs == Type::Double)
+                        && matches!(
+                            op,
+                            AssignmentOperator::RemainderAssign
+                                | AssignmentOperator::BitwiseAndAssign
+                                | AssignmentOperator::BitwiseOrAssign
+                                | AssignmentOperator::BitwiseXorAssign
+                                | AssignmentOperator::ShiftLeftAssign
+                                | AssignmentOperator::ShiftRightAssign
+                        )
+                    {
+                        return Err("Operator requires integer operands".to_string());
+                    }
+
+                    match ty_lhs {
+                        Type::Bool => {
+                            return Err(
+                                "Compound assignment to a _Bool is not supported".to_string()
+                            )
+                        }
+                        // pointers can only move by a number of elements
+                        Type::Pointer(_) => match op {
+                            AssignmentOperator::AddAssign | AssignmentOperator::SubtractAssign
+                                if ty_rhs.is_integer() =>
+                            {
+                                validate_pointer_arithmetic(&ty_lhs)?;
+                                self.convert_to_type(&typed_rhs, &Type::Long)
+                            }
+                            _ => return Err("Invalid operands to compound assignment".to_string()),
+                        },
+                        // the operation happens in the common type, and tackygen converts the
+                        // result back to the type of the lhs
+                        _ => {
+                            let common = self.get_common_type(&ty_lhs, &ty_rhs);
+                            self.convert_to_type(&typed_rhs, &common)
+                        }
+                    }
+                };
 
                 Expression::Assignment {
                     op: *op,
@@ -660,14 +1081,24 @@ impl TypeChecker {
                 else_expr,
                 ty: _,
             } => {
-                let typed_condition = self.handle_expression(condition)?;
+                let typed_condition = self.handle_condition(condition)?;
                 let typed_then = self.handle_expression(then_expr)?;
                 let typed_else = self.handle_expression(else_expr)?;
 
                 let ty_then = typed_then.ty().unwrap();
                 let ty_else = typed_else.ty().unwrap();
 
-                let common = self.get_common_type(&ty_then, &ty_else);
+                let common = if ty_then == Type::Void || ty_else == Type::Void {
+                    if ty_then != ty_else {
+                        return Err("Expressions have incompatible types".to_string());
+                    }
+                    Type::Void
+                } else if matches!(ty_then, Type::Pointer(_)) || matches!(ty_else, Type::Pointer(_))
+                {
+                    self.get_common_pointer_type(&typed_then, &typed_else)?
+                } else {
+                    self.get_common_type(&ty_then, &ty_else)
+                };
 
                 let converted_then = self.convert_to_type(&typed_then, &common);
                 let converted_else = self.convert_to_type(&typed_else, &common);
@@ -679,25 +1110,193 @@ impl TypeChecker {
                     ty: Some(common),
                 }
             }
+            Expression::SizeOf { expr, ty: _ } => {
+                let typed = self.handle_expression_without_decay(expr)?;
+
+                Self::size_of(&typed.ty().unwrap())?
+            }
+            Expression::SizeOfType { target_ty, ty: _ } => {
+                validate_type(target_ty)?;
+                Self::size_of(target_ty)?
+            }
+            Expression::AddrOf { expr, ty: _ } => {
+                if !expr.is_lvalue() {
+                    return Err("Cannot take the address of a non-lvalue".to_string());
+                }
+
+                let typed = self.handle_expression_without_decay(expr)?;
+                let ty = Type::Pointer(Box::new(typed.ty().unwrap()));
+
+                Expression::AddrOf {
+                    expr: Box::new(typed),
+                    ty: Some(ty),
+                }
+            }
+            Expression::Dereference { expr, ty: _ } => {
+                let typed = self.handle_expression(expr)?;
+
+                let Some(Type::Pointer(referenced)) = typed.ty() else {
+                    return Err("Cannot dereference a non-pointer".to_string());
+                };
+
+                Expression::Dereference {
+                    expr: Box::new(typed),
+                    ty: Some(*referenced),
+                }
+            }
+            Expression::Subscript { expr, index, ty: _ } => {
+                let typed_expr = self.handle_expression(expr)?;
+                let typed_index = self.handle_expression(index)?;
+
+                // `a[i]` and `i[a]` are the same thing
+                let (ptr, index) = match (typed_expr.ty().unwrap(), typed_index.ty().unwrap()) {
+                    (Type::Pointer(_), ty) if ty.is_integer() => (typed_expr, typed_index),
+                    (ty, Type::Pointer(_)) if ty.is_integer() => (typed_index, typed_expr),
+                    _ => return Err("Subscript requires a pointer and an integer".to_string()),
+                };
+
+                validate_pointer_arithmetic(&ptr.ty().unwrap())?;
+
+                let Some(Type::Pointer(referenced)) = ptr.ty() else {
+                    unreachable!()
+                };
+
+                Expression::Subscript {
+                    index: Box::new(self.convert_to_type(&index, &Type::Long)),
+                    expr: Box::new(ptr),
+                    ty: Some(*referenced),
+                }
+            }
+            Expression::Comma { lhs, rhs, ty: _ } => {
+                let typed_lhs = self.handle_expression(lhs)?;
+                let typed_rhs = self.handle_expression(rhs)?;
+                let ty = typed_rhs.ty();
+
+                Expression::Comma {
+                    lhs: Box::new(typed_lhs),
+                    rhs: Box::new(typed_rhs),
+                    ty,
+                }
+            }
             Expression::Constant { c, ty: _ } => Expression::Constant {
                 c: c.clone(),
                 ty: Some(match c {
                     Constant::ConstantInt(_) => Type::Int,
                     Constant::ConstantLong(_) => Type::Long,
+                    Constant::ConstantUInt(_) => Type::UInt,
+                    Constant::ConstantULong(_) => Type::ULong,
+                    Constant::ConstantDouble(_) => Type::Double,
                 }),
             },
             Expression::Cast {
                 target_ty,
                 expr,
                 ty: _,
-            } => Expression::Cast {
-                target_ty: target_ty.clone(),
-                expr: Box::new(self.handle_expression(expr)?),
-                ty: Some(target_ty.clone()),
-            },
+            } => {
+                if let Type::Array { .. } = target_ty {
+                    return Err("Cannot cast to an array type".to_string());
+                }
+
+                // anything can be cast to void to discard it
+                if *target_ty != Type::Void {
+                    validate_type(target_ty)?;
+                }
+
+                let typed = self.handle_expression(expr)?;
+
+                if typed.ty() == Some(Type::Void) && *target_ty != Type::Void {
+                    return Err("Cannot cast a void expression".to_string());
+                }
+
+                if let (Type::Pointer(_), Type::Double) | (Type::Double, Type::Pointer(_)) =
+                    (target_ty, &typed.ty().unwrap())
+                {
+                    return Err("Cannot cast between pointers and doubles".to_string());
+                }
+
+                Expression::Cast {
+                    target_ty: target_ty.clone(),
+                    expr: Box::new(typed),
+                    ty: Some(target_ty.clone()),
+                }
+            }
         })
     }
 
+    /// Pointers can be compared, offset by an integer, and subtracted from each other.
+    fn handle_pointer_binary(
+        &self,
+        op: BinaryOperator,
+        lhs: &Expression,
+        rhs: &Expression,
+    ) -> Result<Expression, String> {
+        let ty_lhs = lhs.ty().unwrap();
+        let ty_rhs = rhs.ty().unwrap();
+
+        if let BinaryOperator::Add | BinaryOperator::Subtract = op {
+            validate_pointer_arithmetic(&ty_lhs)?;
+            validate_pointer_arithmetic(&ty_rhs)?;
+        }
+
+        let (lhs, rhs, ty) = match op {
+            BinaryOperator::Equal | BinaryOperator::NotEqual => {
+                let common = self.get_common_pointer_type(lhs, rhs)?;
+
+                (
+                    self.convert_to_type(lhs, &common),
+                    self.convert_to_type(rhs, &common),
+                    Type::Int,
+                )
+            }
+            BinaryOperator::Add | BinaryOperator::Subtract if ty_rhs.is_integer() => {
+                (lhs.clone(), self.convert_to_type(rhs, &Type::Long), ty_lhs)
+            }
+            BinaryOperator::Add if ty_lhs.is_integer() => {
+                (self.convert_to_type(lhs, &Type::Long), rhs.clone(), ty_rhs)
+            }
+            // the distance in elements
+            BinaryOperator::Subtract if ty_lhs == ty_rhs => (lhs.clone(), rhs.clone(), Type::Long),
+            BinaryOperator::LessThan
+            | BinaryOperator::LessOrEqual
+            | BinaryOperator::GreaterThan
+            | BinaryOperator::GreaterOrEqual
+                if ty_lhs == ty_rhs =>
+            {
+                (lhs.clone(), rhs.clone(), Type::Int)
+            }
+            _ => return Err("Invalid operands to binary operator".to_string()),
+        };
+
+        Ok(Expression::Binary {
+            op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+            ty: Some(ty),
+        })
+    }
+
+    /// The operand is never evaluated, so `sizeof` folds to a constant here.
+    fn size_of(ty: &Type) -> Result<Expression, String> {
+        let Some(size) = ty.size() else {
+            return Err("Invalid operand to sizeof".to_string());
+        };
+
+        Ok(Expression::Constant {
+            c: Constant::ConstantLong(size),
+            ty: Some(Type::Long),
+        })
+    }
+
+    fn handle_condition(&mut self, condition: &Expression) -> Result<Expression, String> {
+        let typed = self.handle_expression(condition)?;
+
+        if !typed.ty().unwrap().is_scalar() {
+            return Err("Condition must have scalar type".to_string());
+        }
+
+        Ok(typed)
+    }
+
     fn handle_opt_expression(
         &mut self,
         expr: &Option<Expression>,
@@ -709,7 +1308,202 @@ impl TypeChecker {
     }
 }
 
+/// `void` can only be the return type of a function.
+fn validate_type(ty: &Type) -> Result<(), String> {
+    match ty {
+        Type::Void => Err("Invalid use of void".to_string()),
+        Type::Pointer(referenced) => validate_type(referenced),
+        Type::Array { element, .. } => validate_type(element),
+        Type::Function {
+            return_type,
+            parameters,
+        } => {
+            if **return_type != Type::Void {
+                validate_type(return_type)?;
+            }
+
+            parameters.iter().try_for_each(validate_type)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Pointer arithmetic moves by whole objects, so it needs to know their size.
+fn validate_pointer_arithmetic(ty: &Type) -> Result<(), String> {
+    match ty {
+        Type::Pointer(referenced) if referenced.size().is_none() => {
+            Err("Invalid arithmetic on a function pointer".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn requires_integer_operands(op: BinaryOperator) -> bool {
+    matches!(
+        op,
+        BinaryOperator::Remainder
+            | BinaryOperator::BitwiseAnd
+            | BinaryOperator::BitwiseOr
+            | BinaryOperator::BitwiseXor
+            | BinaryOperator::ShiftLeft
+            | BinaryOperator::ShiftRight
+    )
+}
+
+fn promoted_type(ty: &Type) -> Type {
+    match ty {
+        Type::Bool | Type::Char | Type::SChar | Type::UChar => Type::Int,
+        _ => ty.clone(),
+    }
+}
+
+fn is_null_pointer_constant(expr: &Expression) -> bool {
+    matches!(expr, Expression::Constant { c, .. } if is_zero_constant(c))
+}
+
+fn is_zero_constant(c: &Constant) -> bool {
+    matches!(
+        c,
+        Constant::ConstantInt(0)
+            | Constant::ConstantLong(0)
+            | Constant::ConstantUInt(0)
+            | Constant::ConstantULong(0)
+    )
+}
+
 struct EnclosingContext {
     function_return_type: Type,
     switch_expr_type: Option<Type>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(source: &str) -> Result<Program, String> {
+        let tokens = crate::compiler::lexer::tokenize(source).unwrap();
+        let ast = crate::compiler::parser::parse(&tokens, false).unwrap();
+        TypeChecker::analyze(&ast).map(|(program, _)| program)
+    }
+
+    fn returned_expression(program: &Program) -> &Expression {
+        let Some(Declaration::Function(FunctionDeclaration {
+            body: Some(body), ..
+        })) = program.declarations.last()
+        else {
+            panic!("expected a function definition");
+        };
+
+        let Some(BlockItem::Statement(Statement::Return(Some(expr)))) = body.items.last() else {
+            panic!("expected a return statement");
+        };
+
+        expr
+    }
+
+    #[test]
+    fn test_conditional_common_type() {
+        let program = check("long f(void) { return 1 ? 2L : 3; }").unwrap();
+
+        let Expression::Conditional {
+            condition,
+            then_expr,
+            else_expr,
+            ty,
+        } = returned_expression(&program)
+        else {
+            panic!("expected a conditional expression");
+        };
+
+        assert_eq!(*ty, Some(Type::Long));
+        assert_eq!(condition.ty(), Some(Type::Int));
+        assert_eq!(then_expr.ty(), Some(Type::Long));
+        assert!(matches!(
+            **else_expr,
+            Expression::Cast {
+                target_ty: Type::Long,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_conditional_errors() {
+        let test_cases = [
+            (
+                "void g(void); int f(void) { return g() ? 1 : 2; }",
+                "Condition must have scalar type",
+            ),
+            (
+                "int f(int *p) { return 1 ? p : 2.0; }",
+                "Expressions have incompatible types",
+            ),
+            (
+                "void g(void); int f(void) { return 1 ? g() : 2; }",
+                "Expressions have incompatible types",
+            ),
+        ];
+
+        for (source, message) in test_cases {
+            assert_eq!(check(source).unwrap_err(), message, "{source}");
+        }
+    }
+
+    #[test]
+    fn test_switch_errors() {
+        let test_cases = [
+            (
+                "int f(double d) { switch (d) { default: return 1; } }",
+                "Switch expression must have integer type",
+            ),
+            (
+                "int f(int *p) { switch (p) { default: return 1; } }",
+                "Switch expression must have integer type",
+            ),
+            (
+                "int f(int x) { switch (x) { case 1.5: return 1; } return 0; }",
+                "Case value must be an integer",
+            ),
+        ];
+
+        for (source, message) in test_cases {
+            assert_eq!(check(source).unwrap_err(), message, "{source}");
+        }
+    }
+
+    #[test]
+    fn test_undeclared_identifiers() {
+        // without identifier resolution first, these used to panic
+        assert_eq!(
+            check("int f(void) { return g(); }").unwrap_err(),
+            "Function g not declared"
+        );
+        assert_eq!(
+            check("int f(void) { return x; }").unwrap_err(),
+            "Variable x not declared"
+        );
+    }
+
+    #[test]
+    fn test_const_assignment() {
+        let test_cases = [
+            "const int x = 1; int f(void) { x = 2; return x; }",
+            "int f(void) { const long x = 1; x += 2; return x; }",
+            "int f(void) { const int x = 1; return x++; }",
+            "int f(void) { const int x = 1; return --x; }",
+            "int f(void) { int *const x = 0; x = 0; return 0; }",
+        ];
+
+        for source in test_cases {
+            assert_eq!(
+                check(source).unwrap_err(),
+                "Assignment to const variable x",
+                "{source}"
+            );
+        }
+
+        // only what they point to is const
+        assert!(check("int f(const int *p) { p = 0; return 0; }").is_ok());
+        assert!(check("int f(void) { const int y = 1; return y; }").is_ok());
+    }
+}
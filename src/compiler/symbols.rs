@@ -1,6 +1,9 @@
 use crate::compiler::ast::Type;
 
-use std::collections::{hash_map::Iter, HashMap};
+use std::{
+    collections::{hash_map::Iter, HashMap},
+    fmt,
+};
 
 #[derive(Debug, Clone)]
 pub enum SymbolAttributes {
@@ -12,20 +15,99 @@ pub enum SymbolAttributes {
         initial: SymbolInitialValue,
         global: bool,
     },
+    /// Read-only data the compiler introduces, e.g. for floating-point literals.
+    Constant(SymbolStaticInitial),
     Local,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum SymbolInitialValue {
     Tentative,
-    Initial(SymbolStaticInitial),
+    /// The values of all scalars in the object, in memory order.
+    Initial(Vec<SymbolStaticInitial>),
     None,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SymbolStaticInitial {
+    Bool(bool),
+    Char(i8),
+    UChar(u8),
     Int(i32),
     Long(i64),
+    UInt(u32),
+    ULong(u64),
+    Double(f64),
+    /// A number of zero bytes
+    Zero(i64),
+}
+
+impl SymbolStaticInitial {
+    pub fn is_zero(&self) -> bool {
+        match self {
+            // `-0.0` has its sign bit set
+            SymbolStaticInitial::Double(d) => d.to_bits() == 0,
+            _ => matches!(
+                self,
+                SymbolStaticInitial::Bool(false)
+                    | SymbolStaticInitial::Char(0)
+                    | SymbolStaticInitial::UChar(0)
+                    | SymbolStaticInitial::Int(0)
+                    | SymbolStaticInitial::Long(0)
+                    | SymbolStaticInitial::UInt(0)
+                    | SymbolStaticInitial::ULong(0)
+                    | SymbolStaticInitial::Zero(_)
+            ),
+        }
+    }
+}
+
+impl fmt::Display for SymbolStaticInitial {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SymbolStaticInitial::Bool(b) => write!(f, "{}", *b as i32),
+            SymbolStaticInitial::Char(c) => write!(f, "{c}"),
+            SymbolStaticInitial::UChar(c) => write!(f, "{c}"),
+            SymbolStaticInitial::Int(n) => write!(f, "{n}"),
+            SymbolStaticInitial::Long(n) => write!(f, "{n}"),
+            SymbolStaticInitial::UInt(n) => write!(f, "{n}"),
+            SymbolStaticInitial::ULong(n) => write!(f, "{n}"),
+            SymbolStaticInitial::Double(d) => write!(f, "{d:?}"),
+            SymbolStaticInitial::Zero(n) => write!(f, "{n} zero bytes"),
+        }
+    }
+}
+
+impl fmt::Display for SymbolAttributes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let linkage = |global: bool| if global { "global" } else { "internal" };
+
+        match self {
+            SymbolAttributes::Function { defined, global } => write!(
+                f,
+                "function, {}, {}",
+                linkage(*global),
+                if *defined { "defined" } else { "declared" }
+            ),
+            SymbolAttributes::Static { initial, global } => {
+                write!(f, "static, {}, ", linkage(*global))?;
+
+                match initial {
+                    SymbolInitialValue::Tentative => write!(f, "tentative"),
+                    SymbolInitialValue::Initial(values) => {
+                        let values = values
+                            .iter()
+                            .map(|value| value.to_string())
+                            .collect::<Vec<_>>();
+                        write!(f, "initial {}", values.join(", "))
+                    }
+                    SymbolInitialValue::None => write!(f, "defined elsewhere"),
+                }
+            }
+            SymbolAttributes::Constant(value) => write!(f, "constant {value}"),
+            SymbolAttributes::Local => write!(f, "local"),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -49,11 +131,39 @@ impl SymbolTable {
         self.entries.get(identifier)
     }
 
+    /// For the passes after type checking, which only see identifiers it put in the table, along
+    /// with the temporaries added while generating the IR.
+    pub fn get_checked(&self, identifier: &str) -> &Symbol {
+        self.get(identifier)
+            .unwrap_or_else(|| unreachable!("{identifier} is not in the symbol table"))
+    }
+
     pub fn insert(&mut self, identifier: String, entry: Symbol) -> Option<Symbol> {
         self.entries.insert(identifier, entry)
     }
 
-    pub fn iter(&self) -> Iter<String, Symbol> {
+    pub fn iter(&self) -> Iter<'_, String, Symbol> {
         self.entries.iter()
     }
 }
+
+/// One symbol per line, sorted by name, with the types lined up.
+impl fmt::Display for SymbolTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut entries = self
+            .entries
+            .iter()
+            .map(|(identifier, symbol)| (identifier, symbol.ty.to_string(), &symbol.attrs))
+            .collect::<Vec<_>>();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+
+        let identifier_width = entries.iter().map(|e| e.0.len()).max().unwrap_or(0);
+        let ty_width = entries.iter().map(|e| e.1.len()).max().unwrap_or(0);
+
+        for (identifier, ty, attrs) in entries {
+            writeln!(f, "{identifier:identifier_width$}  {ty:ty_width$}  {attrs}")?;
+        }
+
+        Ok(())
+    }
+}
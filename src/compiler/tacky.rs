@@ -1,3 +1,7 @@
+use std::fmt;
+
+use crate::compiler::{ast::Type, symbols::SymbolStaticInitial};
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Program {
     pub items: Vec<TopLevelItem>,
@@ -7,6 +11,7 @@ pub struct Program {
 pub enum TopLevelItem {
     FunctionDefinition(FunctionDefinition),
     StaticVariable(StaticVariable),
+    StaticConstant(StaticConstant),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -21,12 +26,20 @@ pub struct FunctionDefinition {
 pub struct StaticVariable {
     pub variable: Variable,
     pub global: bool,
-    pub initial: i64,
+    pub ty: Type,
+    pub initial: Vec<SymbolStaticInitial>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaticConstant {
+    pub variable: Variable,
+    pub ty: Type,
+    pub initial: SymbolStaticInitial,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Instruction {
-    Return(Value),
+    Return(Option<Value>),
     Unary {
         op: UnaryOperator,
         src: Value,
@@ -42,6 +55,47 @@ pub enum Instruction {
         src: Value,
         dst: Variable,
     },
+    SignExtend {
+        src: Value,
+        dst: Variable,
+    },
+    ZeroExtend {
+        src: Value,
+        dst: Variable,
+    },
+    Truncate {
+        src: Value,
+        dst: Variable,
+    },
+    /// Truncates towards zero into a signed integer.
+    DoubleToInt {
+        src: Value,
+        dst: Variable,
+    },
+    /// From a signed integer.
+    IntToDouble {
+        src: Value,
+        dst: Variable,
+    },
+    GetAddress {
+        src: Value,
+        dst: Variable,
+    },
+    Load {
+        src_ptr: Value,
+        dst: Variable,
+    },
+    Store {
+        src: Value,
+        dst_ptr: Value,
+    },
+    /// `dst = ptr + index * scale`
+    AddPtr {
+        ptr: Value,
+        index: Value,
+        scale: i64,
+        dst: Variable,
+    },
     Jump {
         target: Label,
     },
@@ -57,16 +111,150 @@ pub enum Instruction {
     FunctionCall {
         function: Function,
         args: Vec<Value>,
-        dst: Variable,
+        /// `None` for calls to void functions.
+        dst: Option<Variable>,
     },
+    /// A call to the function `function_ptr` points to.
+    IndirectCall {
+        function_ptr: Value,
+        args: Vec<Value>,
+        dst: Option<Variable>,
+    },
+    JumpTable {
+        condition: Value,
+        base: i64,
+        targets: Vec<Label>,
+        default: Label,
+        table: Label,
+    },
+    /// Where the code for a line of the source starts, for debug info.
+    SourceLine(usize),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
-    Constant(i64),
+    Constant(Constant),
     Variable(Variable),
 }
 
+/// An integer constant, typed so the backend knows its width and signedness.
+///
+/// Doubles have no immediates, so they are read from static constants instead.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Constant {
+    Int(i32),
+    Long(i64),
+    UInt(u32),
+    ULong(u64),
+}
+
+impl Constant {
+    /// `value` wrapped around to `ty`, like two's complement hardware does. Bools and chars are
+    /// ints and pointers unsigned longs.
+    pub fn new(value: i64, ty: &Type) -> Self {
+        match ty {
+            Type::Int => Constant::Int(value as i32),
+            Type::Long => Constant::Long(value),
+            Type::UInt => Constant::UInt(value as u32),
+            Type::ULong | Type::Pointer(_) => Constant::ULong(value as u64),
+            Type::Bool => Constant::Int((value != 0) as i32),
+            Type::Char | Type::SChar => Constant::Int(value as i8 as i32),
+            Type::UChar => Constant::Int(value as u8 as i32),
+            Type::Double | Type::Void | Type::Array { .. } | Type::Function { .. } => {
+                unreachable!("{ty:?} has no integer constants")
+            }
+        }
+    }
+
+    /// The value sign- or zero-extended to 64 bits, depending on the type.
+    pub fn value(self) -> i64 {
+        match self {
+            Constant::Int(n) => n as i64,
+            Constant::Long(n) => n,
+            Constant::UInt(n) => n as i64,
+            Constant::ULong(n) => n as i64,
+        }
+    }
+
+    pub fn ty(self) -> Type {
+        match self {
+            Constant::Int(_) => Type::Int,
+            Constant::Long(_) => Type::Long,
+            Constant::UInt(_) => Type::UInt,
+            Constant::ULong(_) => Type::ULong,
+        }
+    }
+
+    /// `op` applied to the constant, wrapping around to `ty` like the generated code would.
+    pub fn apply_unary(self, op: UnaryOperator, ty: &Type) -> Constant {
+        let n = self.value();
+        let value = match op {
+            UnaryOperator::Complement => !n,
+            UnaryOperator::Negate => n.wrapping_neg(),
+            UnaryOperator::Not => (n == 0) as i64,
+        };
+
+        Constant::new(value, ty)
+    }
+
+    /// Returns `None` for operations that trap or are undefined, leaving them to runtime.
+    ///
+    /// Unsigned ints are zero-extended, so they compare and divide correctly as u64.
+    pub fn apply_binary(self, op: BinaryOperator, rhs: Constant, ty: &Type) -> Option<Constant> {
+        let bits = ty.size()? * 8;
+        let signed = self.ty().is_signed();
+        let (lhs, rhs) = (self.value(), rhs.value());
+        let (ulhs, urhs) = (lhs as u64, rhs as u64);
+
+        let value = match op {
+            BinaryOperator::Add => lhs.wrapping_add(rhs),
+            BinaryOperator::Subtract => lhs.wrapping_sub(rhs),
+            BinaryOperator::Multiply => lhs.wrapping_mul(rhs),
+            BinaryOperator::Divide | BinaryOperator::Remainder if !signed => {
+                let quotient = ulhs.checked_div(urhs)?;
+
+                match op {
+                    BinaryOperator::Divide => quotient as i64,
+                    _ => (ulhs - quotient * urhs) as i64,
+                }
+            }
+            BinaryOperator::Divide | BinaryOperator::Remainder => {
+                // dividing by zero or overflowing the quotient raises #DE
+                let quotient = lhs.checked_div(rhs)?;
+                if Constant::new(quotient, ty).value() != quotient {
+                    return None;
+                }
+
+                match op {
+                    BinaryOperator::Divide => quotient,
+                    _ => lhs - quotient * rhs,
+                }
+            }
+            BinaryOperator::BitwiseAnd => lhs & rhs,
+            BinaryOperator::BitwiseOr => lhs | rhs,
+            BinaryOperator::BitwiseXor => lhs ^ rhs,
+            BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight if !(0..bits).contains(&rhs) => {
+                return None;
+            }
+            BinaryOperator::ShiftLeft => lhs.wrapping_shl(rhs as u32),
+            BinaryOperator::ShiftRight if !signed => (ulhs >> rhs) as i64,
+            BinaryOperator::ShiftRight => lhs >> rhs,
+            BinaryOperator::Equal => (lhs == rhs) as i64,
+            BinaryOperator::NotEqual => (lhs != rhs) as i64,
+            BinaryOperator::LessThan if !signed => (ulhs < urhs) as i64,
+            BinaryOperator::LessOrEqual if !signed => (ulhs <= urhs) as i64,
+            BinaryOperator::GreaterThan if !signed => (ulhs > urhs) as i64,
+            BinaryOperator::GreaterOrEqual if !signed => (ulhs >= urhs) as i64,
+            BinaryOperator::LessThan => (lhs < rhs) as i64,
+            BinaryOperator::LessOrEqual => (lhs <= rhs) as i64,
+            BinaryOperator::GreaterThan => (lhs > rhs) as i64,
+            BinaryOperator::GreaterOrEqual => (lhs >= rhs) as i64,
+        };
+
+        Some(Constant::new(value, ty))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Variable {
     pub identifier: String,
@@ -108,3 +296,499 @@ pub enum BinaryOperator {
 pub struct Function {
     pub identifier: String,
 }
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, item) in self.items.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{item}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for TopLevelItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let linkage = |global| if global { "global " } else { "" };
+
+        match self {
+            TopLevelItem::FunctionDefinition(fd) => {
+                let parameters = fd.parameters.iter().map(Variable::to_string);
+                writeln!(
+                    f,
+                    "{}function {}({}):",
+                    linkage(fd.global),
+                    fd.function.identifier,
+                    parameters.collect::<Vec<_>>().join(", ")
+                )?;
+
+                for (i, instruction) in fd.instructions.iter().enumerate() {
+                    writeln!(f, "{i:>4}  {instruction}")?;
+                }
+
+                Ok(())
+            }
+            TopLevelItem::StaticVariable(sv) => {
+                let initial = sv.initial.iter().map(|initial| format!("{initial:?}"));
+                writeln!(
+                    f,
+                    "{}static {}: {} = [{}]",
+                    linkage(sv.global),
+                    sv.variable,
+                    sv.ty,
+                    initial.collect::<Vec<_>>().join(", ")
+                )
+            }
+            TopLevelItem::StaticConstant(sc) => {
+                writeln!(f, "constant {}: {} = {:?}", sc.variable, sc.ty, sc.initial)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instruction::Return(Some(value)) => write!(f, "return {value}"),
+            Instruction::Return(None) => write!(f, "return"),
+            Instruction::Unary { op, src, dst } => write!(f, "{dst} = {op}{src}"),
+            Instruction::Binary { op, lhs, rhs, dst } => write!(f, "{dst} = {lhs} {op} {rhs}"),
+            Instruction::Copy { src, dst } => write!(f, "{dst} = {src}"),
+            Instruction::SignExtend { src, dst } => write!(f, "{dst} = sign_extend {src}"),
+            Instruction::ZeroExtend { src, dst } => write!(f, "{dst} = zero_extend {src}"),
+            Instruction::Truncate { src, dst } => write!(f, "{dst} = truncate {src}"),
+            Instruction::DoubleToInt { src, dst } => write!(f, "{dst} = double_to_int {src}"),
+            Instruction::IntToDouble { src, dst } => write!(f, "{dst} = int_to_double {src}"),
+            Instruction::GetAddress { src, dst } => write!(f, "{dst} = &{src}"),
+            Instruction::Load { src_ptr, dst } => write!(f, "{dst} = *{src_ptr}"),
+            Instruction::Store { src, dst_ptr } => write!(f, "*{dst_ptr} = {src}"),
+            Instruction::AddPtr {
+                ptr,
+                index,
+                scale,
+                dst,
+            } => write!(f, "{dst} = {ptr} + {index} * {scale}"),
+            Instruction::Jump { target } => write!(f, "jump {target}"),
+            Instruction::JumpIfZero { condition, target } => {
+                write!(f, "jump_if_zero {condition}, {target}")
+            }
+            Instruction::JumpIfNotZero { condition, target } => {
+                write!(f, "jump_if_not_zero {condition}, {target}")
+            }
+            Instruction::Label(label) => write!(f, "{label}:"),
+            Instruction::FunctionCall {
+                function,
+                args,
+                dst,
+            } => {
+                if let Some(dst) = dst {
+                    write!(f, "{dst} = ")?;
+                }
+
+                let args = args.iter().map(Value::to_string).collect::<Vec<_>>();
+                write!(f, "{}({})", function.identifier, args.join(", "))
+            }
+            Instruction::IndirectCall {
+                function_ptr,
+                args,
+                dst,
+            } => {
+                if let Some(dst) = dst {
+                    write!(f, "{dst} = ")?;
+                }
+
+                let args = args.iter().map(Value::to_string).collect::<Vec<_>>();
+                write!(f, "(*{function_ptr})({})", args.join(", "))
+            }
+            Instruction::JumpTable {
+                condition,
+                base,
+                default,
+                ..
+            } => write!(f, "jump_table {condition} - {base}, default {default}"),
+            Instruction::SourceLine(line) => write!(f, "line {line}"),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Constant(c) => write!(f, "{c}"),
+            Value::Variable(variable) => write!(f, "{variable}"),
+        }
+    }
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constant::Int(n) => write!(f, "{n}"),
+            Constant::Long(n) => write!(f, "{n}L"),
+            Constant::UInt(n) => write!(f, "{n}U"),
+            Constant::ULong(n) => write!(f, "{n}UL"),
+        }
+    }
+}
+
+impl fmt::Display for Variable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.identifier)
+    }
+}
+
+impl fmt::Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.identifier)
+    }
+}
+
+impl fmt::Display for UnaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            UnaryOperator::Complement => "~",
+            UnaryOperator::Negate => "-",
+            UnaryOperator::Not => "!",
+        };
+
+        write!(f, "{op}")
+    }
+}
+
+impl fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Remainder => "%",
+            BinaryOperator::BitwiseAnd => "&",
+            BinaryOperator::BitwiseOr => "|",
+            BinaryOperator::BitwiseXor => "^",
+            BinaryOperator::ShiftLeft => "<<",
+            BinaryOperator::ShiftRight => ">>",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::LessThan => "<",
+            BinaryOperator::LessOrEqual => "<=",
+            BinaryOperator::GreaterThan => ">",
+            BinaryOperator::GreaterOrEqual => ">=",
+        };
+
+        write!(f, "{op}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_unary() {
+        use UnaryOperator::*;
+
+        let cases = [
+            (Constant::Int(0), Complement, Type::Int, Constant::Int(-1)),
+            (
+                Constant::UInt(0),
+                Complement,
+                Type::UInt,
+                Constant::UInt(u32::MAX),
+            ),
+            (
+                Constant::Long(0),
+                Complement,
+                Type::Long,
+                Constant::Long(-1),
+            ),
+            (
+                Constant::ULong(0),
+                Complement,
+                Type::ULong,
+                Constant::ULong(u64::MAX),
+            ),
+            (
+                Constant::Int(i32::MIN),
+                Negate,
+                Type::Int,
+                Constant::Int(i32::MIN),
+            ),
+            (
+                Constant::Long(i64::MIN),
+                Negate,
+                Type::Long,
+                Constant::Long(i64::MIN),
+            ),
+            (
+                Constant::UInt(1),
+                Negate,
+                Type::UInt,
+                Constant::UInt(u32::MAX),
+            ),
+            (
+                Constant::ULong(1),
+                Negate,
+                Type::ULong,
+                Constant::ULong(u64::MAX),
+            ),
+            (
+                Constant::Int(i32::MAX),
+                Negate,
+                Type::Int,
+                Constant::Int(-i32::MAX),
+            ),
+            (Constant::Int(0), Not, Type::Int, Constant::Int(1)),
+            (Constant::UInt(u32::MAX), Not, Type::Int, Constant::Int(0)),
+            (Constant::Long(i64::MIN), Not, Type::Int, Constant::Int(0)),
+        ];
+
+        for (c, op, ty, expected) in cases {
+            assert_eq!(c.apply_unary(op, &ty), expected, "{op:?} {c:?}");
+        }
+    }
+
+    #[test]
+    fn test_apply_binary() {
+        use BinaryOperator::*;
+
+        let cases = [
+            (
+                Constant::Int(i32::MAX),
+                Add,
+                Constant::Int(1),
+                Type::Int,
+                Some(Constant::Int(i32::MIN)),
+            ),
+            (
+                Constant::UInt(u32::MAX),
+                Add,
+                Constant::UInt(1),
+                Type::UInt,
+                Some(Constant::UInt(0)),
+            ),
+            (
+                Constant::Long(i64::MAX),
+                Add,
+                Constant::Long(1),
+                Type::Long,
+                Some(Constant::Long(i64::MIN)),
+            ),
+            (
+                Constant::Int(i32::MIN),
+                Subtract,
+                Constant::Int(1),
+                Type::Int,
+                Some(Constant::Int(i32::MAX)),
+            ),
+            (
+                Constant::UInt(0),
+                Subtract,
+                Constant::UInt(1),
+                Type::UInt,
+                Some(Constant::UInt(u32::MAX)),
+            ),
+            (
+                Constant::Int(65536),
+                Multiply,
+                Constant::Int(65536),
+                Type::Int,
+                Some(Constant::Int(0)),
+            ),
+            (
+                Constant::ULong(u64::MAX),
+                Multiply,
+                Constant::ULong(2),
+                Type::ULong,
+                Some(Constant::ULong(u64::MAX - 1)),
+            ),
+            (
+                Constant::Int(-7),
+                Divide,
+                Constant::Int(2),
+                Type::Int,
+                Some(Constant::Int(-3)),
+            ),
+            (
+                Constant::Int(-7),
+                Remainder,
+                Constant::Int(2),
+                Type::Int,
+                Some(Constant::Int(-1)),
+            ),
+            (
+                Constant::Int(i32::MIN),
+                Divide,
+                Constant::Int(-1),
+                Type::Int,
+                None,
+            ),
+            (
+                Constant::Int(i32::MIN),
+                Remainder,
+                Constant::Int(-1),
+                Type::Int,
+                None,
+            ),
+            (
+                Constant::Long(i64::MIN),
+                Divide,
+                Constant::Long(-1),
+                Type::Long,
+                None,
+            ),
+            (Constant::Int(1), Divide, Constant::Int(0), Type::Int, None),
+            (
+                Constant::UInt(1),
+                Remainder,
+                Constant::UInt(0),
+                Type::UInt,
+                None,
+            ),
+            (
+                Constant::UInt(u32::MAX),
+                Divide,
+                Constant::UInt(2),
+                Type::UInt,
+                Some(Constant::UInt(u32::MAX / 2)),
+            ),
+            (
+                Constant::ULong(u64::MAX),
+                Remainder,
+                Constant::ULong(10),
+                Type::ULong,
+                Some(Constant::ULong(5)),
+            ),
+            (
+                Constant::Int(-1),
+                BitwiseAnd,
+                Constant::Int(0xf0),
+                Type::Int,
+                Some(Constant::Int(0xf0)),
+            ),
+            (
+                Constant::UInt(0xf0),
+                BitwiseOr,
+                Constant::UInt(0x0f),
+                Type::UInt,
+                Some(Constant::UInt(0xff)),
+            ),
+            (
+                Constant::Long(-1),
+                BitwiseXor,
+                Constant::Long(1),
+                Type::Long,
+                Some(Constant::Long(-2)),
+            ),
+            (
+                Constant::Int(1),
+                ShiftLeft,
+                Constant::Int(31),
+                Type::Int,
+                Some(Constant::Int(i32::MIN)),
+            ),
+            (
+                Constant::Int(1),
+                ShiftLeft,
+                Constant::Int(32),
+                Type::Int,
+                None,
+            ),
+            (
+                Constant::Long(1),
+                ShiftLeft,
+                Constant::Int(63),
+                Type::Long,
+                Some(Constant::Long(i64::MIN)),
+            ),
+            (
+                Constant::Int(1),
+                ShiftLeft,
+                Constant::Int(-1),
+                Type::Int,
+                None,
+            ),
+            (
+                Constant::Int(-8),
+                ShiftRight,
+                Constant::Int(1),
+                Type::Int,
+                Some(Constant::Int(-4)),
+            ),
+            (
+                Constant::UInt(u32::MAX),
+                ShiftRight,
+                Constant::Int(31),
+                Type::UInt,
+                Some(Constant::UInt(1)),
+            ),
+            (
+                Constant::ULong(u64::MAX),
+                ShiftRight,
+                Constant::Int(64),
+                Type::ULong,
+                None,
+            ),
+            (
+                Constant::Int(-1),
+                LessThan,
+                Constant::Int(0),
+                Type::Int,
+                Some(Constant::Int(1)),
+            ),
+            (
+                Constant::UInt(u32::MAX),
+                LessThan,
+                Constant::UInt(0),
+                Type::Int,
+                Some(Constant::Int(0)),
+            ),
+            (
+                Constant::ULong(u64::MAX),
+                GreaterOrEqual,
+                Constant::ULong(1),
+                Type::Int,
+                Some(Constant::Int(1)),
+            ),
+            (
+                Constant::Long(i64::MIN),
+                LessOrEqual,
+                Constant::Long(i64::MAX),
+                Type::Int,
+                Some(Constant::Int(1)),
+            ),
+            (
+                Constant::UInt(1),
+                GreaterThan,
+                Constant::UInt(2),
+                Type::Int,
+                Some(Constant::Int(0)),
+            ),
+            (
+                Constant::Int(3),
+                Equal,
+                Constant::Int(3),
+                Type::Int,
+                Some(Constant::Int(1)),
+            ),
+            (
+                Constant::Int(3),
+                NotEqual,
+                Constant::Int(3),
+                Type::Int,
+                Some(Constant::Int(0)),
+            ),
+        ];
+
+        for (lhs, op, rhs, ty, expected) in cases {
+            assert_eq!(
+                lhs.apply_binary(op, rhs, &ty),
+                expected,
+                "{lhs:?} {op:?} {rhs:?}"
+            );
+        }
+    }
+}
@@ -1,34 +1,91 @@
+use std::collections::HashMap;
+
 use crate::compiler::{
     ast,
-    prefixes::{TAC_LABEL_PREFIX, TAC_VAR_PREFIX},
-    symbols::{SymbolAttributes, SymbolInitialValue, SymbolTable},
+    prefixes::{TAC_CONST_PREFIX, TAC_LABEL_PREFIX, TAC_VAR_PREFIX},
+    symbols::{Symbol, SymbolAttributes, SymbolInitialValue, SymbolStaticInitial, SymbolTable},
     tacky,
 };
 
-pub fn generate(program: &ast::Program, symbols: &SymbolTable) -> tacky::Program {
-    (TackyGen::new()).handle_program(program, symbols)
+const JUMP_TABLE_MIN_CASES: usize = 8;
+
+pub fn generate(program: &ast::Program, symbols: &mut SymbolTable) -> tacky::Program {
+    (TackyGen::new(symbols)).handle_program(program)
+}
+
+/// Where the result of an lvalue expression lives.
+enum Lvalue {
+    Variable(tacky::Variable),
+    Dereferenced(tacky::Value),
 }
 
-pub struct TackyGen {
+pub struct TackyGen<'a> {
+    symbols: &'a mut SymbolTable,
     variable_counter: usize,
     label_counter: usize,
+    /// Read-only constants by the bits of their value, so each is only emitted once.
+    double_constants: HashMap<u64, tacky::Variable>,
 }
 
-impl TackyGen {
-    fn new() -> Self {
+impl<'a> TackyGen<'a> {
+    /// Stands in for the result of a void expression, which the type checker never lets be used.
+    const VOID: tacky::Value = tacky::Value::Constant(tacky::Constant::Int(0));
+
+    fn new(symbols: &'a mut SymbolTable) -> Self {
         Self {
+            symbols,
             variable_counter: 0,
             label_counter: 0,
+            double_constants: HashMap::new(),
         }
     }
 
-    fn fresh_variable(&mut self) -> tacky::Variable {
+    fn fresh_variable(&mut self, ty: ast::Type) -> tacky::Variable {
         let name = format!("{TAC_VAR_PREFIX}.{}", self.variable_counter);
         self.variable_counter += 1;
 
+        self.symbols.insert(
+            name.clone(),
+            Symbol {
+                ty,
+                attrs: SymbolAttributes::Local,
+            },
+        );
+
         tacky::Variable { identifier: name }
     }
 
+    /// There are no immediate doubles in x86-64, so they are loaded from memory instead.
+    fn double_constant(&mut self, value: f64) -> tacky::Value {
+        if let Some(variable) = self.double_constants.get(&value.to_bits()) {
+            return tacky::Value::Variable(variable.clone());
+        }
+
+        let name = format!("{TAC_CONST_PREFIX}.{}", self.double_constants.len());
+
+        self.symbols.insert(
+            name.clone(),
+            Symbol {
+                ty: ast::Type::Double,
+                attrs: SymbolAttributes::Constant(SymbolStaticInitial::Double(value)),
+            },
+        );
+
+        let variable = tacky::Variable { identifier: name };
+        self.double_constants
+            .insert(value.to_bits(), variable.clone());
+
+        tacky::Value::Variable(variable)
+    }
+
+    /// `n` as a value of type `ty`.
+    fn constant(&mut self, n: i64, ty: &ast::Type) -> tacky::Value {
+        match ty {
+            ast::Type::Double => self.double_constant(n as f64),
+            _ => tacky::Value::Constant(tacky::Constant::new(n, ty)),
+        }
+    }
+
     fn fresh_label(&mut self, suffix: Option<&str>) -> tacky::Label {
         let name = match suffix {
             Some(suffix) => format!("{TAC_LABEL_PREFIX}.{}.{}", self.label_counter, suffix),
@@ -65,19 +122,78 @@ impl TackyGen {
         }
     }
 
-    fn handle_program(&mut self, program: &ast::Program, symbols: &SymbolTable) -> tacky::Program {
+    fn jump_table_label(label: &ast::SwitchLabel) -> tacky::Label {
+        tacky::Label {
+            identifier: format!("{}.table", label.identifier),
+        }
+    }
+
+    /// Returns the lowest case value and a target for every value in the case range, if dense.
+    fn build_jump_table(
+        cases: &ast::SwitchCases,
+        default: &tacky::Label,
+    ) -> Option<(i64, Vec<tacky::Label>)> {
+        if cases.cases.len() <= JUMP_TABLE_MIN_CASES {
+            return None;
+        }
+
+        let values = cases
+            .cases
+            .iter()
+            .map(|(c, _)| Self::handle_constant(c).value())
+            .collect::<Vec<_>>();
+
+        let min = *values.iter().min()?;
+        let max = *values.iter().max()?;
+
+        if i32::try_from(min).is_err() || i32::try_from(max).is_err() {
+            return None;
+        }
+
+        let range = (max - min + 1) as usize;
+        if range >= 2 * cases.cases.len() {
+            return None;
+        }
+
+        let mut targets = vec![default.clone(); range];
+        for (value, (_, case_label)) in values.iter().zip(&cases.cases) {
+            targets[(value - min) as usize] = tacky::Label {
+                identifier: case_label.identifier.clone(),
+            };
+        }
+
+        Some((min, targets))
+    }
+
+    fn handle_program(&mut self, program: &ast::Program) -> tacky::Program {
         let mut items = Vec::new();
 
         for declaration in &program.declarations {
             if let ast::Declaration::Function(fd) = declaration {
-                if let Some(definition) = self.handle_top_level_function_declaration(fd, symbols) {
+                if let Some(definition) = self.handle_top_level_function_declaration(fd) {
                     items.push(tacky::TopLevelItem::FunctionDefinition(definition));
                 }
             }
         }
 
-        for (identifier, symbol) in symbols.iter() {
-            if let SymbolAttributes::Static { initial, global } = symbol.attrs {
+        // in a fixed order, so that the same source always compiles to the same assembly
+        let mut symbols = self.symbols.iter().collect::<Vec<_>>();
+        symbols.sort_by(|a, b| a.0.cmp(b.0));
+
+        for (identifier, symbol) in symbols {
+            if let SymbolAttributes::Constant(initial) = &symbol.attrs {
+                items.push(tacky::TopLevelItem::StaticConstant(tacky::StaticConstant {
+                    variable: tacky::Variable {
+                        identifier: identifier.clone(),
+                    },
+                    ty: symbol.ty.clone(),
+                    initial: *initial,
+                }));
+            }
+
+            if let SymbolAttributes::Static { initial, global } = &symbol.attrs {
+                let global = *global;
+
                 match initial {
                     SymbolInitialValue::Tentative => {
                         items.push(tacky::TopLevelItem::StaticVariable(tacky::StaticVariable {
@@ -85,7 +201,8 @@ impl TackyGen {
                                 identifier: identifier.clone(),
                             },
                             global,
-                            initial: 0,
+                            ty: symbol.ty.clone(),
+                            initial: vec![SymbolStaticInitial::Zero(symbol.ty.size().unwrap())],
                         }));
                     }
                     SymbolInitialValue::Initial(initial) => {
@@ -94,7 +211,8 @@ impl TackyGen {
                                 identifier: identifier.clone(),
                             },
                             global,
-                            initial: todo!(), // initial,
+                            ty: symbol.ty.clone(),
+                            initial: initial.clone(),
                         }));
                     }
                     SymbolInitialValue::None => {}
@@ -108,7 +226,6 @@ impl TackyGen {
     fn handle_top_level_function_declaration(
         &mut self,
         fd: &ast::FunctionDeclaration,
-        symbols: &SymbolTable,
     ) -> Option<tacky::FunctionDefinition> {
         let Some(body) = &fd.body else {
             return None;
@@ -116,9 +233,22 @@ impl TackyGen {
 
         let mut instructions = self.handle_block(body);
 
-        instructions.push(tacky::Instruction::Return(tacky::Value::Constant(0)));
+        let ast::Type::Function { return_type, .. } = self
+            .symbols
+            .get(&fd.function.identifier)
+            .unwrap()
+            .ty
+            .clone()
+        else {
+            unreachable!()
+        };
+        let zero = match *return_type {
+            ast::Type::Void => None,
+            ref ty => Some(self.constant(0, ty)),
+        };
+        instructions.push(tacky::Instruction::Return(zero));
 
-        let symbol = symbols.get(&fd.function.identifier).unwrap();
+        let symbol = self.symbols.get_checked(&fd.function.identifier);
         let SymbolAttributes::Function { global, .. } = symbol.attrs else {
             unreachable!()
         };
@@ -143,7 +273,11 @@ impl TackyGen {
     fn handle_block(&mut self, block: &ast::Block) -> Vec<tacky::Instruction> {
         let mut ins = vec![];
 
-        for item in &block.items {
+        for (item, line) in block.items.iter().zip(&block.lines) {
+            if let Some(line) = line {
+                ins.push(tacky::Instruction::SourceLine(*line));
+            }
+
             match item {
                 ast::BlockItem::Declaration(declaration) => {
                     self.handle_block_level_declaration(&mut ins, declaration);
@@ -164,7 +298,7 @@ impl TackyGen {
     ) {
         match declaration {
             ast::Declaration::Variable(vd) => self.handle_block_level_variable_declaration(ins, vd),
-            ast::Declaration::Function(_) => {}
+            ast::Declaration::Function(_) | ast::Declaration::Enum(_) => {}
         }
     }
 
@@ -177,22 +311,74 @@ impl TackyGen {
             return;
         }
 
-        if let Some(initializer) = &vd.initializer {
-            let value = self.handle_expression(ins, initializer);
+        let variable = tacky::Variable {
+            identifier: vd.variable.identifier.clone(),
+        };
 
-            ins.push(tacky::Instruction::Copy {
-                src: value,
-                dst: tacky::Variable {
-                    identifier: vd.variable.identifier.clone(),
-                },
-            });
+        match &vd.initializer {
+            Some(ast::Initializer::Single(expr)) => {
+                let value = self.handle_expression(ins, expr);
+
+                ins.push(tacky::Instruction::Copy {
+                    src: value,
+                    dst: variable,
+                });
+            }
+            Some(initializer @ ast::Initializer::Compound(_)) => {
+                let base = self.fresh_variable(ast::Type::Pointer(Box::new(vd.ty.clone())));
+                ins.push(tacky::Instruction::GetAddress {
+                    src: tacky::Value::Variable(variable),
+                    dst: base.clone(),
+                });
+
+                self.handle_compound_initializer(ins, &base, initializer, &vd.ty, 0);
+            }
+            None => {}
+        }
+    }
+
+    /// Stores every scalar of an array initializer through a pointer to its position.
+    fn handle_compound_initializer(
+        &mut self,
+        ins: &mut Vec<tacky::Instruction>,
+        base: &tacky::Variable,
+        initializer: &ast::Initializer,
+        ty: &ast::Type,
+        offset: i64,
+    ) {
+        match (initializer, ty) {
+            (ast::Initializer::Compound(initializers), ast::Type::Array { element, .. }) => {
+                let element_size = element.size().unwrap();
+
+                for (i, initializer) in initializers.iter().enumerate() {
+                    let offset = offset + i as i64 * element_size;
+                    self.handle_compound_initializer(ins, base, initializer, element, offset);
+                }
+            }
+            (ast::Initializer::Single(expr), _) => {
+                let value = self.handle_expression(ins, expr);
+
+                let ptr = self.fresh_variable(ast::Type::Pointer(Box::new(ty.clone())));
+                ins.push(tacky::Instruction::AddPtr {
+                    ptr: tacky::Value::Variable(base.clone()),
+                    index: tacky::Value::Constant(tacky::Constant::Long(offset)),
+                    scale: 1,
+                    dst: ptr.clone(),
+                });
+
+                ins.push(tacky::Instruction::Store {
+                    src: value,
+                    dst_ptr: tacky::Value::Variable(ptr),
+                });
+            }
+            _ => unreachable!("initializers are checked against their type"),
         }
     }
 
     fn handle_statement(&mut self, ins: &mut Vec<tacky::Instruction>, statement: &ast::Statement) {
         match statement {
             ast::Statement::Return(expr) => {
-                let value = self.handle_expression(ins, expr);
+                let value = expr.as_ref().map(|expr| self.handle_expression(ins, expr));
                 ins.push(tacky::Instruction::Return(value));
             }
             ast::Statement::Expression(expr) => {
@@ -320,8 +506,10 @@ impl TackyGen {
 
                 if let Some(initializer) = initializer {
                     match initializer {
-                        ast::ForInitializer::VariableDeclaration(vd) => {
-                            self.handle_block_level_variable_declaration(ins, vd);
+                        ast::ForInitializer::VariableDeclarations(declarations) => {
+                            for vd in declarations {
+                                self.handle_block_level_variable_declaration(ins, vd);
+                            }
                         }
                         ast::ForInitializer::Expression(expression) => {
                             self.handle_expression(ins, expression);
@@ -358,44 +546,47 @@ impl TackyGen {
 
                 let controlling_value = self.handle_expression(ins, expression);
 
-                if let Some(cases) = cases {
-                    for (case_expr, case_label) in &cases.cases {
-                        todo!();
-
-                        // let ast::Expression::Constant(case_expr) = case_expr else {
-                        //     unreachable!()
-                        // };
-
-                        // let dst = self.fresh_variable();
-                        // ins.push(tacky::Instruction::Binary {
-                        //     op: tacky::BinaryOperator::Equal,
-                        //     lhs: controlling_value.clone(),
-                        //     rhs: tacky::Value::Constant(*case_expr),
-                        //     dst: dst.clone(),
-                        // });
-
-                        // ins.push(tacky::Instruction::JumpIfNotZero {
-                        //     condition: tacky::Value::Variable(dst),
-                        //     target: tacky::Label {
-                        //         identifier: case_label.identifier.clone(),
-                        //     },
-                        // });
-                    }
+                let default_target = match cases.as_ref().and_then(|c| c.default.as_ref()) {
+                    Some(default_label) => tacky::Label {
+                        identifier: default_label.identifier.clone(),
+                    },
+                    None => Self::break_switch_label(label),
+                };
 
-                    if let Some(default_label) = &cases.default {
-                        ins.push(tacky::Instruction::Jump {
-                            target: tacky::Label {
-                                identifier: default_label.identifier.clone(),
-                            },
+                if let Some(cases) = cases {
+                    if let Some((base, targets)) = Self::build_jump_table(cases, &default_target) {
+                        ins.push(tacky::Instruction::JumpTable {
+                            condition: controlling_value,
+                            base,
+                            targets,
+                            default: default_target,
+                            table: Self::jump_table_label(label),
                         });
                     } else {
+                        for (c, case_label) in &cases.cases {
+                            let dst = self.fresh_variable(ast::Type::Int);
+                            ins.push(tacky::Instruction::Binary {
+                                op: tacky::BinaryOperator::Equal,
+                                lhs: controlling_value.clone(),
+                                rhs: tacky::Value::Constant(Self::handle_constant(c)),
+                                dst: dst.clone(),
+                            });
+
+                            ins.push(tacky::Instruction::JumpIfNotZero {
+                                condition: tacky::Value::Variable(dst),
+                                target: tacky::Label {
+                                    identifier: case_label.identifier.clone(),
+                                },
+                            });
+                        }
+
                         ins.push(tacky::Instruction::Jump {
-                            target: Self::break_switch_label(label),
+                            target: default_target,
                         });
                     }
                 } else {
                     ins.push(tacky::Instruction::Jump {
-                        target: Self::break_switch_label(label),
+                        target: default_target,
                     });
                 }
 
@@ -439,20 +630,21 @@ impl TackyGen {
         expr: &ast::Expression,
     ) -> tacky::Value {
         match expr {
-            ast::Expression::Constant { c, ty } => todo!(), // tacky::Value::Constant(*value),
+            ast::Expression::Constant {
+                c: ast::Constant::ConstantDouble(d),
+                ..
+            } => self.double_constant(*d),
+            // null pointer constants are typed as pointers but hold a long
+            ast::Expression::Constant { c, ty } => {
+                self.constant(Self::handle_constant(c).value(), ty.as_ref().unwrap())
+            }
             ast::Expression::Unary {
-                op,
-                expr: inner,
-                ty,
+                op, expr: inner, ..
             } => match op {
+                ast::UnaryOperator::Plus => self.handle_expression(ins, inner),
                 ast::UnaryOperator::PrefixIncrement | ast::UnaryOperator::PrefixDecrement => {
-                    let variable = match *inner.clone() {
-                        ast::Expression::Variable {
-                            v: ast::Variable { identifier },
-                            ty,
-                        } => tacky::Variable { identifier },
-                        _ => unreachable!(),
-                    };
+                    let lvalue = self.handle_lvalue(ins, inner);
+                    let current = self.load(ins, &lvalue, expr.ty().unwrap());
 
                     let op = match op {
                         ast::UnaryOperator::PrefixIncrement => tacky::BinaryOperator::Add,
@@ -460,28 +652,25 @@ impl TackyGen {
                         _ => unreachable!(),
                     };
 
-                    ins.push(tacky::Instruction::Binary {
+                    let one = self.constant(1, &expr.ty().unwrap());
+                    self.update(
+                        ins,
+                        &lvalue,
                         op,
-                        lhs: tacky::Value::Variable(variable.clone()),
-                        rhs: tacky::Value::Constant(1),
-                        dst: variable.clone(),
-                    });
-
-                    tacky::Value::Variable(variable)
+                        current,
+                        one,
+                        expr.ty().unwrap(),
+                        expr.ty().unwrap(),
+                    )
                 }
                 ast::UnaryOperator::PostfixIncrement | ast::UnaryOperator::PostfixDecrement => {
-                    let variable = match *inner.clone() {
-                        ast::Expression::Variable {
-                            v: ast::Variable { identifier },
-                            ty,
-                        } => tacky::Variable { identifier },
-                        _ => unreachable!(),
-                    };
+                    let lvalue = self.handle_lvalue(ins, inner);
+                    let current = self.load(ins, &lvalue, expr.ty().unwrap());
 
-                    let prev = self.fresh_variable();
+                    let prev = self.fresh_variable(expr.ty().unwrap());
 
                     ins.push(tacky::Instruction::Copy {
-                        src: tacky::Value::Variable(variable.clone()),
+                        src: current.clone(),
                         dst: prev.clone(),
                     });
 
@@ -491,19 +680,38 @@ impl TackyGen {
                         _ => unreachable!(),
                     };
 
-                    ins.push(tacky::Instruction::Binary {
+                    let one = self.constant(1, &expr.ty().unwrap());
+                    self.update(
+                        ins,
+                        &lvalue,
                         op,
-                        lhs: tacky::Value::Variable(variable.clone()),
-                        rhs: tacky::Value::Constant(1),
-                        dst: variable.clone(),
-                    });
+                        current,
+                        one,
+                        expr.ty().unwrap(),
+                        expr.ty().unwrap(),
+                    );
 
                     tacky::Value::Variable(prev)
                 }
 
+                // there is no negation instruction for doubles, but `-0.0 - x` flips just the sign
+                ast::UnaryOperator::Negate if expr.ty() == Some(ast::Type::Double) => {
+                    let src = self.handle_expression(ins, inner);
+                    let dst = self.fresh_variable(ast::Type::Double);
+                    let negative_zero = self.double_constant(-0.0);
+
+                    ins.push(tacky::Instruction::Binary {
+                        op: tacky::BinaryOperator::Subtract,
+                        lhs: negative_zero,
+                        rhs: src,
+                        dst: dst.clone(),
+                    });
+
+                    tacky::Value::Variable(dst)
+                }
                 _ => {
                     let src = self.handle_expression(ins, inner);
-                    let dst = self.fresh_variable();
+                    let dst = self.fresh_variable(expr.ty().unwrap());
                     let op = Self::handle_unary_operator(*op);
 
                     ins.push(tacky::Instruction::Unary {
@@ -515,9 +723,9 @@ impl TackyGen {
                     tacky::Value::Variable(dst)
                 }
             },
-            ast::Expression::Binary { op, lhs, rhs, ty } => match op {
+            ast::Expression::Binary { op, lhs, rhs, .. } => match op {
                 ast::BinaryOperator::LogicalAnd => {
-                    let dst = self.fresh_variable();
+                    let dst = self.fresh_variable(expr.ty().unwrap());
 
                     let label_false = self.fresh_label(Some("and_false"));
                     let label_end = self.fresh_label(Some("and_end"));
@@ -535,7 +743,7 @@ impl TackyGen {
                     });
 
                     ins.push(tacky::Instruction::Copy {
-                        src: tacky::Value::Constant(1),
+                        src: tacky::Value::Constant(tacky::Constant::Int(1)),
                         dst: dst.clone(),
                     });
                     ins.push(tacky::Instruction::Jump {
@@ -545,7 +753,7 @@ impl TackyGen {
                     ins.push(tacky::Instruction::Label(label_false));
 
                     ins.push(tacky::Instruction::Copy {
-                        src: tacky::Value::Constant(0),
+                        src: tacky::Value::Constant(tacky::Constant::Int(0)),
                         dst: dst.clone(),
                     });
 
@@ -554,7 +762,7 @@ impl TackyGen {
                     tacky::Value::Variable(dst)
                 }
                 ast::BinaryOperator::LogicalOr => {
-                    let dst = self.fresh_variable();
+                    let dst = self.fresh_variable(expr.ty().unwrap());
 
                     let label_true = self.fresh_label(Some("or_true"));
                     let label_end = self.fresh_label(Some("or_end"));
@@ -572,7 +780,7 @@ impl TackyGen {
                     });
 
                     ins.push(tacky::Instruction::Copy {
-                        src: tacky::Value::Constant(0),
+                        src: tacky::Value::Constant(tacky::Constant::Int(0)),
                         dst: dst.clone(),
                     });
                     ins.push(tacky::Instruction::Jump {
@@ -582,7 +790,7 @@ impl TackyGen {
                     ins.push(tacky::Instruction::Label(label_true));
 
                     ins.push(tacky::Instruction::Copy {
-                        src: tacky::Value::Constant(1),
+                        src: tacky::Value::Constant(tacky::Constant::Int(1)),
                         dst: dst.clone(),
                     });
 
@@ -590,10 +798,16 @@ impl TackyGen {
 
                     tacky::Value::Variable(dst)
                 }
+                ast::BinaryOperator::Add | ast::BinaryOperator::Subtract
+                    if matches!(lhs.ty(), Some(ast::Type::Pointer(_)))
+                        || matches!(rhs.ty(), Some(ast::Type::Pointer(_))) =>
+                {
+                    self.handle_pointer_binary(ins, *op, lhs, rhs)
+                }
                 _ => {
                     let lhs = self.handle_expression(ins, lhs);
                     let rhs = self.handle_expression(ins, rhs);
-                    let dst = self.fresh_variable();
+                    let dst = self.fresh_variable(expr.ty().unwrap());
                     let op = Self::handle_binary_operator(*op);
 
                     ins.push(tacky::Instruction::Binary {
@@ -608,47 +822,58 @@ impl TackyGen {
             },
             ast::Expression::Variable {
                 v: ast::Variable { identifier },
-                ty,
+                ..
             } => tacky::Value::Variable(tacky::Variable {
                 identifier: identifier.clone(),
             }),
-            ast::Expression::Assignment { op, lhs, rhs, ty } => {
-                let lhs_variable = match *lhs.clone() {
-                    ast::Expression::Variable {
-                        v: ast::Variable { identifier },
-                        ty,
-                    } => tacky::Variable { identifier },
-                    _ => unreachable!(),
-                };
-
+            ast::Expression::Assignment { op, lhs, rhs, .. } => {
+                let lvalue = self.handle_lvalue(ins, lhs);
                 let rhs_value = self.handle_expression(ins, rhs);
 
                 match op {
-                    ast::AssignmentOperator::Assign => {
-                        ins.push(tacky::Instruction::Copy {
-                            src: rhs_value,
-                            dst: lhs_variable.clone(),
-                        });
-                    }
+                    ast::AssignmentOperator::Assign => match lvalue {
+                        Lvalue::Variable(variable) => {
+                            ins.push(tacky::Instruction::Copy {
+                                src: rhs_value,
+                                dst: variable.clone(),
+                            });
+
+                            tacky::Value::Variable(variable)
+                        }
+                        Lvalue::Dereferenced(ptr) => {
+                            ins.push(tacky::Instruction::Store {
+                                src: rhs_value.clone(),
+                                dst_ptr: ptr,
+                            });
+
+                            rhs_value
+                        }
+                    },
                     _ => {
-                        ins.push(tacky::Instruction::Binary {
-                            op: Self::handle_assignment_operator(*op),
-                            lhs: tacky::Value::Variable(lhs_variable.clone()),
-                            rhs: rhs_value,
-                            dst: lhs_variable.clone(),
-                        });
+                        let current = self.load(ins, &lvalue, expr.ty().unwrap());
+
+                        self.update(
+                            ins,
+                            &lvalue,
+                            Self::handle_assignment_operator(*op),
+                            current,
+                            rhs_value,
+                            expr.ty().unwrap(),
+                            rhs.ty().unwrap(),
+                        )
                     }
                 }
-
-                tacky::Value::Variable(lhs_variable)
             }
             ast::Expression::Conditional {
                 condition,
                 then_expr,
                 else_expr,
-                ty,
+                ..
             } => {
-                let dst = self.fresh_variable();
+                let dst = match expr.ty().unwrap() {
+                    ast::Type::Void => None,
+                    ty => Some(self.fresh_variable(ty)),
+                };
 
                 let label_else = self.fresh_label(Some("cond_else"));
                 let label_end = self.fresh_label(Some("cond_end"));
@@ -660,31 +885,38 @@ impl TackyGen {
                 });
 
                 let then_value = self.handle_expression(ins, then_expr);
-                ins.push(tacky::Instruction::Copy {
-                    src: then_value,
-                    dst: dst.clone(),
-                });
+                if let Some(dst) = &dst {
+                    ins.push(tacky::Instruction::Copy {
+                        src: then_value,
+                        dst: dst.clone(),
+                    });
+                }
                 ins.push(tacky::Instruction::Jump {
                     target: label_end.clone(),
                 });
 
                 ins.push(tacky::Instruction::Label(label_else));
                 let else_value = self.handle_expression(ins, else_expr);
-                ins.push(tacky::Instruction::Copy {
-                    src: else_value,
-                    dst: dst.clone(),
-                });
+                if let Some(dst) = &dst {
+                    ins.push(tacky::Instruction::Copy {
+                        src: else_value,
+                        dst: dst.clone(),
+                    });
+                }
 
                 ins.push(tacky::Instruction::Label(label_end));
 
-                tacky::Value::Variable(dst)
+                dst.map_or(Self::VOID, tacky::Value::Variable)
             }
             ast::Expression::FunctionCall {
                 function,
                 arguments,
-                ty,
+                ..
             } => {
-                let dst = self.fresh_variable();
+                let dst = match expr.ty().unwrap() {
+                    ast::Type::Void => None,
+                    ty => Some(self.fresh_variable(ty)),
+                };
 
                 let mut args = Vec::new();
 
@@ -700,13 +932,500 @@ impl TackyGen {
                     dst: dst.clone(),
                 });
 
-                tacky::Value::Variable(dst)
+                dst.map_or(Self::VOID, tacky::Value::Variable)
+            }
+            ast::Expression::IndirectCall {
+                callee, arguments, ..
+            } => {
+                let dst = match expr.ty().unwrap() {
+                    ast::Type::Void => None,
+                    ty => Some(self.fresh_variable(ty)),
+                };
+
+                let function_ptr = self.handle_expression(ins, callee);
+
+                let mut args = Vec::new();
+
+                for arg in arguments {
+                    args.push(self.handle_expression(ins, arg));
+                }
+
+                ins.push(tacky::Instruction::IndirectCall {
+                    function_ptr,
+                    args,
+                    dst: dst.clone(),
+                });
+
+                dst.map_or(Self::VOID, tacky::Value::Variable)
+            }
+            ast::Expression::SizeOf { .. } | ast::Expression::SizeOfType { .. } => {
+                unreachable!("sizeof is folded during type checking")
+            }
+            ast::Expression::Comma { lhs, rhs, .. } => {
+                self.handle_expression(ins, lhs);
+                self.handle_expression(ins, rhs)
             }
             ast::Expression::Cast {
                 target_ty,
-                expr,
-                ty,
-            } => todo!(),
+                expr: inner,
+                ..
+            } => {
+                let value = self.handle_expression(ins, inner);
+                self.convert(ins, value, &inner.ty().unwrap(), target_ty)
+            }
+            ast::Expression::AddrOf { expr: inner, .. } => match self.handle_lvalue(ins, inner) {
+                Lvalue::Variable(variable) => {
+                    let dst = self.fresh_variable(expr.ty().unwrap());
+
+                    ins.push(tacky::Instruction::GetAddress {
+                        src: tacky::Value::Variable(variable),
+                        dst: dst.clone(),
+                    });
+
+                    tacky::Value::Variable(dst)
+                }
+                // `&*p` is just `p`
+                Lvalue::Dereferenced(ptr) => ptr,
+            },
+            ast::Expression::Dereference { .. } | ast::Expression::Subscript { .. } => {
+                let lvalue = self.handle_lvalue(ins, expr);
+                self.load(ins, &lvalue, expr.ty().unwrap())
+            }
+        }
+    }
+
+    fn handle_lvalue(
+        &mut self,
+        ins: &mut Vec<tacky::Instruction>,
+        expr: &ast::Expression,
+    ) -> Lvalue {
+        match expr {
+            ast::Expression::Variable {
+                v: ast::Variable { identifier },
+                ..
+            } => Lvalue::Variable(tacky::Variable {
+                identifier: identifier.clone(),
+            }),
+            ast::Expression::Dereference { expr: inner, .. } => {
+                Lvalue::Dereferenced(self.handle_expression(ins, inner))
+            }
+            ast::Expression::Subscript {
+                expr: inner, index, ..
+            } => {
+                let ptr = self.handle_expression(ins, inner);
+                let index = self.handle_expression(ins, index);
+                let ptr_ty = inner.ty().unwrap();
+
+                let dst = self.fresh_variable(ptr_ty.clone());
+                self.add_to_pointer(ins, tacky::BinaryOperator::Add, ptr, index, &ptr_ty, &dst);
+
+                Lvalue::Dereferenced(tacky::Value::Variable(dst))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Converts `value` from `inner_ty` to `target_ty`, like a cast.
+    fn convert(
+        &mut self,
+        ins: &mut Vec<tacky::Instruction>,
+        value: tacky::Value,
+        inner_ty: &ast::Type,
+        target_ty: &ast::Type,
+    ) -> tacky::Value {
+        if target_ty == inner_ty {
+            return value;
+        }
+
+        if *target_ty == ast::Type::Void {
+            return Self::VOID;
+        }
+
+        // there are no byte-sized conversions to or from doubles, so characters go through int
+        if (inner_ty.is_character() && *target_ty == ast::Type::Double)
+            || (*inner_ty == ast::Type::Double && target_ty.is_character())
+        {
+            let int = self.convert(ins, value, inner_ty, &ast::Type::Int);
+            return self.convert(ins, int, &ast::Type::Int, target_ty);
+        }
+
+        let dst = self.fresh_variable(target_ty.clone());
+
+        if *target_ty != ast::Type::Bool
+            && (*inner_ty == ast::Type::Double || *target_ty == ast::Type::Double)
+        {
+            self.convert_double(ins, value, inner_ty, target_ty, &dst);
+            return tacky::Value::Variable(dst);
+        }
+
+        let zero = self.constant(0, inner_ty);
+
+        ins.push(match (inner_ty, target_ty) {
+            // any nonzero value converts to 1
+            (_, ast::Type::Bool) => tacky::Instruction::Binary {
+                op: tacky::BinaryOperator::NotEqual,
+                lhs: value,
+                rhs: zero,
+                dst: dst.clone(),
+            },
+            _ => match target_ty.size().cmp(&inner_ty.size()) {
+                std::cmp::Ordering::Greater if inner_ty.is_signed() => {
+                    tacky::Instruction::SignExtend {
+                        src: value,
+                        dst: dst.clone(),
+                    }
+                }
+                std::cmp::Ordering::Greater => tacky::Instruction::ZeroExtend {
+                    src: value,
+                    dst: dst.clone(),
+                },
+                std::cmp::Ordering::Less => tacky::Instruction::Truncate {
+                    src: value,
+                    dst: dst.clone(),
+                },
+                // e.g. between long and pointers, only the type changes
+                std::cmp::Ordering::Equal => tacky::Instruction::Copy {
+                    src: value,
+                    dst: dst.clone(),
+                },
+            },
+        });
+
+        tacky::Value::Variable(dst)
+    }
+
+    /// Reads the current value of an lvalue.
+    fn load(
+        &mut self,
+        ins: &mut Vec<tacky::Instruction>,
+        lvalue: &Lvalue,
+        ty: ast::Type,
+    ) -> tacky::Value {
+        match lvalue {
+            Lvalue::Variable(variable) => tacky::Value::Variable(variable.clone()),
+            Lvalue::Dereferenced(ptr) => {
+                let dst = self.fresh_variable(ty);
+
+                ins.push(tacky::Instruction::Load {
+                    src_ptr: ptr.clone(),
+                    dst: dst.clone(),
+                });
+
+                tacky::Value::Variable(dst)
+            }
+        }
+    }
+
+    /// Computes `current op rhs` in the type of `rhs` and writes the result back to the lvalue,
+    /// converted to its type `ty`.
+    #[allow(clippy::too_many_arguments)]
+    fn update(
+        &mut self,
+        ins: &mut Vec<tacky::Instruction>,
+        lvalue: &Lvalue,
+        op: tacky::BinaryOperator,
+        current: tacky::Value,
+        rhs: tacky::Value,
+        ty: ast::Type,
+        rhs_ty: ast::Type,
+    ) -> tacky::Value {
+        let dst = match lvalue {
+            Lvalue::Variable(variable) => variable.clone(),
+            Lvalue::Dereferenced(_) => self.fresh_variable(ty.clone()),
+        };
+
+        if let ast::Type::Pointer(_) = ty {
+            self.add_to_pointer(ins, op, current, rhs, &ty, &dst);
+        } else if rhs_ty == ty {
+            ins.push(tacky::Instruction::Binary {
+                op,
+                lhs: current,
+                rhs,
+                dst: dst.clone(),
+            });
+        } else {
+            let converted = self.convert(ins, current, &ty, &rhs_ty);
+            let result = self.fresh_variable(rhs_ty.clone());
+            ins.push(tacky::Instruction::Binary {
+                op,
+                lhs: converted,
+                rhs,
+                dst: result.clone(),
+            });
+
+            let src = self.convert(ins, tacky::Value::Variable(result), &rhs_ty, &ty);
+            ins.push(tacky::Instruction::Copy {
+                src,
+                dst: dst.clone(),
+            });
+        }
+
+        if let Lvalue::Dereferenced(ptr) = lvalue {
+            ins.push(tacky::Instruction::Store {
+                src: tacky::Value::Variable(dst.clone()),
+                dst_ptr: ptr.clone(),
+            });
+        }
+
+        tacky::Value::Variable(dst)
+    }
+
+    fn handle_pointer_binary(
+        &mut self,
+        ins: &mut Vec<tacky::Instruction>,
+        op: ast::BinaryOperator,
+        lhs: &ast::Expression,
+        rhs: &ast::Expression,
+    ) -> tacky::Value {
+        let ty_lhs = lhs.ty().unwrap();
+        let ty_rhs = rhs.ty().unwrap();
+
+        let lhs = self.handle_expression(ins, lhs);
+        let rhs = self.handle_expression(ins, rhs);
+
+        match (&ty_lhs, &ty_rhs) {
+            // the distance between two pointers is counted in elements
+            (ast::Type::Pointer(referenced), ast::Type::Pointer(_)) => {
+                let diff = self.fresh_variable(ast::Type::Long);
+                ins.push(tacky::Instruction::Binary {
+                    op: tacky::BinaryOperator::Subtract,
+                    lhs,
+                    rhs,
+                    dst: diff.clone(),
+                });
+
+                let dst = self.fresh_variable(ast::Type::Long);
+                ins.push(tacky::Instruction::Binary {
+                    op: tacky::BinaryOperator::Divide,
+                    lhs: tacky::Value::Variable(diff),
+                    rhs: tacky::Value::Constant(tacky::Constant::Long(referenced.size().unwrap())),
+                    dst: dst.clone(),
+                });
+
+                tacky::Value::Variable(dst)
+            }
+            (ast::Type::Pointer(_), _) => {
+                let dst = self.fresh_variable(ty_lhs.clone());
+                let op = Self::handle_binary_operator(op);
+                self.add_to_pointer(ins, op, lhs, rhs, &ty_lhs, &dst);
+
+                tacky::Value::Variable(dst)
+            }
+            _ => {
+                let dst = self.fresh_variable(ty_rhs.clone());
+                self.add_to_pointer(ins, tacky::BinaryOperator::Add, rhs, lhs, &ty_rhs, &dst);
+
+                tacky::Value::Variable(dst)
+            }
+        }
+    }
+
+    /// Moves `ptr` by `index` elements, backwards for `Subtract`.
+    fn add_to_pointer(
+        &mut self,
+        ins: &mut Vec<tacky::Instruction>,
+        op: tacky::BinaryOperator,
+        ptr: tacky::Value,
+        index: tacky::Value,
+        ptr_ty: &ast::Type,
+        dst: &tacky::Variable,
+    ) {
+        let ast::Type::Pointer(referenced) = ptr_ty else {
+            unreachable!()
+        };
+
+        let index = match (op, index) {
+            (tacky::BinaryOperator::Add, index) => index,
+            (tacky::BinaryOperator::Subtract, tacky::Value::Constant(n)) => {
+                tacky::Value::Constant(tacky::Constant::Long(n.value().wrapping_neg()))
+            }
+            (tacky::BinaryOperator::Subtract, index) => {
+                let negated = self.fresh_variable(ast::Type::Long);
+                ins.push(tacky::Instruction::Unary {
+                    op: tacky::UnaryOperator::Negate,
+                    src: index,
+                    dst: negated.clone(),
+                });
+
+                tacky::Value::Variable(negated)
+            }
+            _ => unreachable!(),
+        };
+
+        ins.push(tacky::Instruction::AddPtr {
+            ptr,
+            index,
+            scale: referenced.size().unwrap(),
+            dst: dst.clone(),
+        });
+    }
+
+    /// Converts between doubles and integers. The hardware only converts signed integers, so
+    /// unsigned ones take a detour.
+    fn convert_double(
+        &mut self,
+        ins: &mut Vec<tacky::Instruction>,
+        value: tacky::Value,
+        from: &ast::Type,
+        to: &ast::Type,
+        dst: &tacky::Variable,
+    ) {
+        match (from, to) {
+            (ast::Type::Double, ast::Type::Int | ast::Type::Long) => {
+                ins.push(tacky::Instruction::DoubleToInt {
+                    src: value,
+                    dst: dst.clone(),
+                });
+            }
+            (ast::Type::Double, ast::Type::UInt) => {
+                let wide = self.fresh_variable(ast::Type::Long);
+                ins.push(tacky::Instruction::DoubleToInt {
+                    src: value,
+                    dst: wide.clone(),
+                });
+                ins.push(tacky::Instruction::Truncate {
+                    src: tacky::Value::Variable(wide),
+                    dst: dst.clone(),
+                });
+            }
+            // values of 2^63 and above don't fit a long, so they are shifted down and back up
+            (ast::Type::Double, ast::Type::ULong) => {
+                let upper_bound = self.double_constant(9223372036854775808.0);
+                let label_large = self.fresh_label(Some("large_double"));
+                let label_end = self.fresh_label(Some("double_end"));
+
+                let is_large = self.fresh_variable(ast::Type::Int);
+                ins.push(tacky::Instruction::Binary {
+                    op: tacky::BinaryOperator::GreaterOrEqual,
+                    lhs: value.clone(),
+                    rhs: upper_bound.clone(),
+                    dst: is_large.clone(),
+                });
+                ins.push(tacky::Instruction::JumpIfNotZero {
+                    condition: tacky::Value::Variable(is_large),
+                    target: label_large.clone(),
+                });
+                ins.push(tacky::Instruction::DoubleToInt {
+                    src: value.clone(),
+                    dst: dst.clone(),
+                });
+                ins.push(tacky::Instruction::Jump {
+                    target: label_end.clone(),
+                });
+
+                ins.push(tacky::Instruction::Label(label_large));
+                let shifted = self.fresh_variable(ast::Type::Double);
+                ins.push(tacky::Instruction::Binary {
+                    op: tacky::BinaryOperator::Subtract,
+                    lhs: value,
+                    rhs: upper_bound,
+                    dst: shifted.clone(),
+                });
+                ins.push(tacky::Instruction::DoubleToInt {
+                    src: tacky::Value::Variable(shifted),
+                    dst: dst.clone(),
+                });
+                ins.push(tacky::Instruction::Binary {
+                    op: tacky::BinaryOperator::Add,
+                    lhs: tacky::Value::Variable(dst.clone()),
+                    rhs: tacky::Value::Constant(tacky::Constant::ULong(1 << 63)),
+                    dst: dst.clone(),
+                });
+
+                ins.push(tacky::Instruction::Label(label_end));
+            }
+            (ast::Type::Int | ast::Type::Long, ast::Type::Double) => {
+                ins.push(tacky::Instruction::IntToDouble {
+                    src: value,
+                    dst: dst.clone(),
+                });
+            }
+            (ast::Type::UInt | ast::Type::Bool, ast::Type::Double) => {
+                let wide = self.fresh_variable(ast::Type::Long);
+                ins.push(tacky::Instruction::ZeroExtend {
+                    src: value,
+                    dst: wide.clone(),
+                });
+                ins.push(tacky::Instruction::IntToDouble {
+                    src: tacky::Value::Variable(wide),
+                    dst: dst.clone(),
+                });
+            }
+            // values that don't fit a long are halved, keeping the lowest bit so they round
+            // the same way
+            (ast::Type::ULong, ast::Type::Double) => {
+                let label_large = self.fresh_label(Some("large_ulong"));
+                let label_end = self.fresh_label(Some("ulong_end"));
+
+                let signed = self.fresh_variable(ast::Type::Long);
+                ins.push(tacky::Instruction::Copy {
+                    src: value.clone(),
+                    dst: signed.clone(),
+                });
+                let is_large = self.fresh_variable(ast::Type::Int);
+                ins.push(tacky::Instruction::Binary {
+                    op: tacky::BinaryOperator::LessThan,
+                    lhs: tacky::Value::Variable(signed.clone()),
+                    rhs: tacky::Value::Constant(tacky::Constant::Long(0)),
+                    dst: is_large.clone(),
+                });
+                ins.push(tacky::Instruction::JumpIfNotZero {
+                    condition: tacky::Value::Variable(is_large),
+                    target: label_large.clone(),
+                });
+                ins.push(tacky::Instruction::IntToDouble {
+                    src: tacky::Value::Variable(signed),
+                    dst: dst.clone(),
+                });
+                ins.push(tacky::Instruction::Jump {
+                    target: label_end.clone(),
+                });
+
+                ins.push(tacky::Instruction::Label(label_large));
+                let half = self.fresh_variable(ast::Type::ULong);
+                ins.push(tacky::Instruction::Binary {
+                    op: tacky::BinaryOperator::ShiftRight,
+                    lhs: value.clone(),
+                    rhs: tacky::Value::Constant(tacky::Constant::ULong(1)),
+                    dst: half.clone(),
+                });
+                let lowest_bit = self.fresh_variable(ast::Type::ULong);
+                ins.push(tacky::Instruction::Binary {
+                    op: tacky::BinaryOperator::BitwiseAnd,
+                    lhs: value,
+                    rhs: tacky::Value::Constant(tacky::Constant::ULong(1)),
+                    dst: lowest_bit.clone(),
+                });
+                ins.push(tacky::Instruction::Binary {
+                    op: tacky::BinaryOperator::BitwiseOr,
+                    lhs: tacky::Value::Variable(half.clone()),
+                    rhs: tacky::Value::Variable(lowest_bit),
+                    dst: half.clone(),
+                });
+                ins.push(tacky::Instruction::IntToDouble {
+                    src: tacky::Value::Variable(half),
+                    dst: dst.clone(),
+                });
+                ins.push(tacky::Instruction::Binary {
+                    op: tacky::BinaryOperator::Add,
+                    lhs: tacky::Value::Variable(dst.clone()),
+                    rhs: tacky::Value::Variable(dst.clone()),
+                    dst: dst.clone(),
+                });
+
+                ins.push(tacky::Instruction::Label(label_end));
+            }
+            _ => unreachable!("not a conversion between doubles and integers"),
+        }
+    }
+
+    fn handle_constant(c: &ast::Constant) -> tacky::Constant {
+        match c {
+            ast::Constant::ConstantInt(n) => tacky::Constant::Int(*n),
+            ast::Constant::ConstantLong(n) => tacky::Constant::Long(*n),
+            ast::Constant::ConstantUInt(n) => tacky::Constant::UInt(*n),
+            ast::Constant::ConstantULong(n) => tacky::Constant::ULong(*n),
+            ast::Constant::ConstantDouble(_) => unreachable!("doubles are read from memory"),
         }
     }
 
@@ -715,7 +1434,8 @@ impl TackyGen {
             ast::UnaryOperator::Negate => tacky::UnaryOperator::Negate,
             ast::UnaryOperator::Complement => tacky::UnaryOperator::Complement,
             ast::UnaryOperator::Not => tacky::UnaryOperator::Not,
-            ast::UnaryOperator::PrefixIncrement
+            ast::UnaryOperator::Plus
+            | ast::UnaryOperator::PrefixIncrement
             | ast::UnaryOperator::PrefixDecrement
             | ast::UnaryOperator::PostfixIncrement
             | ast::UnaryOperator::PostfixDecrement => unreachable!(),
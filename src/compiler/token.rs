@@ -1,4 +1,5 @@
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Token {
     /// e.g. `main`
     Identifier(String),
@@ -9,6 +10,28 @@ pub enum Token {
     IntKeyword,
     /// `long`
     LongKeyword,
+    /// `_Bool`
+    BoolKeyword,
+    /// `char`
+    CharKeyword,
+    /// `signed`
+    SignedKeyword,
+    /// `unsigned`
+    UnsignedKeyword,
+    /// `double`
+    DoubleKeyword,
+    /// `enum`
+    EnumKeyword,
+    /// `struct`, which isn't supported yet
+    StructKeyword,
+    /// `union`, which isn't supported yet
+    UnionKeyword,
+    /// `const`
+    ConstKeyword,
+    /// `volatile`
+    VolatileKeyword,
+    /// `restrict`, or `__restrict` as in glibc headers
+    RestrictKeyword,
 
     /// `return`
     ReturnKeyword,
@@ -38,11 +61,21 @@ pub enum Token {
     CaseKeyword,
     /// `default`
     DefaultKeyword,
+    /// `sizeof`
+    SizeofKeyword,
 
     /// 4 byte (32 bit) integer
     ConstantInt(String),
     /// 8 byte (64 bit) integer
     ConstantLong(String),
+    /// 4 byte (32 bit) unsigned integer, e.g. `42u`
+    ConstantUInt(String),
+    /// 8 byte (64 bit) unsigned integer, e.g. `42ul`
+    ConstantULong(String),
+    /// e.g. `1.5`, `1e10` or `.5`
+    ConstantDouble(String),
+    /// e.g. `'a'`
+    ConstantChar(char),
 
     /// `(`
     OpenParen,
@@ -52,6 +85,10 @@ pub enum Token {
     OpenBrace,
     /// `}`
     CloseBrace,
+    /// `[`
+    OpenBracket,
+    /// `]`
+    CloseBracket,
     /// `;`
     Semicolon,
     /// `~`
@@ -86,6 +123,8 @@ pub enum Token {
     Colon,
     /// `,`
     Comma,
+    /// `.`
+    Dot,
 
     /// `<<`
     LessLess,
@@ -123,6 +162,8 @@ pub enum Token {
     MinusMinus,
     /// `++`
     PlusPlus,
+    /// `->`
+    Arrow,
 
     /// `<<=`
     LessLessEqual,
@@ -1,43 +1,130 @@
-use std::{path::PathBuf, process::Command};
-
-pub fn preprocess(input: &PathBuf, output: &PathBuf) {
-    let command_output = Command::new("gcc")
-        .arg("-E")
-        .arg("-P")
-        .arg(input)
-        .arg("-o")
-        .arg(output)
-        .output()
-        .unwrap();
-
-    if !command_output.status.success() {
-        panic!("Failed to preprocess: {:?}", command_output);
+use std::{ffi::OsString, path::PathBuf, process::Command};
+
+use crate::compiler::CompilerError;
+
+/// Compilers to try when `CC` isn't set, in order.
+const C_COMPILERS: [&str; 3] = ["cc", "clang", "gcc"];
+
+/// The C compiler to preprocess, assemble and link with.
+fn c_compiler() -> Result<OsString, CompilerError> {
+    choose_c_compiler(std::env::var_os("CC"), &C_COMPILERS)
+        .map_err(|message| CompilerError::Driver { message })
+}
+
+/// `cc` if it's set, or else the first of `candidates` that can be run.
+fn choose_c_compiler(cc: Option<OsString>, candidates: &[&str]) -> Result<OsString, String> {
+    if let Some(cc) = cc.filter(|cc| !cc.is_empty()) {
+        return Ok(cc);
     }
+
+    candidates
+        .iter()
+        .find(|candidate| Command::new(candidate).arg("--version").output().is_ok())
+        .map(OsString::from)
+        .ok_or_else(|| format!("No C compiler found, tried {}", candidates.join(", ")))
 }
 
-pub fn assemble(input: &PathBuf, output: &PathBuf) {
-    let command_output = Command::new("gcc")
-        .arg("-c")
-        .arg(input)
-        .arg("-o")
-        .arg(output)
-        .output()
-        .unwrap();
-
-    if !command_output.status.success() {
-        panic!("Failed to assemble: {:?}", command_output);
+/// Runs `command`, reporting what it printed to stderr if it fails to `step`.
+fn run(command: &mut Command, step: &str) -> Result<(), CompilerError> {
+    let output = command.output().map_err(|e| CompilerError::Driver {
+        message: format!(
+            "Could not run {}: {e}",
+            command.get_program().to_string_lossy()
+        ),
+    })?;
+
+    if output.status.success() {
+        return Ok(());
     }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let details = match stderr.trim_end() {
+        "" => output.status.to_string(),
+        stderr => stderr.to_string(),
+    };
+
+    Err(CompilerError::Driver {
+        message: format!("Failed to {step}: {details}"),
+    })
+}
+
+pub fn preprocess(input: &PathBuf, output: &PathBuf) -> Result<(), CompilerError> {
+    run(
+        Command::new(c_compiler()?)
+            .arg("-E")
+            .arg(input)
+            .arg("-o")
+            .arg(output),
+        "preprocess",
+    )
 }
 
-pub fn assemble_and_link(input: &PathBuf, output: &PathBuf) {
-    let command_output = Command::new("gcc")
-        .arg(input)
-        .arg("-o")
-        .arg(output)
-        .output()
-        .unwrap();
+pub fn assemble(input: &PathBuf, output: &PathBuf) -> Result<(), CompilerError> {
+    run(
+        Command::new(c_compiler()?)
+            .arg("-c")
+            .arg(input)
+            .arg("-o")
+            .arg(output),
+        "assemble",
+    )
+}
+
+pub fn assemble_and_link(inputs: &[PathBuf], output: &PathBuf) -> Result<(), CompilerError> {
+    run(
+        Command::new(c_compiler()?)
+            .args(inputs)
+            .arg("-o")
+            .arg(output),
+        "link",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_choose_c_compiler() {
+        assert_eq!(
+            choose_c_compiler(Some(OsString::from("my-cc")), &C_COMPILERS),
+            Ok(OsString::from("my-cc"))
+        );
+
+        // an empty `CC` counts as unset
+        assert_eq!(
+            choose_c_compiler(Some(OsString::new()), &["cco-missing-cc", "true"]),
+            Ok(OsString::from("true"))
+        );
+
+        assert_eq!(
+            choose_c_compiler(None, &["cco-missing-cc", "cco-missing-clang"]),
+            Err("No C compiler found, tried cco-missing-cc, cco-missing-clang".to_string())
+        );
+    }
+
+    #[test]
+    fn test_run_errors() {
+        let Err(CompilerError::Driver { message }) =
+            run(Command::new("cco-missing-cc").arg("-c"), "assemble")
+        else {
+            panic!("expected a driver error");
+        };
+        assert!(
+            message.starts_with("Could not run cco-missing-cc: "),
+            "{message}"
+        );
+
+        assert_eq!(
+            run(
+                Command::new("sh").args(["-c", "echo oops >&2; exit 1"]),
+                "link"
+            ),
+            Err(CompilerError::Driver {
+                message: "Failed to link: oops".to_string()
+            })
+        );
 
-    if !command_output.status.success() {
-        panic!("Failed to link: {:?}", command_output);
+        assert_eq!(run(&mut Command::new("true"), "link"), Ok(()));
     }
 }
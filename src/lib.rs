@@ -0,0 +1,10 @@
+//! A C compiler targeting x86-64 assembly.
+//!
+//! [`compile_str`] runs the whole pipeline in memory. The [`driver`] module shells out to a C
+//! compiler (`$CC`, or else the first of cc, clang and gcc) for preprocessing, assembling and
+//! linking.
+
+pub mod compiler;
+pub mod driver;
+
+pub use compiler::{compile_str, compile_str_with_options, CompilerError, CompilerStage};
@@ -1,14 +1,18 @@
-use clap::Parser;
-use compiler::CompilerStage;
+use std::path::PathBuf;
 
-mod compiler;
-mod driver;
+use cco::{
+    compiler::{
+        self, CompileOptions, CompilerError, CompilerStage, OptLevel, Optimizations, Target,
+    },
+    driver,
+};
+use clap::{error::ErrorKind, CommandFactory, Parser};
 
 #[derive(Parser, Debug)]
 #[command(about, long_about = None)]
 struct Args {
-    #[arg(help = "Path to the C source file")]
-    path: String,
+    #[arg(required = true, help = "Paths to the C source files")]
+    paths: Vec<String>,
 
     #[arg(
         long,
@@ -18,6 +22,15 @@ struct Args {
     )]
     lex: bool,
 
+    #[arg(
+        long,
+        group = "stage",
+        conflicts_with_all = &["assembly", "object"],
+        help = "Stop after lexing and print the tokens as JSON"
+    )]
+    #[cfg(feature = "serde")]
+    emit_tokens: bool,
+
     #[arg(
         long,
         group = "stage",
@@ -26,6 +39,15 @@ struct Args {
     )]
     parse: bool,
 
+    #[arg(
+        long,
+        group = "stage",
+        conflicts_with_all = &["assembly", "object"],
+        help = "Stop after parsing and print the AST as JSON"
+    )]
+    #[cfg(feature = "serde")]
+    emit_ast: bool,
+
     #[arg(
         long,
         group = "stage",
@@ -34,6 +56,14 @@ struct Args {
     )]
     validate: bool,
 
+    #[arg(
+        long,
+        group = "stage",
+        conflicts_with_all = &["assembly", "object"],
+        help = "Stop after semantic analysis and print the symbol table"
+    )]
+    print_symbols: bool,
+
     #[arg(
         long,
         group = "stage",
@@ -55,31 +85,104 @@ struct Args {
 
     #[arg(long, short = 'c', help = "Emit object code, but do not link")]
     object: bool,
-}
 
-fn main() {
-    let args = Args::parse();
+    #[arg(
+        long,
+        short = 'o',
+        conflicts_with = "stage",
+        help = "Place the output into <OUTPUT> ('-' for stdout, only with -S)"
+    )]
+    output: Option<String>,
+
+    #[arg(long, short = 'O', help = "Enable all optimizations")]
+    optimize: bool,
+
+    #[arg(long, help = "Fold constant expressions in the IR")]
+    fold_constants: bool,
+
+    #[arg(long, help = "Remove unreachable code and redundant jumps from the IR")]
+    eliminate_unreachable_code: bool,
+
+    #[arg(long, help = "Send jumps in the IR straight to their final target")]
+    thread_jumps: bool,
 
-    let input_path = std::fs::canonicalize(&args.path).unwrap();
-    assert!(input_path.is_file());
+    #[arg(long, help = "Remove redundant moves from the assembly")]
+    remove_redundant_moves: bool,
 
-    let input_filename = input_path.file_name().unwrap().to_str().unwrap();
-    assert!(input_filename.ends_with(".c"));
+    #[arg(
+        long,
+        help = "Keep every variable on the stack instead of in registers"
+    )]
+    no_register_allocation: bool,
 
-    let input_filename_stem = input_path.file_stem().unwrap().to_str().unwrap();
+    #[arg(long, help = "Treat warnings as errors")]
+    strict: bool,
 
-    let preprocessed_filename = format!("{}.i", input_filename_stem);
-    let preprocessed_path = input_path.with_file_name(preprocessed_filename);
+    #[arg(long, help = "Accept trailing commas after the arguments of a call")]
+    lenient: bool,
 
-    let object_filename = format!("{}.o", input_filename_stem);
-    let object_path = input_path.with_file_name(object_filename);
+    #[arg(short = 'g', help = "Emit line number information for debuggers")]
+    debug: bool,
 
-    let assembly_filename = format!("{}.s", input_filename_stem);
-    let assembly_path = input_path.with_file_name(assembly_filename);
+    #[arg(
+        long,
+        help = "Comment the assembly with the IR instructions it was generated from"
+    )]
+    annotate: bool,
+
+    #[arg(long, help = "Record the version of the compiler in the object file")]
+    ident: bool,
 
-    let binary_path = input_path.with_file_name(input_filename_stem);
+    #[arg(long, value_enum, help = "Target platform (defaults to the host)")]
+    target: Option<Target>,
 
-    driver::preprocess(&input_path, &preprocessed_path);
+    #[arg(long, help = "Keep the preprocessed and assembly files")]
+    save_temps: bool,
+}
+
+struct Input {
+    input_path: PathBuf,
+    preprocessed_path: PathBuf,
+    assembly_path: PathBuf,
+    object_path: PathBuf,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if args.output.as_deref() == Some(compiler::STDOUT_PATH) && !args.assembly {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "writing to stdout is only supported for assembly output (-S)",
+            )
+            .exit();
+    }
+
+    let Some(target) = args.target.or_else(Target::host) else {
+        Args::command()
+            .error(
+                ErrorKind::InvalidValue,
+                format!(
+                    "unsupported host OS {}, specify --target",
+                    std::env::consts::OS
+                ),
+            )
+            .exit();
+    };
+
+    let opt_level = if args.optimize {
+        OptLevel::O1
+    } else {
+        OptLevel::O0
+    };
+
+    let mut optimizations = Optimizations::from(opt_level);
+    optimizations.fold_constants |= args.fold_constants;
+    optimizations.eliminate_unreachable_code |= args.eliminate_unreachable_code;
+    optimizations.thread_jumps |= args.thread_jumps;
+    optimizations.remove_redundant_moves |= args.remove_redundant_moves;
+    optimizations.allocate_registers &= !args.no_register_allocation;
 
     let stage = if args.lex {
         CompilerStage::Lex
@@ -87,6 +190,8 @@ fn main() {
         CompilerStage::Parse
     } else if args.validate {
         CompilerStage::Validate
+    } else if args.print_symbols {
+        CompilerStage::Symbols
     } else if args.tacky {
         CompilerStage::Tacky
     } else if args.codegen {
@@ -94,19 +199,152 @@ fn main() {
     } else {
         CompilerStage::Full
     };
+    #[cfg(feature = "serde")]
+    let stage = if args.emit_tokens {
+        CompilerStage::LexJson
+    } else if args.emit_ast {
+        CompilerStage::ParseJson
+    } else {
+        stage
+    };
+
+    if args.paths.len() > 1 {
+        if stage != CompilerStage::Full {
+            Args::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "stage flags only support a single input file",
+                )
+                .exit();
+        }
+
+        if args.output.is_some() && (args.assembly || args.object) {
+            Args::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "cannot specify -o with -S or -c and multiple input files",
+                )
+                .exit();
+        }
+    }
+
+    let mut inputs = Vec::new();
+    let mut binary_path = None;
+
+    for path in &args.paths {
+        let input_path = std::fs::canonicalize(path).unwrap();
+        assert!(input_path.is_file());
+
+        let input_filename = input_path.file_name().unwrap().to_str().unwrap();
+        assert!(input_filename.ends_with(".c"));
+
+        let input_filename_stem = input_path.file_stem().unwrap().to_str().unwrap();
+
+        let preprocessed_filename = format!("{}.i", input_filename_stem);
+        let preprocessed_path = input_path.with_file_name(preprocessed_filename);
+
+        let object_filename = format!("{}.o", input_filename_stem);
+        let object_path = match &args.output {
+            Some(output) if args.object && !args.assembly => PathBuf::from(output),
+            _ => input_path.with_file_name(object_filename),
+        };
+
+        let assembly_filename = format!("{}.s", input_filename_stem);
+        let assembly_path = match &args.output {
+            Some(output) if args.assembly => PathBuf::from(output),
+            _ => input_path.with_file_name(assembly_filename),
+        };
+
+        if binary_path.is_none() {
+            binary_path = Some(match &args.output {
+                Some(output) if !args.assembly && !args.object => PathBuf::from(output),
+                _ => input_path.with_file_name(input_filename_stem),
+            });
+        }
+
+        inputs.push(Input {
+            input_path,
+            preprocessed_path,
+            assembly_path,
+            object_path,
+        });
+    }
+
+    let options = CompileOptions {
+        target,
+        optimizations,
+        strict: args.strict,
+        lenient: args.lenient,
+        debug_info: args.debug,
+        annotate: args.annotate,
+        ident: args.ident,
+    };
+
+    let mut defines_main = false;
 
-    compiler::compile(&preprocessed_path, &assembly_path, stage);
-    std::fs::remove_file(&preprocessed_path).unwrap();
+    for input in &inputs {
+        if let Err(e) = driver::preprocess(&input.input_path, &input.preprocessed_path) {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+
+        let result = compiler::compile(
+            &input.preprocessed_path,
+            &input.assembly_path,
+            stage,
+            options,
+        );
+        if args.save_temps {
+            eprintln!(
+                "Kept preprocessed file {}",
+                input.preprocessed_path.display()
+            );
+        } else {
+            std::fs::remove_file(&input.preprocessed_path).unwrap();
+        }
+
+        match result {
+            Ok(report) => {
+                for warning in report.warnings {
+                    eprintln!("warning: {warning}");
+                }
+                defines_main |= report.defines_main;
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+    }
 
     if args.assembly || stage != CompilerStage::Full {
         return;
     }
 
-    if args.object {
-        driver::assemble(&assembly_path, &object_path);
+    let result = if args.object {
+        inputs
+            .iter()
+            .try_for_each(|input| driver::assemble(&input.assembly_path, &input.object_path))
+    } else if !defines_main {
+        // rather than the linker's undefined reference from the C runtime
+        Err(CompilerError::Driver {
+            message: "No main function to link, use -c to compile without linking".to_string(),
+        })
     } else {
-        driver::assemble_and_link(&assembly_path, &binary_path);
+        let assembly_paths: Vec<_> = inputs.iter().map(|i| i.assembly_path.clone()).collect();
+        driver::assemble_and_link(&assembly_paths, &binary_path.unwrap())
+    };
+
+    for input in &inputs {
+        if args.save_temps {
+            eprintln!("Kept assembly file {}", input.assembly_path.display());
+        } else {
+            std::fs::remove_file(&input.assembly_path).unwrap();
+        }
     }
 
-    std::fs::remove_file(&assembly_path).unwrap();
+    if let Err(e) = result {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
 }
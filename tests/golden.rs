@@ -0,0 +1,77 @@
+//! Compiles each `.c` file in `tests/golden` and compares the assembly with the `.s` file next to
+//! it. Run with `UPDATE_GOLDEN=1` to rewrite the `.s` files after an intended codegen change.
+//!
+//! The files directly in `tests/golden` are compiled without optimizations, as by default, and the
+//! ones in `tests/golden/O1` with `-O`.
+
+use std::path::Path;
+
+use cco::{
+    compile_str_with_options,
+    compiler::{CompileOptions, OptLevel, Optimizations, Target},
+    CompilerStage,
+};
+
+#[test]
+fn test_golden_files() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+
+    let mut mismatches = Vec::new();
+    for (dir, level) in [(dir.clone(), OptLevel::O0), (dir.join("O1"), OptLevel::O1)] {
+        mismatches.extend(check_golden_files(&dir, level, update));
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "assembly differs from the golden files (rerun with UPDATE_GOLDEN=1 if intended):\n\n{}",
+        mismatches.join("\n")
+    );
+}
+
+/// Returns a description of every file in `dir` whose assembly differs from its golden file.
+fn check_golden_files(dir: &Path, level: OptLevel, update: bool) -> Vec<String> {
+    let options = CompileOptions {
+        target: Target::Linux,
+        optimizations: Optimizations::from(level),
+        strict: true,
+        ..CompileOptions::default()
+    };
+
+    let mut sources = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "c"))
+        .collect::<Vec<_>>();
+    sources.sort();
+    assert!(
+        !sources.is_empty(),
+        "no golden sources in {}",
+        dir.display()
+    );
+
+    let mut mismatches = Vec::new();
+
+    for source_path in sources {
+        let source = std::fs::read_to_string(&source_path).unwrap();
+        let assembly = compile_str_with_options(&source, CompilerStage::Full, options)
+            .unwrap_or_else(|e| panic!("{}: {e}", source_path.display()));
+
+        let golden_path = source_path.with_extension("s");
+        if update {
+            std::fs::write(&golden_path, &assembly).unwrap();
+            continue;
+        }
+
+        let expected = std::fs::read_to_string(&golden_path)
+            .unwrap_or_else(|e| panic!("{}: {e}", golden_path.display()));
+        if assembly != expected {
+            mismatches.push(format!(
+                "{}:\n--- expected\n{expected}\n--- actual\n{assembly}",
+                golden_path.display()
+            ));
+        }
+    }
+
+    mismatches
+}
@@ -0,0 +1,12 @@
+int twice(int a) {
+    return a + a;
+}
+
+int square_plus(int a) {
+    int b = a * a;
+    return b + a;
+}
+
+int main(void) {
+    return square_plus(twice(3));
+}
@@ -0,0 +1,43 @@
+	.globl	twice
+	.text
+twice:
+	pushq	%rbx
+	movl	%edi, %ebx
+	addl	%ebx, %ebx
+	movl	%ebx, %eax
+	popq	%rbx
+	ret
+
+	.globl	square_plus
+	.text
+square_plus:
+	pushq	%rbx
+	pushq	%r12
+	movl	%edi, %ebx
+	movl	%ebx, %r12d
+	imull	%ebx, %r12d
+	addl	%ebx, %r12d
+	movl	%r12d, %eax
+	popq	%r12
+	popq	%rbx
+	ret
+
+	.globl	main
+	.text
+main:
+	pushq	%rbp
+	movq	%rsp, %rbp
+	subq	$8, %rsp
+	pushq	%rbx
+	movl	$3, %edi
+	call	twice
+	movl	%eax, %ebx
+	movl	%ebx, %edi
+	call	square_plus
+	movl	%eax, %ebx
+	popq	%rbx
+	movq	%rbp, %rsp
+	popq	%rbp
+	ret
+
+	.section .note.GNU-stack,"",@progbits
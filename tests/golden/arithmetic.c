@@ -0,0 +1,5 @@
+int main(void) {
+    int a = 6;
+    long b = 7L;
+    return (a * b - 2) / 4 % 5;
+}
@@ -0,0 +1,42 @@
+	.globl	main
+	.text
+main:
+	pushq	%rbp
+	movq	%rsp, %rbp
+	subq	$96, %rsp
+	movl	$6, -4(%rbp)
+	movq	$7, -16(%rbp)
+	movslq	-4(%rbp), %r11
+	movq	%r11, -24(%rbp)
+	movq	-24(%rbp), %r10
+	movq	%r10, -32(%rbp)
+	movq	-32(%rbp), %r11
+	imulq	-16(%rbp), %r11
+	movq	%r11, -32(%rbp)
+	movq	$2, -40(%rbp)
+	movq	-32(%rbp), %r10
+	movq	%r10, -48(%rbp)
+	movq	-40(%rbp), %r10
+	subq	%r10, -48(%rbp)
+	movq	$4, -56(%rbp)
+	movq	-48(%rbp), %rax
+	cqo
+	idivq	-56(%rbp)
+	movq	%rax, -64(%rbp)
+	movq	$5, -72(%rbp)
+	movq	-64(%rbp), %rax
+	cqo
+	idivq	-72(%rbp)
+	movq	%rdx, -80(%rbp)
+	movl	-80(%rbp), %r10d
+	movl	%r10d, -84(%rbp)
+	movl	-84(%rbp), %eax
+	movq	%rbp, %rsp
+	popq	%rbp
+	ret
+	movl	$0, %eax
+	movq	%rbp, %rsp
+	popq	%rbp
+	ret
+
+	.section .note.GNU-stack,"",@progbits
@@ -0,0 +1,9 @@
+int putchar(int c);
+
+int twice(int x) {
+    return x * 2;
+}
+
+int main(void) {
+    return putchar(twice(33));
+}
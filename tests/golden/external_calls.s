@@ -0,0 +1,43 @@
+	.globl	twice
+	.text
+twice:
+	pushq	%rbp
+	movq	%rsp, %rbp
+	subq	$16, %rsp
+	movl	%edi, -4(%rbp)
+	movl	-4(%rbp), %r10d
+	movl	%r10d, -8(%rbp)
+	movl	-8(%rbp), %r11d
+	imull	$2, %r11d
+	movl	%r11d, -8(%rbp)
+	movl	-8(%rbp), %eax
+	movq	%rbp, %rsp
+	popq	%rbp
+	ret
+	movl	$0, %eax
+	movq	%rbp, %rsp
+	popq	%rbp
+	ret
+
+	.globl	main
+	.text
+main:
+	pushq	%rbp
+	movq	%rsp, %rbp
+	subq	$16, %rsp
+	movl	$33, %edi
+	call	twice
+	movl	%eax, -4(%rbp)
+	movl	-4(%rbp), %edi
+	call	putchar@PLT
+	movl	%eax, -8(%rbp)
+	movl	-8(%rbp), %eax
+	movq	%rbp, %rsp
+	popq	%rbp
+	ret
+	movl	$0, %eax
+	movq	%rbp, %rsp
+	popq	%rbp
+	ret
+
+	.section .note.GNU-stack,"",@progbits
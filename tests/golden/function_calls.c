@@ -0,0 +1,11 @@
+int add(int a, int b) {
+    return a + b;
+}
+
+long many(int a, int b, int c, int d, int e, int f, int g, long h) {
+    return a + b + c + d + e + f + g + h;
+}
+
+int main(void) {
+    return add(1, 2) + (int)many(1, 2, 3, 4, 5, 6, 7, 8L);
+}
@@ -0,0 +1,113 @@
+	.globl	add
+	.text
+add:
+	pushq	%rbp
+	movq	%rsp, %rbp
+	subq	$16, %rsp
+	movl	%edi, -4(%rbp)
+	movl	%esi, -8(%rbp)
+	movl	-4(%rbp), %r10d
+	movl	%r10d, -12(%rbp)
+	movl	-8(%rbp), %r10d
+	addl	%r10d, -12(%rbp)
+	movl	-12(%rbp), %eax
+	movq	%rbp, %rsp
+	popq	%rbp
+	ret
+	movl	$0, %eax
+	movq	%rbp, %rsp
+	popq	%rbp
+	ret
+
+	.globl	many
+	.text
+many:
+	pushq	%rbp
+	movq	%rsp, %rbp
+	subq	$80, %rsp
+	movl	%edi, -4(%rbp)
+	movl	%esi, -8(%rbp)
+	movl	%edx, -12(%rbp)
+	movl	%ecx, -16(%rbp)
+	movl	%r8d, -20(%rbp)
+	movl	%r9d, -24(%rbp)
+	movl	16(%rbp), %r10d
+	movl	%r10d, -28(%rbp)
+	movq	24(%rbp), %r10
+	movq	%r10, -40(%rbp)
+	movl	-4(%rbp), %r10d
+	movl	%r10d, -44(%rbp)
+	movl	-8(%rbp), %r10d
+	addl	%r10d, -44(%rbp)
+	movl	-44(%rbp), %r10d
+	movl	%r10d, -48(%rbp)
+	movl	-12(%rbp), %r10d
+	addl	%r10d, -48(%rbp)
+	movl	-48(%rbp), %r10d
+	movl	%r10d, -52(%rbp)
+	movl	-16(%rbp), %r10d
+	addl	%r10d, -52(%rbp)
+	movl	-52(%rbp), %r10d
+	movl	%r10d, -56(%rbp)
+	movl	-20(%rbp), %r10d
+	addl	%r10d, -56(%rbp)
+	movl	-56(%rbp), %r10d
+	movl	%r10d, -60(%rbp)
+	movl	-24(%rbp), %r10d
+	addl	%r10d, -60(%rbp)
+	movl	-60(%rbp), %r10d
+	movl	%r10d, -64(%rbp)
+	movl	-28(%rbp), %r10d
+	addl	%r10d, -64(%rbp)
+	movslq	-64(%rbp), %r11
+	movq	%r11, -72(%rbp)
+	movq	-72(%rbp), %r10
+	movq	%r10, -80(%rbp)
+	movq	-40(%rbp), %r10
+	addq	%r10, -80(%rbp)
+	movq	-80(%rbp), %rax
+	movq	%rbp, %rsp
+	popq	%rbp
+	ret
+	movq	$0, %rax
+	movq	%rbp, %rsp
+	popq	%rbp
+	ret
+
+	.globl	main
+	.text
+main:
+	pushq	%rbp
+	movq	%rsp, %rbp
+	subq	$32, %rsp
+	movl	$1, %edi
+	movl	$2, %esi
+	call	add
+	movl	%eax, -4(%rbp)
+	movl	$1, %edi
+	movl	$2, %esi
+	movl	$3, %edx
+	movl	$4, %ecx
+	movl	$5, %r8d
+	movl	$6, %r9d
+	pushq	$8
+	pushq	$7
+	call	many
+	addq	$16, %rsp
+	movq	%rax, -16(%rbp)
+	movl	-16(%rbp), %r10d
+	movl	%r10d, -20(%rbp)
+	movl	-4(%rbp), %r10d
+	movl	%r10d, -24(%rbp)
+	movl	-20(%rbp), %r10d
+	addl	%r10d, -24(%rbp)
+	movl	-24(%rbp), %eax
+	movq	%rbp, %rsp
+	popq	%rbp
+	ret
+	movl	$0, %eax
+	movq	%rbp, %rsp
+	popq	%rbp
+	ret
+
+	.section .note.GNU-stack,"",@progbits
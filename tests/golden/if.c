@@ -0,0 +1,8 @@
+int main(void) {
+    int x = 3;
+    if (x > 2)
+        x = x + 1;
+    else
+        x = 0;
+    return x == 4 ? 1 : 2;
+}
@@ -0,0 +1,41 @@
+	.globl	main
+	.text
+main:
+	pushq	%rbp
+	movq	%rsp, %rbp
+	subq	$32, %rsp
+	movl	$3, -4(%rbp)
+	cmpl	$2, -4(%rbp)
+	movl	$0, -8(%rbp)
+	setg	-8(%rbp)
+	cmpl	$0, -8(%rbp)
+	je		.Ltac.label.0.if_else
+	movl	-4(%rbp), %r10d
+	movl	%r10d, -12(%rbp)
+	addl	$1, -12(%rbp)
+	movl	-12(%rbp), %r10d
+	movl	%r10d, -4(%rbp)
+	jmp		.Ltac.label.1.if_end
+.Ltac.label.0.if_else:
+	movl	$0, -4(%rbp)
+.Ltac.label.1.if_end:
+	cmpl	$4, -4(%rbp)
+	movl	$0, -16(%rbp)
+	sete	-16(%rbp)
+	cmpl	$0, -16(%rbp)
+	je		.Ltac.label.2.cond_else
+	movl	$1, -20(%rbp)
+	jmp		.Ltac.label.3.cond_end
+.Ltac.label.2.cond_else:
+	movl	$2, -20(%rbp)
+.Ltac.label.3.cond_end:
+	movl	-20(%rbp), %eax
+	movq	%rbp, %rsp
+	popq	%rbp
+	ret
+	movl	$0, %eax
+	movq	%rbp, %rsp
+	popq	%rbp
+	ret
+
+	.section .note.GNU-stack,"",@progbits
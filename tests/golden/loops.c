@@ -0,0 +1,14 @@
+int main(void) {
+    int sum = 0;
+    for (int i = 0; i < 10; i = i + 1) {
+        if (i == 5)
+            continue;
+        sum = sum + i;
+    }
+    while (sum > 20)
+        sum = sum - 3;
+    do {
+        sum = sum + 1;
+    } while (sum < 25);
+    return sum;
+}
@@ -0,0 +1,71 @@
+	.globl	main
+	.text
+main:
+	pushq	%rbp
+	movq	%rsp, %rbp
+	subq	$48, %rsp
+	movl	$0, -4(%rbp)
+	movl	$0, -8(%rbp)
+.Ltac.label.0.for_start:
+	cmpl	$10, -8(%rbp)
+	movl	$0, -12(%rbp)
+	setl	-12(%rbp)
+	cmpl	$0, -12(%rbp)
+	je		.Lsem.loop.0.for.break
+	cmpl	$5, -8(%rbp)
+	movl	$0, -16(%rbp)
+	sete	-16(%rbp)
+	cmpl	$0, -16(%rbp)
+	je		.Ltac.label.1.if_end
+	jmp		.Lsem.loop.0.for.continue
+.Ltac.label.1.if_end:
+	movl	-4(%rbp), %r10d
+	movl	%r10d, -20(%rbp)
+	movl	-8(%rbp), %r10d
+	addl	%r10d, -20(%rbp)
+	movl	-20(%rbp), %r10d
+	movl	%r10d, -4(%rbp)
+.Lsem.loop.0.for.continue:
+	movl	-8(%rbp), %r10d
+	movl	%r10d, -24(%rbp)
+	addl	$1, -24(%rbp)
+	movl	-24(%rbp), %r10d
+	movl	%r10d, -8(%rbp)
+	jmp		.Ltac.label.0.for_start
+.Lsem.loop.0.for.break:
+.Lsem.loop.1.while.continue:
+	cmpl	$20, -4(%rbp)
+	movl	$0, -28(%rbp)
+	setg	-28(%rbp)
+	cmpl	$0, -28(%rbp)
+	je		.Lsem.loop.1.while.break
+	movl	-4(%rbp), %r10d
+	movl	%r10d, -32(%rbp)
+	subl	$3, -32(%rbp)
+	movl	-32(%rbp), %r10d
+	movl	%r10d, -4(%rbp)
+	jmp		.Lsem.loop.1.while.continue
+.Lsem.loop.1.while.break:
+.Ltac.label.2.do_while_start:
+	movl	-4(%rbp), %r10d
+	movl	%r10d, -36(%rbp)
+	addl	$1, -36(%rbp)
+	movl	-36(%rbp), %r10d
+	movl	%r10d, -4(%rbp)
+.Lsem.loop.2.do.continue:
+	cmpl	$25, -4(%rbp)
+	movl	$0, -40(%rbp)
+	setl	-40(%rbp)
+	cmpl	$0, -40(%rbp)
+	jne		.Ltac.label.2.do_while_start
+.Lsem.loop.2.do.break:
+	movl	-4(%rbp), %eax
+	movq	%rbp, %rsp
+	popq	%rbp
+	ret
+	movl	$0, %eax
+	movq	%rbp, %rsp
+	popq	%rbp
+	ret
+
+	.section .note.GNU-stack,"",@progbits